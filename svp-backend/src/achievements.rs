@@ -0,0 +1,254 @@
+/*
+
+This file handles achievements, which users unlock by playing and show off as badges.
+
+The rest of the server reports events as they happen, like a pet being fed or reaching a level.
+Each user keeps a running total for every kind of event, and an achievement unlocks once the
+total for its event reaches its threshold. Achievements are loaded from a data file.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+// Used when no achievement file is given and there isn't one in the working directory
+const BUNDLED_ACHIEVEMENTS: &str = include_str!("../achievements.toml");
+
+pub const DEFAULT_ACHIEVEMENTS_PATH: &str = "achievements.toml";
+
+/// Something a user did that can count towards an achievement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameEvent {
+    PetCreated,
+    PetAdopted,
+    PetFed,
+    PetPetted,
+    PetRested,
+    PetCleaned,
+    // Petting a pet owned by someone else in one of the user's pet yards
+    YardMatePetted,
+    // A mini-game was finished
+    GamePlayed,
+    ContestWon,
+    // The value is the level the pet reached
+    LevelReached,
+    JoinedYard,
+    // The value is how many days a pet has been alive
+    PetAlive,
+    ItemBought,
+    TradeCompleted,
+}
+
+impl GameEvent {
+    /// Whether the event's total counts how often it happened,
+    /// rather than being the highest value it has been reported with
+    pub fn is_counted(&self) -> bool {
+        !matches!(self, GameEvent::LevelReached | GameEvent::PetAlive)
+    }
+}
+
+/// Running totals for each kind of event
+pub type EventProgress = BTreeMap<GameEvent, u64>;
+
+/// Adds an event to a user's totals
+pub fn record(progress: &mut EventProgress, event: GameEvent, value: u64) {
+    let total = progress.entry(event).or_insert(0);
+
+    if event.is_counted() {
+        *total = total.saturating_add(value);
+    } else {
+        *total = (*total).max(value);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Achievement {
+    pub name: String,
+    pub description: String,
+    pub event: GameEvent,
+    // The total the event needs to reach
+    pub threshold: u64,
+    // Coins for unlocking it, instead of economy.achievement_reward
+    #[serde(default)]
+    pub reward: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AchievementCatalog {
+    achievements: BTreeMap<String, Achievement>,
+}
+
+impl Default for AchievementCatalog {
+    fn default() -> Self {
+        Self::from_toml(BUNDLED_ACHIEVEMENTS).expect("bundled achievement file is invalid")
+    }
+}
+
+impl AchievementCatalog {
+    /// Loads the catalog from a file, falling back to the bundled one
+    /// if the default file doesn't exist
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if path == Path::new(DEFAULT_ACHIEVEMENTS_PATH) && !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read achievement file {}: {}", path.display(), e))?;
+
+        Self::from_toml(&contents)
+            .map_err(|e| format!("Invalid achievement file {}: {}", path.display(), e).into())
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, Box<dyn Error>> {
+        let catalog: Self = toml::from_str(contents)?;
+        catalog.validate()?;
+
+        Ok(catalog)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(id) = self.achievements.iter().find(|(_, achievement)| achievement.threshold == 0).map(|(id, _)| id) {
+            return Err(format!("achievement {} must have a threshold above 0", id));
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Achievement> {
+        self.achievements.get(id)
+    }
+
+    /// Achievements the totals are enough for that haven't been unlocked yet
+    pub fn newly_unlocked<'a>(&'a self, progress: &EventProgress, unlocked: &BTreeMap<String, u64>) -> Vec<&'a String> {
+        self.achievements
+            .iter()
+            .filter(|(id, _)| !unlocked.contains_key(*id))
+            .filter(|(_, achievement)| progress.get(&achievement.event).copied().unwrap_or(0) >= achievement.threshold)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Every achievement, with how close the user is to it and when they unlocked it
+    pub fn for_user(&self, progress: &EventProgress, unlocked: &BTreeMap<String, u64>) -> serde_json::Value {
+        let achievements: Vec<serde_json::Value> = self
+            .achievements
+            .iter()
+            .map(|(id, achievement)| {
+                serde_json::json!({
+                    "id": id,
+                    "name": achievement.name,
+                    "description": achievement.description,
+                    "progress": progress.get(&achievement.event).copied().unwrap_or(0).min(achievement.threshold),
+                    "threshold": achievement.threshold,
+                    "unlocked_at": unlocked.get(id),
+                })
+            })
+            .collect();
+
+        serde_json::json!(achievements)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::economy::LedgerReason;
+    use crate::leaderboards::Board;
+    use crate::simulation::{add_user_with_pet, mock_state};
+
+    #[test]
+    fn test_achievements_unlock_at_threshold() {
+        let catalog = AchievementCatalog::from_toml(
+            r#"
+            [fed_3]
+            name = "Snack time"
+            description = "Feed a pet 3 times"
+            event = "pet_fed"
+            threshold = 3
+
+            [level_5]
+            name = "Growing up"
+            description = "Get a pet to level 5"
+            event = "level_reached"
+            threshold = 5
+            "#,
+        )
+        .unwrap();
+
+        let mut progress = EventProgress::new();
+        let unlocked = BTreeMap::new();
+
+        record(&mut progress, GameEvent::PetFed, 1);
+        record(&mut progress, GameEvent::PetFed, 1);
+        record(&mut progress, GameEvent::LevelReached, 4);
+        record(&mut progress, GameEvent::LevelReached, 2);
+        assert!(catalog.newly_unlocked(&progress, &unlocked).is_empty());
+
+        // Levels keep the highest value, rather than adding up
+        assert_eq!(progress[&GameEvent::LevelReached], 4);
+
+        record(&mut progress, GameEvent::PetFed, 1);
+        record(&mut progress, GameEvent::LevelReached, 5);
+        assert_eq!(catalog.newly_unlocked(&progress, &unlocked), vec!["fed_3", "level_5"]);
+    }
+
+    #[test]
+    fn test_unlocked_achievements_pay_out_and_rank_users() {
+        let achievements = AchievementCatalog::from_toml(
+            r#"
+            [fed_3]
+            name = "Snack time"
+            description = "Feed a pet 3 times"
+            event = "pet_fed"
+            threshold = 3
+            reward = 50
+
+            [fed_10]
+            name = "Regular meals"
+            description = "Feed a pet 10 times"
+            event = "pet_fed"
+            threshold = 10
+            "#,
+        )
+        .unwrap();
+        let (mut state, _) = mock_state(Config { achievements, ..Config::default() });
+        let (alice, _) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+
+        state.record_event(&alice, GameEvent::PetFed, 2);
+        assert!(state.get_user_by_uuid(&alice).unwrap().get_achievements().is_empty());
+
+        state.record_event(&alice, GameEvent::PetFed, 1);
+        let user = state.get_user_by_uuid(&alice).unwrap();
+        assert_eq!(user.get_achievements().keys().collect::<Vec<_>>(), vec!["fed_3"]);
+        assert_eq!(user.get_coins(), 50);
+
+        let entry = state.get_ledger(&alice)[0];
+        assert_eq!(entry.amount, 50);
+        assert_eq!(entry.balance, 50);
+        assert_eq!(entry.reason, LedgerReason::Achievement { achievement: "fed_3".to_string() });
+
+        // Achievements without their own reward pay the default
+        state.record_event(&bob, GameEvent::PetFed, 10);
+        assert_eq!(state.get_user_by_uuid(&bob).unwrap().get_achievements().len(), 2);
+        assert_eq!(state.get_user_by_uuid(&bob).unwrap().get_coins(), 50 + state.config.economy.achievement_reward);
+
+        // Badges are only paid for once
+        state.record_event(&bob, GameEvent::PetFed, 10);
+        assert_eq!(state.get_ledger(&bob).len(), 2);
+
+        let ranking = state.leaderboards.get(Board::MostAchievements, None).unwrap();
+        let page: Vec<(usize, String, u64)> = ranking
+            .page(0, 10)
+            .into_iter()
+            .map(|(rank, uuid, score)| (rank, uuid.clone(), score))
+            .collect();
+        assert_eq!(page, vec![(1, bob, 2), (2, alice, 1)]);
+    }
+}
@@ -0,0 +1,220 @@
+/*
+
+This file handles the pet art catalog, and drawing pets in their current mood.
+
+Art is loaded from one text file per species, copied from the frontend's pet_art.
+Pictures are separated by blank lines, and each one is identified by the line it starts on,
+which is what a pet's image is set to.
+
+*/
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use crate::health::HealthState;
+use crate::needs::PetStats;
+
+// Used when no art directory is given and there isn't one in the working directory
+const BUNDLED_ART: [(&str, &str); 3] = [
+    ("cat", include_str!("../pet_art/cat.txt")),
+    ("dog", include_str!("../pet_art/dog.txt")),
+    ("fish", include_str!("../pet_art/fish.txt")),
+];
+
+pub const DEFAULT_ART_DIR: &str = "pet_art";
+
+// Every species looks the same before it hatches
+const EGG: &str = "   __\n  /  \\\n |    |\n  \\__/\n";
+
+// Stats below this put a pet in a bad mood, and happiness above HAPPY_ABOVE puts it in a good one
+const LOW_STAT: f64 = 30.0;
+const HAPPY_ABOVE: f64 = 80.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Mood {
+    Happy,
+    Content,
+    Sad,
+    Hungry,
+    Tired,
+    Dirty,
+    Sick,
+    Dead,
+}
+
+impl Mood {
+    /// Works out how a pet is feeling, with its health taking priority over its stats
+    pub fn of(health: HealthState, stats: &PetStats) -> Self {
+        match health {
+            HealthState::Dead => Mood::Dead,
+            HealthState::Sick | HealthState::Critical => Mood::Sick,
+            HealthState::Hungry => Mood::Hungry,
+            HealthState::Healthy if stats.hunger < LOW_STAT => Mood::Hungry,
+            HealthState::Healthy if stats.energy < LOW_STAT => Mood::Tired,
+            HealthState::Healthy if stats.hygiene < LOW_STAT => Mood::Dirty,
+            HealthState::Healthy if stats.happiness < LOW_STAT => Mood::Sad,
+            HealthState::Healthy if stats.happiness > HAPPY_ABOVE => Mood::Happy,
+            HealthState::Healthy => Mood::Content,
+        }
+    }
+
+    /// The line drawn above the pet to show its mood
+    fn overlay(&self) -> Option<&'static str> {
+        match self {
+            Mood::Happy => Some("  <3  <3"),
+            Mood::Content => None,
+            Mood::Sad => Some("  ;_;"),
+            Mood::Hungry => Some("  ...food?"),
+            Mood::Tired => Some("  z z Z"),
+            Mood::Dirty => Some("  ~ * ~ *"),
+            Mood::Sick => Some("  +  ~ ~ ~"),
+            Mood::Dead => Some("  R.I.P."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArtCatalog {
+    // Pictures by species, then by the line they start on
+    art: BTreeMap<String, BTreeMap<u64, String>>,
+}
+
+impl Default for ArtCatalog {
+    fn default() -> Self {
+        Self::bundled()
+    }
+}
+
+impl ArtCatalog {
+    fn empty() -> Self {
+        Self { art: BTreeMap::new() }
+    }
+
+    /// Loads every `<species>.txt` file in the directory, falling back to the bundled art
+    /// if the default directory doesn't exist
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        if dir == Path::new(DEFAULT_ART_DIR) && !dir.exists() {
+            return Ok(Self::bundled());
+        }
+
+        let mut catalog = Self::empty();
+
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| format!("Could not read art directory {}: {}", dir.display(), e))?;
+
+        for entry in entries {
+            let path = entry?.path();
+
+            if path.extension().is_none_or(|extension| extension != "txt") {
+                continue;
+            }
+
+            let species = path
+                .file_stem()
+                .ok_or("art file has no name")?
+                .to_string_lossy()
+                .to_string();
+            let contents = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Could not read art file {}: {}", path.display(), e))?;
+
+            catalog.add_sheet(species, &contents);
+        }
+
+        Ok(catalog)
+    }
+
+    pub fn bundled() -> Self {
+        let mut catalog = Self::empty();
+
+        for (species, contents) in BUNDLED_ART {
+            catalog.add_sheet(species.to_string(), contents);
+        }
+
+        catalog
+    }
+
+    /// Splits a sheet of art into pictures, the same way the frontend does.
+    /// Only pictures that come after a blank line count.
+    fn add_sheet(&mut self, species: String, contents: &str) {
+        let lines: Vec<&str> = contents.lines().collect();
+        let mut pictures = BTreeMap::new();
+
+        for (index, line) in lines.iter().enumerate() {
+            if !line.is_empty() {
+                continue;
+            }
+
+            let start = index + 1;
+            let picture: Vec<&str> = lines[start.min(lines.len())..]
+                .iter()
+                .take_while(|line| !line.is_empty())
+                .copied()
+                .collect();
+
+            if !picture.is_empty() {
+                pictures.insert(start as u64, picture.join("\n") + "\n");
+            }
+        }
+
+        self.art.insert(species, pictures);
+    }
+
+    pub fn get(&self, species: &str, index: u64) -> Option<&String> {
+        self.art.get(species)?.get(&index)
+    }
+
+    pub fn has_species(&self, species: &str) -> bool {
+        self.art.contains_key(species)
+    }
+
+    /// Draws the picture with the mood shown above it
+    pub fn render(&self, species: &str, index: u64, mood: Mood) -> Option<String> {
+        Some(draw(self.get(species, index)?, mood))
+    }
+}
+
+/// Draws an egg with the mood shown above it
+pub fn render_egg(mood: Mood) -> String {
+    draw(EGG, mood)
+}
+
+fn draw(picture: &str, mood: Mood) -> String {
+    match mood.overlay() {
+        Some(overlay) => format!("{}\n{}", overlay, picture),
+        None => picture.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sheets_split_like_the_frontend() {
+        let catalog = ArtCatalog::bundled();
+
+        // Pictures are found by the line they start on, and nothing else
+        assert!(catalog.get("cat", 0).is_none());
+        assert_eq!(catalog.get("cat", 1).unwrap(), "|\\---/|\n| o_o |\n \\_^_/\n");
+        assert_eq!(catalog.get("cat", 5).unwrap(), " /\\_/\\\n( o.o )\n > ^ <\n");
+        assert!(catalog.get("cat", 2).is_none());
+    }
+
+    #[test]
+    fn test_moods() {
+        let stats = PetStats::default();
+        let tired = PetStats { energy: 10.0, ..stats };
+
+        assert_eq!(Mood::of(HealthState::Healthy, &stats), Mood::Happy);
+        assert_eq!(Mood::of(HealthState::Healthy, &tired), Mood::Tired);
+        assert_eq!(Mood::of(HealthState::Critical, &tired), Mood::Sick);
+
+        let catalog = ArtCatalog::bundled();
+        let drawn = catalog.render("cat", 1, Mood::Dead).unwrap();
+        assert!(drawn.starts_with("  R.I.P.\n|\\---/|"));
+    }
+}
@@ -7,8 +7,9 @@ This file handling login and signup of users.
 
 
 use crate::structs::User;
+use crate::economy::LedgerReason;
 
-use crate::APP_STATE;
+use crate::lock_app_state;
 use axum::http::{self, Response, StatusCode};
 use aide::axum::IntoApiResponse;
 
@@ -17,13 +18,13 @@ use aide::axum::IntoApiResponse;
 
 
 pub async fn verify_token(token: &str, uuid: &str) -> bool {
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     if app_state.tokens.contains_key(token) {
         let user_token = app_state.tokens.get(token).unwrap();
 
         // Check if token is valid
-        user_token.is_valid() && user_token.get_uuid() == uuid
+        user_token.is_valid(app_state.now()) && user_token.get_uuid() == uuid
     } else {
         false
     }
@@ -43,7 +44,7 @@ pub async fn verify_token_header(headers: &http::HeaderMap, uuid: &str) -> bool
 
 
 pub async fn signup(username: String, email: String, password: String) -> impl IntoApiResponse  {
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     if app_state.get_user_by_username(&username).is_some() {
         return Response::builder()
@@ -52,13 +53,21 @@ pub async fn signup(username: String, email: String, password: String) -> impl I
             .unwrap();
     }
 
-    let user = User::new(username.to_string(), email.to_string(), password.to_string());
+    let mut user = User::new(username.to_string(), email.to_string(), password.to_string(), app_state.now());
+
+    for (item_id, count) in app_state.config.inventory.starter_items.iter() {
+        user.add_items(item_id.clone(), *count);
+    }
 
     drop(app_state);
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
-    app_state.users.insert(user.get_uuid(), user);
+    let user_uuid = user.get_uuid();
+    app_state.users.insert(user_uuid.clone(), user);
+
+    let starting_coins = app_state.config.economy.starting_coins;
+    app_state.credit_coins(&user_uuid, starting_coins, LedgerReason::StartingCoins);
 
     Response::builder()
         .status(StatusCode::OK)
@@ -67,7 +76,7 @@ pub async fn signup(username: String, email: String, password: String) -> impl I
 }
 
 pub async fn logout(token: String) -> impl IntoApiResponse  {
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     if app_state.tokens.contains_key(&token) {
         app_state.tokens.remove(&token);
@@ -84,6 +93,7 @@ pub async fn logout(token: String) -> impl IntoApiResponse  {
         .unwrap()
 }
 
+#[allow(dead_code)]
 pub async fn verify(token: String, uuid: String) -> impl IntoApiResponse  {
     let valid = verify_token(&token, &uuid).await;
 
@@ -101,11 +111,14 @@ pub async fn verify(token: String, uuid: String) -> impl IntoApiResponse  {
 }
 
 pub async fn refresh(token: String, _uuid: String) -> impl IntoApiResponse  {
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
+
+    let now = app_state.now();
+    let lifetime_ms = app_state.config.auth.token_lifetime_ms();
 
     if app_state.tokens.contains_key(&token) {
         if let Some(user_token) = app_state.tokens.get_mut(&token) {
-            user_token.refresh();
+            user_token.refresh(now, lifetime_ms);
 
             return Response::builder()
                 .status(StatusCode::OK)
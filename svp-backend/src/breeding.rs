@@ -0,0 +1,409 @@
+/*
+
+This file handles breeding two pets to get an egg.
+
+A pet's genes decide its colour and nudge how quickly its stats decay.
+Eggs get a mix of their parents' genes, with a little random mutation.
+Pets with different owners can only breed if both are in the same pet yard
+and the partner's owner agrees, which is handled with breeding requests.
+
+*/
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::needs::DecayRates;
+
+const MS_PER_HOUR: u64 = 1000 * 60 * 60;
+
+// Genes can't push a decay rate further than this from normal
+const MIN_GENE_RATE: f64 = 0.5;
+const MAX_GENE_RATE: f64 = 1.5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Genes {
+    pub color: String,
+    // Multiplies the species' decay rates
+    pub decay_rates: DecayRates,
+}
+
+impl Default for Genes {
+    /// Genes for pets saved before breeding existed
+    fn default() -> Self {
+        Self {
+            color: "plain".to_string(),
+            decay_rates: DecayRates::default(),
+        }
+    }
+}
+
+impl Genes {
+    /// Genes for a pet created from scratch
+    pub fn random(config: &BreedingConfig) -> Self {
+        let mut rng = rand::thread_rng();
+
+        Self {
+            color: config.colors.choose(&mut rng).cloned().unwrap_or_default(),
+            decay_rates: DecayRates::default(),
+        }
+    }
+
+    /// Mixes two parents' genes, with some mutation
+    pub fn inherit<R: Rng>(a: &Genes, b: &Genes, config: &BreedingConfig, rng: &mut R) -> Self {
+        let color = if rng.gen_bool(config.mutation_chance) {
+            config.colors.choose(rng).cloned().unwrap_or_default()
+        } else if rng.gen_bool(0.5) {
+            a.color.clone()
+        } else {
+            b.color.clone()
+        };
+
+        let mut mix = |a: f64, b: f64| {
+            let mutation = if config.mutation_amount > 0.0 {
+                rng.gen_range(-config.mutation_amount..=config.mutation_amount)
+            } else {
+                0.0
+            };
+
+            ((a + b) / 2.0 + mutation).clamp(MIN_GENE_RATE, MAX_GENE_RATE)
+        };
+
+        let decay_rates = DecayRates {
+            hunger: mix(a.decay_rates.hunger, b.decay_rates.hunger),
+            happiness: mix(a.decay_rates.happiness, b.decay_rates.happiness),
+            energy: mix(a.decay_rates.energy, b.decay_rates.energy),
+            hygiene: mix(a.decay_rates.hygiene, b.decay_rates.hygiene),
+        };
+
+        Self { color, decay_rates }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BreedingConfig {
+    // How long a pet has to wait between breedings
+    pub cooldown_hours: u64,
+    // How long a breeding request waits for the partner's owner to answer
+    pub request_expiry_hours: u64,
+    // Most breeding requests a user can have waiting on others at once
+    pub max_open_requests: usize,
+    // Chance of an egg getting a random colour instead of a parent's
+    pub mutation_chance: f64,
+    // Most an egg's decay rates can differ from the average of its parents'
+    pub mutation_amount: f64,
+    pub colors: Vec<String>,
+}
+
+impl Default for BreedingConfig {
+    fn default() -> Self {
+        Self {
+            cooldown_hours: 72,
+            request_expiry_hours: 24,
+            max_open_requests: 10,
+            mutation_chance: 0.1,
+            mutation_amount: 0.05,
+            colors: ["black", "white", "grey", "brown", "ginger", "golden", "spotted", "striped"]
+                .iter()
+                .map(|color| color.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl BreedingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.request_expiry_hours == 0 {
+            return Err("breeding.request_expiry_hours must be greater than 0".to_string());
+        }
+
+        if self.max_open_requests == 0 {
+            return Err("breeding.max_open_requests must be greater than 0".to_string());
+        }
+
+        if !(0.0..=1.0).contains(&self.mutation_chance) {
+            return Err("breeding.mutation_chance must be between 0 and 1".to_string());
+        }
+
+        if !(0.0..=MAX_GENE_RATE - MIN_GENE_RATE).contains(&self.mutation_amount) {
+            return Err("breeding.mutation_amount must be between 0 and 1".to_string());
+        }
+
+        if self.colors.is_empty() {
+            return Err("breeding.colors must not be empty".to_string());
+        }
+
+        Ok(())
+    }
+
+    pub fn cooldown_ms(&self) -> u64 {
+        self.cooldown_hours * MS_PER_HOUR
+    }
+}
+
+/// Why two pets couldn't breed
+#[derive(Debug, Clone, PartialEq)]
+pub enum BreedError {
+    NotFound,
+    NotOwner,
+    SamePet,
+    Dead,
+    NotAdult,
+    DifferentSpecies,
+    NotInSameYard,
+    Cooldown { available_at: u64 },
+    TooManyRequests,
+    Expired,
+}
+
+impl std::fmt::Display for BreedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BreedError::NotFound => write!(f, "Pet or breeding request not found"),
+            BreedError::NotOwner => write!(f, "Pet belongs to another user"),
+            BreedError::SamePet => write!(f, "A pet can't breed with itself"),
+            BreedError::Dead => write!(f, "Pet has died"),
+            BreedError::NotAdult => write!(f, "Only adult pets can breed"),
+            BreedError::DifferentSpecies => write!(f, "Pets must be the same species to breed"),
+            BreedError::NotInSameYard => write!(f, "Pets with different owners must be in the same pet yard"),
+            BreedError::Cooldown { available_at } => write!(f, "Pet can't breed again until {}", available_at),
+            BreedError::TooManyRequests => write!(f, "Too many breeding requests waiting for an answer"),
+            BreedError::Expired => write!(f, "Breeding request has expired"),
+        }
+    }
+}
+
+/// A request to breed with a pet owned by someone else, waiting for them to agree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BreedingRequest {
+    pub uuid: String,
+    // The user asking, who gets the egg
+    pub from_user: String,
+    pub pet: String,
+    // The user whose pet is asked for
+    pub to_user: String,
+    pub partner: String,
+    // Name for the egg
+    pub name: Option<String>,
+    pub created: u64,
+    pub expires: u64,
+}
+
+impl BreedingRequest {
+    pub fn new(from_user: String, pet: String, to_user: String, partner: String, name: Option<String>, now: u64, config: &BreedingConfig) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            from_user,
+            pet,
+            to_user,
+            partner,
+            name,
+            created: now,
+            expires: now + config.request_expiry_hours * MS_PER_HOUR,
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.expires
+    }
+
+    pub fn for_user(&self) -> serde_json::Value {
+        serde_json::json!(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::growth::GrowthStage;
+    use crate::simulation::{add_user_with_pet, mock_state};
+    use crate::structs::{AppState, BreedOutcome, PetYard};
+    use crate::Pet;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Levels the pet up until it is an adult
+    fn grow_up(state: &mut AppState, pet_uuid: &str) {
+        let now = state.now();
+        let mut pet = state.get_pet_by_uuid(pet_uuid).unwrap().clone();
+
+        while pet.get_stage() != GrowthStage::Adult {
+            pet.add_experience(10, now, &state.config);
+        }
+
+        state.update_pet(pet);
+    }
+
+    /// Gives the user another adult cat, returning its UUID
+    fn add_adult_cat(state: &mut AppState, user_uuid: &str) -> String {
+        let now = state.now();
+        let mut pet = Pet::new("Kit".to_string(), "cat".to_string(), 1, None, now);
+        pet.set_stats(state.config.species.traits("cat").base_stats, now);
+        let pet_uuid = pet.get_uuid();
+
+        let mut user = state.get_user_by_uuid(user_uuid).unwrap().clone();
+        user.add_pet(pet_uuid.clone());
+        state.update_user(user);
+        state.update_pet(pet);
+
+        grow_up(state, &pet_uuid);
+        pet_uuid
+    }
+
+    /// Puts both pets in a pet yard owned by the first user, with the second as a member
+    fn share_pet_yard(state: &mut AppState, (owner, pet): (&str, &str), (member, partner): (&str, &str)) {
+        let mut pet_yard = PetYard::new("Park".to_string(), owner.to_string(), 1);
+        pet_yard.add_member(member.to_string());
+        pet_yard.add_pet(pet.to_string());
+        pet_yard.add_pet(partner.to_string());
+        state.update_pet_yard(pet_yard);
+    }
+
+    fn request(outcome: Result<BreedOutcome, BreedError>) -> BreedingRequest {
+        match outcome {
+            Ok(BreedOutcome::Requested(request)) => request,
+            _ => panic!("expected a breeding request"),
+        }
+    }
+
+    #[test]
+    fn test_inherited_genes_stay_near_parents() {
+        let config = BreedingConfig::default();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let a = Genes {
+            color: "black".to_string(),
+            decay_rates: DecayRates { hunger: 0.8, ..DecayRates::default() },
+        };
+        let b = Genes {
+            color: "white".to_string(),
+            decay_rates: DecayRates { hunger: 1.2, ..DecayRates::default() },
+        };
+
+        for _ in 0..100 {
+            let child = Genes::inherit(&a, &b, &config, &mut rng);
+
+            assert!((child.decay_rates.hunger - 1.0).abs() <= config.mutation_amount + 1e-9);
+        }
+
+        // Without mutation, colours always come from a parent
+        let config = BreedingConfig {
+            mutation_chance: 0.0,
+            mutation_amount: 0.0,
+            ..config
+        };
+        let child = Genes::inherit(&a, &b, &config, &mut rng);
+        assert!(child.color == "black" || child.color == "white");
+        assert_eq!(child.decay_rates.hunger, 1.0);
+    }
+
+    #[test]
+    fn test_own_pets_breed_straight_away_then_rest() {
+        let (mut state, clock) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+
+        let kit = add_adult_cat(&mut state, &alice);
+        assert_eq!(state.breed_pets(&alice, &cat, &kit, None).unwrap_err(), BreedError::NotAdult);
+        assert_eq!(state.breed_pets(&alice, &kit, &kit, None).unwrap_err(), BreedError::SamePet);
+
+        grow_up(&mut state, &cat);
+        let egg = match state.breed_pets(&alice, &cat, &kit, Some("Egg".to_string())).unwrap() {
+            BreedOutcome::Egg(egg) => egg,
+            BreedOutcome::Requested(_) => panic!("alice owns both pets"),
+        };
+
+        assert_eq!(egg.get_parents(), vec![cat.clone(), kit.clone()]);
+        assert_eq!(egg.get_stage(), GrowthStage::Egg);
+        assert_eq!(state.get_pet_owner(&egg.get_uuid()), Some(alice.clone()));
+        assert!(state.breeding_requests.is_empty());
+
+        // Both parents need to rest before breeding again
+        let available_at = state.now() + state.config.breeding.cooldown_ms();
+        assert_eq!(
+            state.breed_pets(&alice, &cat, &kit, None).unwrap_err(),
+            BreedError::Cooldown { available_at }
+        );
+
+        clock.set(available_at);
+        assert!(state.breed_pets(&alice, &cat, &kit, None).is_ok());
+    }
+
+    #[test]
+    fn test_other_owners_have_to_agree() {
+        let (mut state, _) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, tom) = add_user_with_pet(&mut state, "bob");
+        grow_up(&mut state, &cat);
+        grow_up(&mut state, &tom);
+
+        assert_eq!(state.breed_pets(&alice, &cat, &tom, None).unwrap_err(), BreedError::NotInSameYard);
+        assert_eq!(state.breed_pets(&alice, &tom, &cat, None).unwrap_err(), BreedError::NotOwner);
+
+        share_pet_yard(&mut state, (&alice, &cat), (&bob, &tom));
+
+        // Bob turns the first request down
+        let declined = request(state.breed_pets(&alice, &cat, &tom, None));
+        assert_eq!(state.get_breeding_requests(&bob).len(), 1);
+        state.decline_breeding_request(&bob, &declined.uuid).unwrap();
+        assert_eq!(state.accept_breeding_request(&bob, &declined.uuid).unwrap_err(), BreedError::NotFound);
+
+        // Only bob can agree to the second, and the egg goes to alice
+        let accepted = request(state.breed_pets(&alice, &cat, &tom, Some("Egg".to_string())));
+        assert_eq!(state.accept_breeding_request(&alice, &accepted.uuid).unwrap_err(), BreedError::NotFound);
+
+        let egg = state.accept_breeding_request(&bob, &accepted.uuid).unwrap();
+        assert_eq!(egg.get_name(), "Egg");
+        assert_eq!(egg.get_parents(), vec![cat.clone(), tom.clone()]);
+        assert_eq!(state.get_pet_owner(&egg.get_uuid()), Some(alice.clone()));
+        assert!(state.breeding_requests.is_empty());
+    }
+
+    #[test]
+    fn test_pets_only_breed_within_their_species() {
+        let (mut state, _) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, rex) = add_user_with_pet(&mut state, "bob");
+
+        let mut dog = state.get_pet_by_uuid(&rex).unwrap().clone();
+        dog.set_species("dog".to_string()).unwrap();
+        state.update_pet(dog);
+
+        grow_up(&mut state, &cat);
+        grow_up(&mut state, &rex);
+        share_pet_yard(&mut state, (&alice, &cat), (&bob, &rex));
+
+        assert_eq!(state.breed_pets(&alice, &cat, &rex, None).unwrap_err(), BreedError::DifferentSpecies);
+    }
+
+    #[test]
+    fn test_breeding_requests_are_capped_and_cleared_up() {
+        let mut config = Config::default();
+        config.breeding.max_open_requests = 1;
+        let (mut state, clock) = mock_state(config);
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, tom) = add_user_with_pet(&mut state, "bob");
+        grow_up(&mut state, &cat);
+        grow_up(&mut state, &tom);
+        share_pet_yard(&mut state, (&alice, &cat), (&bob, &tom));
+
+        let first = request(state.breed_pets(&alice, &cat, &tom, None));
+        assert_eq!(state.breed_pets(&alice, &cat, &tom, None).unwrap_err(), BreedError::TooManyRequests);
+
+        // Unanswered requests expire and make room for new ones
+        clock.set(first.expires + 1);
+        assert_eq!(state.accept_breeding_request(&bob, &first.uuid).unwrap_err(), BreedError::Expired);
+        request(state.breed_pets(&alice, &cat, &tom, None));
+
+        clock.advance(state.config.breeding.request_expiry_hours * MS_PER_HOUR + 1);
+        state.expire_breeding_requests();
+        assert!(state.breeding_requests.is_empty());
+
+        // And go when either pet does
+        request(state.breed_pets(&alice, &cat, &tom, None));
+        state.delete_pet(&tom);
+        assert!(state.breeding_requests.is_empty());
+    }
+}
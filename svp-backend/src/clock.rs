@@ -0,0 +1,104 @@
+/*
+
+This file handles where the server gets the time from.
+
+Everything that depends on the time, like stats decaying, pets dying and tokens expiring,
+reads it from the state's clock rather than straight from the system. The server uses the
+system clock, and tests use a mock clock they can move on, so weeks can pass in a moment.
+
+*/
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+pub trait Clock: std::fmt::Debug + Send + Sync {
+    /// Milliseconds since the Unix epoch
+    fn now(&self) -> u64;
+}
+
+/// The real time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        chrono::Utc::now().timestamp_millis() as u64
+    }
+}
+
+/// A clock that only moves when it's told to, for tests
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicU64,
+}
+
+#[allow(dead_code)]
+impl MockClock {
+    pub fn new(now: u64) -> Self {
+        Self { now: AtomicU64::new(now) }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, by_ms: u64) {
+        self.now.fetch_add(by_ms, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// The clock the state reads the time from. Callers can keep their own handle to a
+/// mock clock to move it on after giving it to the state.
+#[derive(Debug, Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self(clock)
+    }
+
+    pub fn now(&self) -> u64 {
+        self.0.now()
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self(Arc::new(SystemClock))
+    }
+}
+
+// The clock isn't part of what the state holds, so it never makes two states differ
+impl PartialEq for SharedClock {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS_PER_DAY: u64 = 1000 * 60 * 60 * 24;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_told() {
+        let mock = Arc::new(MockClock::new(1000));
+        let clock = SharedClock::new(mock.clone());
+
+        assert_eq!(clock.now(), 1000);
+        mock.advance(MS_PER_DAY);
+        assert_eq!(clock.now(), 1000 + MS_PER_DAY);
+        mock.set(5);
+        assert_eq!(clock.now(), 5);
+
+        assert!(SystemClock.now() > 0);
+    }
+}
@@ -0,0 +1,547 @@
+/*
+
+This file handles loading and validating the server configuration.
+
+Settings are layered: built-in defaults, then the TOML config file,
+then environment variables, then command line flags.
+
+*/
+
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+pub use crate::achievements::AchievementCatalog;
+pub use crate::art::ArtCatalog;
+pub use crate::breeding::BreedingConfig;
+pub use crate::contests::ContestConfig;
+pub use crate::cooldowns::CooldownConfig;
+pub use crate::economy::EconomyConfig;
+pub use crate::graveyard::GraveyardConfig;
+pub use crate::health::HealthConfig;
+pub use crate::items::{InventoryConfig, ItemCatalog};
+pub use crate::leaderboards::LeaderboardConfig;
+pub use crate::leveling::LevelingConfig;
+pub use crate::minigames::MinigameConfig;
+pub use crate::quests::QuestConfig;
+pub use crate::species::SpeciesRegistry;
+pub use crate::trading::TradeConfig;
+pub use crate::transfers::TransferConfig;
+pub use crate::vacations::VacationConfig;
+pub use crate::needs::NeedsConfig;
+
+const DEFAULT_CONFIG_PATH: &str = "svp.toml";
+
+/// Command line flags. Each flag can also be set through the listed environment variable.
+#[derive(Parser, Debug, Default)]
+#[command(version, about = "Secure Virtual Pets backend server")]
+pub struct Cli {
+    /// Path to the TOML config file
+    #[arg(short, long, env = "SVP_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address to bind the server to
+    #[arg(long, env = "SVP_BIND_ADDRESS")]
+    pub bind_address: Option<IpAddr>,
+
+    /// Port to listen on
+    #[arg(short, long, env = "SVP_PORT")]
+    pub port: Option<u16>,
+
+    /// Where the app state is saved
+    #[arg(long, env = "SVP_STATE_FILE")]
+    pub state_file: Option<PathBuf>,
+
+    /// Where logs are written
+    #[arg(long, env = "SVP_LOG_FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Log level or filter directives, overridden by RUST_LOG
+    #[arg(long, env = "SVP_LOG_LEVEL")]
+    pub log_level: Option<String>,
+
+    /// Log output format
+    #[arg(long, env = "SVP_LOG_FORMAT", value_enum)]
+    pub log_format: Option<LogFormat>,
+
+    /// Export traces to this OTLP/HTTP endpoint
+    #[arg(long, env = "SVP_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub rate_limit: RateLimitConfig,
+    pub auth: AuthConfig,
+    pub pets: PetConfig,
+    pub leveling: LevelingConfig,
+    pub cooldowns: CooldownConfig,
+    pub needs: NeedsConfig,
+    pub health: HealthConfig,
+    pub graveyard: GraveyardConfig,
+    pub breeding: BreedingConfig,
+    pub transfers: TransferConfig,
+    pub inventory: InventoryConfig,
+    pub economy: EconomyConfig,
+    pub trading: TradeConfig,
+    pub minigames: MinigameConfig,
+    pub contests: ContestConfig,
+    pub leaderboards: LeaderboardConfig,
+    pub quests: QuestConfig,
+    pub vacations: VacationConfig,
+    pub paths: PathsConfig,
+    pub logging: LoggingConfig,
+    pub telemetry: TelemetryConfig,
+    // Loaded from paths.species_file rather than the config file
+    #[serde(skip)]
+    pub species: SpeciesRegistry,
+    // Loaded from paths.art_dir
+    #[serde(skip)]
+    pub art: ArtCatalog,
+    // Loaded from paths.items_file
+    #[serde(skip)]
+    pub items: ItemCatalog,
+    // Loaded from paths.achievements_file
+    #[serde(skip)]
+    pub achievements: AchievementCatalog,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    // How long in-flight requests get to finish after a shutdown signal
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        // Only listen on localhost when running a debug build
+        let bind_address = if cfg!(debug_assertions) {
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        } else {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        };
+
+        Self {
+            bind_address,
+            port: 3000,
+            shutdown_timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    // Number of requests allowed per period
+    pub requests: u64,
+    pub period_secs: u64,
+    // Number of requests that can wait for the rate limiter
+    pub buffer_size: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests: 100,
+            period_secs: 1,
+            buffer_size: 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    pub token_lifetime_secs: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            token_lifetime_secs: 60 * 60 * 24,
+        }
+    }
+}
+
+// A year, well short of where the lifetime in milliseconds would overflow
+const MAX_TOKEN_LIFETIME_SECS: u64 = 60 * 60 * 24 * 365;
+
+impl AuthConfig {
+    pub fn token_lifetime_ms(&self) -> u64 {
+        self.token_lifetime_secs * 1000
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.token_lifetime_secs == 0 || self.token_lifetime_secs > MAX_TOKEN_LIFETIME_SECS {
+            return Err(format!("auth.token_lifetime_secs must be between 1 and {}", MAX_TOKEN_LIFETIME_SECS));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PetConfig {
+    // How often to check which pets should die
+    pub kill_check_interval_secs: u64,
+}
+
+impl Default for PetConfig {
+    fn default() -> Self {
+        Self {
+            kill_check_interval_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    pub state_file: PathBuf,
+    pub log_file: PathBuf,
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+    pub species_file: PathBuf,
+    pub art_dir: PathBuf,
+    pub items_file: PathBuf,
+    pub achievements_file: PathBuf,
+}
+
+impl Default for PathsConfig {
+    fn default() -> Self {
+        Self {
+            state_file: "state.json".into(),
+            log_file: "svp.log".into(),
+            cert_file: "cert.pem".into(),
+            key_file: "key.pem".into(),
+            species_file: crate::species::DEFAULT_SPECIES_PATH.into(),
+            art_dir: crate::art::DEFAULT_ART_DIR.into(),
+            items_file: crate::items::DEFAULT_ITEMS_PATH.into(),
+            achievements_file: crate::achievements::DEFAULT_ACHIEVEMENTS_PATH.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Compact,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Never,
+    Hourly,
+    Daily,
+    // Rotate once the file reaches max_file_size_mb
+    Size,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    // Level or filter directives, e.g. "info" or "svp_backend=debug,tower_http=info"
+    pub level: String,
+    pub format: LogFormat,
+    pub rotation: LogRotation,
+    pub max_file_size_mb: u64,
+    // Number of rotated log files to keep
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            format: LogFormat::Compact,
+            rotation: LogRotation::Never,
+            max_file_size_mb: 10,
+            max_files: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    pub otlp_enabled: bool,
+    // OTLP/HTTP traces endpoint of the collector
+    pub otlp_endpoint: String,
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_enabled: false,
+            otlp_endpoint: "http://localhost:4318/v1/traces".to_string(),
+            service_name: "svp-backend".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config from the command line, environment and config file, then validates it.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Self::from_cli(Cli::parse())
+    }
+
+    pub fn from_cli(cli: Cli) -> Result<Self, Box<dyn Error>> {
+        // A missing config file is only an error if one was asked for explicitly
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None if std::path::Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(DEFAULT_CONFIG_PATH)?
+            }
+            None => Self::default(),
+        };
+
+        config.apply_cli(cli);
+        config.species = SpeciesRegistry::load(&config.paths.species_file)?;
+        config.art = ArtCatalog::load(&config.paths.art_dir)?;
+        config.items = ItemCatalog::load(&config.paths.items_file)?;
+        config.achievements = AchievementCatalog::load(&config.paths.achievements_file)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read config file {}: {}", path.display(), e))?;
+
+        Self::from_toml(&contents)
+            .map_err(|e| format!("Invalid config file {}: {}", path.display(), e).into())
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    fn apply_cli(&mut self, cli: Cli) {
+        if let Some(bind_address) = cli.bind_address {
+            self.server.bind_address = bind_address;
+        }
+
+        if let Some(port) = cli.port {
+            self.server.port = port;
+        }
+
+        if let Some(state_file) = cli.state_file {
+            self.paths.state_file = state_file;
+        }
+
+        if let Some(log_file) = cli.log_file {
+            self.paths.log_file = log_file;
+        }
+
+        if let Some(log_level) = cli.log_level {
+            self.logging.level = log_level;
+        }
+
+        if let Some(log_format) = cli.log_format {
+            self.logging.format = log_format;
+        }
+
+        // Giving an endpoint on the command line turns exporting on
+        if let Some(otlp_endpoint) = cli.otlp_endpoint {
+            self.telemetry.otlp_enabled = true;
+            self.telemetry.otlp_endpoint = otlp_endpoint;
+        }
+    }
+
+    /// Checks that the settings make sense together
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.rate_limit.requests == 0 || self.rate_limit.period_secs == 0 {
+            return Err("rate_limit.requests and rate_limit.period_secs must be greater than 0".into());
+        }
+
+        if self.rate_limit.buffer_size == 0 {
+            return Err("rate_limit.buffer_size must be greater than 0".into());
+        }
+
+        self.auth.validate()?;
+
+        if self.pets.kill_check_interval_secs == 0 {
+            return Err("pets.kill_check_interval_secs must be greater than 0".into());
+        }
+
+        self.needs.validate()?;
+        self.health.validate()?;
+        self.leveling.validate()?;
+        self.graveyard.validate()?;
+        self.breeding.validate()?;
+        self.transfers.validate()?;
+        self.species.validate_art(&self.art)?;
+        self.species.validate_items(&self.items)?;
+        self.inventory.validate(&self.items)?;
+        self.economy.validate()?;
+        self.trading.validate()?;
+        self.minigames.validate()?;
+        self.contests.validate()?;
+        self.leaderboards.validate()?;
+        self.quests.validate(&self.items)?;
+        self.vacations.validate()?;
+
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.logging.level) {
+            return Err(format!("logging.level is not a valid filter: {}", e).into());
+        }
+
+        if self.logging.max_files == 0 {
+            return Err("logging.max_files must be greater than 0".into());
+        }
+
+        if self.logging.rotation == LogRotation::Size && self.logging.max_file_size_mb == 0 {
+            return Err("logging.max_file_size_mb must be greater than 0 for size rotation".into());
+        }
+
+        if self.telemetry.otlp_enabled
+            && !(self.telemetry.otlp_endpoint.starts_with("http://")
+                || self.telemetry.otlp_endpoint.starts_with("https://"))
+        {
+            return Err("telemetry.otlp_endpoint must be an http:// or https:// URL".into());
+        }
+
+        for (name, path) in [
+            ("state_file", &self.paths.state_file),
+            ("log_file", &self.paths.log_file),
+            ("cert_file", &self.paths.cert_file),
+            ("key_file", &self.paths.key_file),
+        ] {
+            if path.as_os_str().is_empty() {
+                return Err(format!("paths.{} must not be empty", name).into());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.server.bind_address, self.server.port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_are_valid() {
+        let config = Config::default();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.server.port, 3000);
+        assert_eq!(config.auth.token_lifetime_ms(), 1000 * 60 * 60 * 24);
+    }
+
+    #[test]
+    fn test_partial_toml_keeps_defaults() {
+        let config = Config::from_toml(
+            r#"
+            [server]
+            port = 8443
+
+            [leveling.action_experience]
+            feed = 2
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.server.port, 8443);
+        assert_eq!(config.leveling.action_experience.feed, 2);
+        assert_eq!(config.leveling.action_experience.pet, 5);
+        assert_eq!(config.paths, PathsConfig::default());
+    }
+
+    #[test]
+    fn test_example_config_is_valid() {
+        let config = Config::from_file("svp.example.toml").unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(Config::from_toml("[server]\nprot = 1").is_err());
+    }
+
+    #[test]
+    fn test_cli_overrides_file() {
+        // Data files are found from the crate, so the test doesn't depend on where it's run.
+        // There's no art directory in the repo, so the bundled art is used either way.
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let path = std::env::temp_dir().join(format!("svp-config-test-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            format!(
+                r#"
+                [server]
+                port = 5000
+
+                [paths]
+                state_file = "from-file.json"
+                log_file = "from-file.log"
+                species_file = {:?}
+                items_file = {:?}
+                achievements_file = {:?}
+                "#,
+                root.join(crate::species::DEFAULT_SPECIES_PATH),
+                root.join(crate::items::DEFAULT_ITEMS_PATH),
+                root.join(crate::achievements::DEFAULT_ACHIEVEMENTS_PATH),
+            ),
+        )
+        .unwrap();
+
+        let cli = Cli {
+            config: Some(path.clone()),
+            port: Some(4000),
+            state_file: Some("other.json".into()),
+            ..Default::default()
+        };
+
+        let config = Config::from_cli(cli);
+        std::fs::remove_file(&path).unwrap();
+        let config = config.unwrap();
+
+        // The command line wins where both set a value, and the file is kept elsewhere
+        assert_eq!(config.server.port, 4000);
+        assert_eq!(config.paths.state_file, PathBuf::from("other.json"));
+        assert_eq!(config.paths.log_file, PathBuf::from("from-file.log"));
+
+        // A config file that was asked for has to exist
+        let cli = Cli {
+            config: Some(path),
+            ..Default::default()
+        };
+        assert!(Config::from_cli(cli).is_err());
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let mut config = Config::default();
+        config.rate_limit.requests = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.leveling.max_level = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.logging.level = "info,=[".to_string();
+        assert!(config.validate().is_err());
+
+        // Lifetimes long enough to overflow in milliseconds
+        let mut config = Config::default();
+        config.auth.token_lifetime_secs = u64::MAX / 10;
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,396 @@
+/*
+
+This file handles friendly contests between pets of different owners.
+
+A user challenges another user's pet from a pet yard they both belong to, and the contest is
+held once the other owner accepts. Each pet's contest stats come from its level and species.
+The pets take turns trying to tag each other until one runs out of stamina, with every roll
+coming from a seed kept in the battle log, so any contest can be played back exactly.
+Nobody gets hurt: the pets just get experience, and a win, loss or draw on their record.
+
+*/
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const MS_PER_HOUR: u64 = 1000 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContestConfig {
+    // How long a challenge waits for an answer
+    pub challenge_expiry_hours: u64,
+    // Power and speed at level 1, before the species' traits
+    pub base_stat: f64,
+    pub stat_per_level: f64,
+    // Stamina is this many times the stamina stat
+    pub stamina_multiplier: f64,
+    // Contests still going after this many turns go to the pet with more stamina left
+    pub max_turns: u32,
+    pub winner_experience: u64,
+    // Experience for losing, or for both pets in a draw
+    pub loser_experience: u64,
+    // Oldest battle logs are dropped past this many
+    pub max_battle_logs: usize,
+}
+
+impl Default for ContestConfig {
+    fn default() -> Self {
+        Self {
+            challenge_expiry_hours: 24,
+            base_stat: 5.0,
+            stat_per_level: 1.0,
+            stamina_multiplier: 4.0,
+            max_turns: 40,
+            winner_experience: 30,
+            loser_experience: 10,
+            max_battle_logs: 10_000,
+        }
+    }
+}
+
+impl ContestConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.challenge_expiry_hours == 0 {
+            return Err("contests.challenge_expiry_hours must be greater than 0".to_string());
+        }
+
+        let positive = [self.base_stat, self.stamina_multiplier]
+            .iter()
+            .all(|value| value.is_finite() && *value > 0.0);
+        if !positive || !self.stat_per_level.is_finite() || self.stat_per_level < 0.0 {
+            return Err("contests.base_stat and stamina_multiplier must be above 0, and stat_per_level not negative".to_string());
+        }
+
+        if self.max_turns == 0 {
+            return Err("contests.max_turns must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// How a species does in contests compared to others, 1.0 is average
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ContestTraits {
+    pub power: f64,
+    pub speed: f64,
+    pub stamina: f64,
+}
+
+impl Default for ContestTraits {
+    fn default() -> Self {
+        Self {
+            power: 1.0,
+            speed: 1.0,
+            stamina: 1.0,
+        }
+    }
+}
+
+impl ContestTraits {
+    pub fn is_valid(&self) -> bool {
+        [self.power, self.speed, self.stamina]
+            .iter()
+            .all(|value| value.is_finite() && *value > 0.0)
+    }
+}
+
+/// A pet as it enters a contest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contestant {
+    pub pet: String,
+    pub owner: String,
+    pub name: String,
+    pub level: u32,
+    pub power: f64,
+    pub speed: f64,
+    pub stamina: f64,
+}
+
+impl Contestant {
+    pub fn new(pet: String, owner: String, name: String, level: u32, traits: &ContestTraits, config: &ContestConfig) -> Self {
+        let stat = config.base_stat + config.stat_per_level * level.saturating_sub(1) as f64;
+
+        Self {
+            pet,
+            owner,
+            name,
+            level,
+            power: stat * traits.power,
+            speed: stat * traits.speed,
+            stamina: stat * traits.stamina * config.stamina_multiplier,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContestResult {
+    ChallengerWon,
+    OpponentWon,
+    Draw,
+}
+
+/// One pet's go at tagging the other
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Turn {
+    pub turn: u32,
+    // UUID of the pet taking its go
+    pub pet: String,
+    pub hit: bool,
+    pub damage: f64,
+    // The other pet's stamina afterwards
+    pub stamina_left: f64,
+}
+
+/// Plays out a contest. The same seed and contestants always give the same turns and result.
+pub fn resolve(seed: u64, challenger: &Contestant, opponent: &Contestant, config: &ContestConfig) -> (Vec<Turn>, ContestResult) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut stamina = [challenger.stamina, opponent.stamina];
+    let contestants = [challenger, opponent];
+    let mut turns = vec![];
+
+    // The faster pet goes first, with ties settled by the seed
+    let mut attacker = match challenger.speed.partial_cmp(&opponent.speed) {
+        Some(std::cmp::Ordering::Greater) => 0,
+        Some(std::cmp::Ordering::Less) => 1,
+        _ => rng.gen_range(0..2),
+    };
+
+    for turn in 1..=config.max_turns {
+        let defender = 1 - attacker;
+        let (attacking, defending) = (contestants[attacker], contestants[defender]);
+
+        // Faster pets hit more often, but everyone hits at least half the time
+        let hit_chance = 0.5 + 0.5 * attacking.speed / (attacking.speed + defending.speed);
+        let hit = rng.gen_bool(hit_chance.clamp(0.0, 1.0));
+        let roll: f64 = rng.gen_range(0.8..=1.2);
+        let damage = if hit { (attacking.power * roll * 10.0).round() / 10.0 } else { 0.0 };
+
+        stamina[defender] = (stamina[defender] - damage).max(0.0);
+        turns.push(Turn {
+            turn,
+            pet: attacking.pet.clone(),
+            hit,
+            damage,
+            stamina_left: stamina[defender],
+        });
+
+        if stamina[defender] <= 0.0 {
+            let result = if attacker == 0 { ContestResult::ChallengerWon } else { ContestResult::OpponentWon };
+            return (turns, result);
+        }
+
+        attacker = defender;
+    }
+
+    // Out of turns, so whoever has more of their stamina left wins
+    let left = [stamina[0] / challenger.stamina, stamina[1] / opponent.stamina];
+    let result = match left[0].partial_cmp(&left[1]) {
+        Some(std::cmp::Ordering::Greater) => ContestResult::ChallengerWon,
+        Some(std::cmp::Ordering::Less) => ContestResult::OpponentWon,
+        _ => ContestResult::Draw,
+    };
+
+    (turns, result)
+}
+
+/// A pet's contest history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ContestRecord {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl ContestRecord {
+    pub fn add(&mut self, result: ContestResult, challenger: bool) {
+        match (result, challenger) {
+            (ContestResult::Draw, _) => self.draws += 1,
+            (ContestResult::ChallengerWon, true) | (ContestResult::OpponentWon, false) => self.wins += 1,
+            _ => self.losses += 1,
+        }
+    }
+}
+
+/// A challenge waiting for the opponent's owner to accept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContestChallenge {
+    pub uuid: String,
+    pub from_user: String,
+    pub pet: String,
+    pub to_user: String,
+    pub opponent: String,
+    pub created: u64,
+    pub expires: u64,
+}
+
+impl ContestChallenge {
+    pub fn new(from_user: String, pet: String, to_user: String, opponent: String, now: u64, config: &ContestConfig) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            from_user,
+            pet,
+            to_user,
+            opponent,
+            created: now,
+            expires: now + config.challenge_expiry_hours * MS_PER_HOUR,
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.expires
+    }
+
+    pub fn involves(&self, user_uuid: &str) -> bool {
+        self.from_user == user_uuid || self.to_user == user_uuid
+    }
+
+    pub fn for_user(&self) -> serde_json::Value {
+        serde_json::json!(self)
+    }
+}
+
+/// Everything needed to play a contest back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BattleLog {
+    pub uuid: String,
+    pub timestamp: u64,
+    pub seed: u64,
+    pub challenger: Contestant,
+    pub opponent: Contestant,
+    pub turns: Vec<Turn>,
+    pub result: ContestResult,
+}
+
+impl BattleLog {
+    /// The owner of the winning pet, if there was one
+    pub fn winner(&self) -> Option<&String> {
+        match self.result {
+            ContestResult::ChallengerWon => Some(&self.challenger.owner),
+            ContestResult::OpponentWon => Some(&self.opponent.owner),
+            ContestResult::Draw => None,
+        }
+    }
+
+    pub fn involves(&self, user_uuid: &str) -> bool {
+        self.challenger.owner == user_uuid || self.opponent.owner == user_uuid
+    }
+}
+
+/// Why a contest couldn't be set up or held
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContestError {
+    NotFound,
+    NotOwner,
+    // Both pets belong to the same user
+    SameOwner,
+    NotInSameYard,
+    AlreadyChallenged,
+    Dead,
+    Expired,
+}
+
+impl std::fmt::Display for ContestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContestError::NotFound => write!(f, "Pet or challenge not found"),
+            ContestError::NotOwner => write!(f, "Pet belongs to another user"),
+            ContestError::SameOwner => write!(f, "Contests are between pets of different owners"),
+            ContestError::NotInSameYard => write!(f, "Both pets have to be in a pet yard both owners belong to"),
+            ContestError::AlreadyChallenged => write!(f, "There is already a challenge between these pets"),
+            ContestError::Dead => write!(f, "Pet has died"),
+            ContestError::Expired => write!(f, "Challenge has expired"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::leaderboards::Board;
+    use crate::simulation::{add_user_with_pet, mock_state};
+    use crate::structs::PetYard;
+
+    fn contestant(pet: &str, level: u32, traits: ContestTraits) -> Contestant {
+        Contestant::new(pet.to_string(), format!("{}'s owner", pet), pet.to_string(), level, &traits, &ContestConfig::default())
+    }
+
+    #[test]
+    fn test_contests_replay_from_their_seed() {
+        let config = ContestConfig::default();
+        let rex = contestant("rex", 10, ContestTraits::default());
+        let tom = contestant("tom", 10, ContestTraits { speed: 1.5, ..ContestTraits::default() });
+
+        let (turns, result) = resolve(42, &rex, &tom, &config);
+        assert_eq!(resolve(42, &rex, &tom, &config), (turns.clone(), result));
+
+        // The faster pet goes first, and the contest ends once a pet runs out of stamina
+        assert_eq!(turns[0].pet, "tom");
+        assert!(turns.len() <= config.max_turns as usize);
+        if result != ContestResult::Draw {
+            assert_eq!(turns.last().unwrap().stamina_left, 0.0);
+        }
+
+        // A much stronger pet wins every time
+        let champion = contestant("champion", 60, ContestTraits::default());
+        for seed in 0..20 {
+            assert_eq!(resolve(seed, &champion, &rex, &config).1, ContestResult::ChallengerWon);
+        }
+    }
+
+    #[test]
+    fn test_contests_between_yard_mates_are_recorded() {
+        let (mut state, clock) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, tom) = add_user_with_pet(&mut state, "bob");
+
+        assert_eq!(state.challenge_pet(&alice, &cat, &cat).unwrap_err(), ContestError::SameOwner);
+        assert_eq!(state.challenge_pet(&alice, &tom, &cat).unwrap_err(), ContestError::NotOwner);
+        assert_eq!(state.challenge_pet(&alice, &cat, &tom).unwrap_err(), ContestError::NotInSameYard);
+
+        let mut pet_yard = PetYard::new("Park".to_string(), alice.clone(), 1);
+        pet_yard.add_member(bob.clone());
+        pet_yard.add_pet(cat.clone());
+        pet_yard.add_pet(tom.clone());
+        let pet_yard_uuid = pet_yard.get_uuid();
+        state.update_pet_yard(pet_yard);
+
+        let mut expected = (ContestRecord::default(), ContestRecord::default());
+        for round in 1..=2 {
+            let challenge = state.challenge_pet(&alice, &cat, &tom).unwrap();
+            assert_eq!(state.challenge_pet(&bob, &tom, &cat).unwrap_err(), ContestError::AlreadyChallenged);
+
+            // Only the opponent's owner can accept
+            assert_eq!(state.accept_contest_challenge(&alice, &challenge.uuid).unwrap_err(), ContestError::NotFound);
+            let log = state.accept_contest_challenge(&bob, &challenge.uuid).unwrap();
+
+            assert_eq!(log.challenger.pet, cat);
+            assert_eq!(log.opponent.pet, tom);
+            assert_eq!(state.get_battle_log(&log.uuid), Some(&log));
+            assert_eq!(state.get_battle_logs(&alice).len(), round);
+            assert_eq!(state.get_battle_logs(&bob)[0], &log);
+
+            expected.0.add(log.result, true);
+            expected.1.add(log.result, false);
+            assert_eq!(state.get_pet_by_uuid(&cat).unwrap().get_contest_record(), expected.0);
+            assert_eq!(state.get_pet_by_uuid(&tom).unwrap().get_contest_record(), expected.1);
+        }
+
+        let record = expected.0;
+        assert_eq!(record.wins + record.losses + record.draws, 2);
+        let busiest = state.leaderboards.get(Board::MostActiveYard, None).unwrap().page(0, 1);
+        assert_eq!(busiest, vec![(1, &pet_yard_uuid, 2)]);
+
+        // Challenges nobody answers in time can't be accepted
+        let challenge = state.challenge_pet(&alice, &cat, &tom).unwrap();
+        clock.set(challenge.expires + 1);
+        assert_eq!(state.accept_contest_challenge(&bob, &challenge.uuid).unwrap_err(), ContestError::Expired);
+        assert_eq!(state.battle_logs.len(), 2);
+    }
+}
@@ -0,0 +1,196 @@
+/*
+
+This file handles cooldowns and daily caps on pet actions,
+so a pet can't be fed or petted over and over in a loop.
+
+Each pet keeps track of when it last had each action done to it,
+and how many times it has had it done today (in UTC).
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const MS_PER_DAY: u64 = 1000 * 60 * 60 * 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PetAction {
+    Feed,
+    Pet,
+    Medicine,
+    Rest,
+    Clean,
+    // Starting a mini-game
+    Play,
+}
+
+impl PetAction {
+    pub const ALL: [PetAction; 6] = [
+        PetAction::Feed,
+        PetAction::Pet,
+        PetAction::Medicine,
+        PetAction::Rest,
+        PetAction::Clean,
+        PetAction::Play,
+    ];
+}
+
+/// How often an action can be done
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActionLimit {
+    // Time between uses, 0 for none
+    pub cooldown_secs: u64,
+    // Uses per day, 0 for no cap
+    pub daily_cap: u32,
+}
+
+impl ActionLimit {
+    fn new(cooldown_secs: u64, daily_cap: u32) -> Self {
+        Self {
+            cooldown_secs,
+            daily_cap,
+        }
+    }
+}
+
+impl Default for ActionLimit {
+    fn default() -> Self {
+        Self::new(0, 0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CooldownConfig {
+    pub feed: ActionLimit,
+    pub pet: ActionLimit,
+    pub medicine: ActionLimit,
+    pub rest: ActionLimit,
+    pub clean: ActionLimit,
+    pub play: ActionLimit,
+}
+
+impl Default for CooldownConfig {
+    fn default() -> Self {
+        Self {
+            feed: ActionLimit::new(60 * 30, 10),
+            pet: ActionLimit::new(60, 50),
+            medicine: ActionLimit::new(60 * 60, 5),
+            rest: ActionLimit::new(60 * 60, 5),
+            clean: ActionLimit::new(60 * 60, 5),
+            play: ActionLimit::new(60 * 5, 10),
+        }
+    }
+}
+
+impl CooldownConfig {
+    pub fn limit(&self, action: PetAction) -> ActionLimit {
+        match action {
+            PetAction::Feed => self.feed,
+            PetAction::Pet => self.pet,
+            PetAction::Medicine => self.medicine,
+            PetAction::Rest => self.rest,
+            PetAction::Clean => self.clean,
+            PetAction::Play => self.play,
+        }
+    }
+}
+
+/// When an action was last done, and how often it has been done today
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ActionUsage {
+    pub last_used: u64,
+    // Days since the epoch that used_today counts for
+    pub day: u64,
+    pub used_today: u32,
+}
+
+impl ActionUsage {
+    fn used_on(&self, now: u64) -> u32 {
+        if self.day == now / MS_PER_DAY {
+            self.used_today
+        } else {
+            0
+        }
+    }
+}
+
+/// Every action done to a pet, by action
+pub type ActionLog = HashMap<PetAction, ActionUsage>;
+
+/// When the action can next be done, or None if it can be done now
+pub fn available_at(usage: Option<&ActionUsage>, limit: ActionLimit, now: u64) -> Option<u64> {
+    let usage = usage?;
+
+    let mut at = usage.last_used + limit.cooldown_secs * 1000;
+
+    // Once the cap is hit, wait for the next day
+    if limit.daily_cap > 0 && usage.used_on(now) >= limit.daily_cap {
+        at = at.max((now / MS_PER_DAY + 1) * MS_PER_DAY);
+    }
+
+    (at > now).then_some(at)
+}
+
+/// Records that the action was done
+pub fn record_use(log: &mut ActionLog, action: PetAction, now: u64) {
+    let usage = log.entry(action).or_default();
+
+    *usage = ActionUsage {
+        last_used: now,
+        day: now / MS_PER_DAY,
+        used_today: usage.used_on(now) + 1,
+    };
+}
+
+/// The cooldown state of every action, for showing in pet responses
+pub fn summary(log: &ActionLog, config: &CooldownConfig, now: u64) -> serde_json::Value {
+    let mut summary = serde_json::Map::new();
+
+    for action in PetAction::ALL {
+        let limit = config.limit(action);
+        let usage = log.get(&action);
+
+        summary.insert(
+            serde_json::to_value(action).unwrap().as_str().unwrap().to_string(),
+            serde_json::json!({
+                "available_at": available_at(usage, limit, now),
+                "used_today": usage.map(|usage| usage.used_on(now)).unwrap_or(0),
+                "daily_cap": limit.daily_cap,
+            }),
+        );
+    }
+
+    serde_json::Value::Object(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cooldown_and_daily_cap() {
+        let limit = ActionLimit::new(60, 2);
+        let mut log = ActionLog::new();
+        let start = 10 * MS_PER_DAY;
+
+        assert_eq!(available_at(log.get(&PetAction::Feed), limit, start), None);
+
+        record_use(&mut log, PetAction::Feed, start);
+        assert_eq!(available_at(log.get(&PetAction::Feed), limit, start + 1000), Some(start + 60_000));
+        assert_eq!(available_at(log.get(&PetAction::Feed), limit, start + 60_000), None);
+
+        // The second use hits the cap, so the action waits for the next day
+        record_use(&mut log, PetAction::Feed, start + 60_000);
+        assert_eq!(available_at(log.get(&PetAction::Feed), limit, start + 120_000), Some(11 * MS_PER_DAY));
+
+        // Other actions are tracked separately
+        assert_eq!(available_at(log.get(&PetAction::Pet), limit, start + 120_000), None);
+
+        // A new day resets the count
+        record_use(&mut log, PetAction::Feed, 11 * MS_PER_DAY);
+        assert_eq!(log[&PetAction::Feed].used_today, 1);
+    }
+}
@@ -0,0 +1,283 @@
+/*
+
+This file handles coins, the in-game currency.
+
+Users earn coins by logging in each day, caring for their pets and unlocking achievements,
+and spend them in the shop. Every change to a balance is written to a ledger, so balances
+can be checked by replaying it from the start.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const MS_PER_DAY: u64 = 1000 * 60 * 60 * 24;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EconomyConfig {
+    // Coins every new user starts with
+    pub starting_coins: u64,
+    // Coins for the first login each day (in UTC)
+    pub daily_login_reward: u64,
+    // Coins for each time a user cares for one of their pets
+    pub care_reward: u64,
+    // How many care actions are rewarded each day
+    pub care_rewards_per_day: u32,
+    // Coins for unlocking an achievement
+    pub achievement_reward: u64,
+    // Most of one item that can be bought at once
+    pub max_purchase_quantity: u32,
+}
+
+impl Default for EconomyConfig {
+    fn default() -> Self {
+        Self {
+            starting_coins: 100,
+            daily_login_reward: 50,
+            care_reward: 2,
+            care_rewards_per_day: 20,
+            achievement_reward: 100,
+            max_purchase_quantity: 99,
+        }
+    }
+}
+
+impl EconomyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_purchase_quantity == 0 {
+            return Err("economy.max_purchase_quantity must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Why a balance changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LedgerReason {
+    StartingCoins,
+    DailyLogin,
+    Care { pet: String },
+    Achievement { achievement: String },
+    Purchase { item: String, quantity: u32 },
+    Trade { trade: String },
+    Quest { quest: String },
+    QuestStreak { streak: u32 },
+}
+
+/// A change to a user's balance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub uuid: String,
+    pub timestamp: u64,
+    pub user: String,
+    // Negative when coins are spent
+    pub amount: i64,
+    // The user's balance after the change
+    pub balance: u64,
+    pub reason: LedgerReason,
+}
+
+impl LedgerEntry {
+    pub fn new(user: String, amount: i64, balance: u64, reason: LedgerReason, now: u64) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            timestamp: now,
+            user,
+            amount,
+            balance,
+            reason,
+        }
+    }
+}
+
+/// Works out every user's balance from the ledger, checking each entry's balance
+/// matches and no balance ever goes below zero
+pub fn replay(ledger: &[LedgerEntry]) -> Result<HashMap<String, u64>, String> {
+    let mut balances: HashMap<String, u64> = HashMap::new();
+
+    for entry in ledger {
+        let balance = balances.entry(entry.user.clone()).or_insert(0);
+
+        let replayed = balance
+            .checked_add_signed(entry.amount)
+            .ok_or_else(|| format!("ledger entry {} takes {} below zero", entry.uuid, entry.user))?;
+
+        if replayed != entry.balance {
+            return Err(format!(
+                "ledger entry {} says {} has {} coins, but replaying gives {}",
+                entry.uuid, entry.user, entry.balance, replayed
+            ));
+        }
+
+        *balance = replayed;
+    }
+
+    Ok(balances)
+}
+
+/// Which rewards a user has already had, so they can't be claimed twice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct RewardTracker {
+    // Days since the epoch
+    pub login_day: u64,
+    pub care_day: u64,
+    pub care_today: u32,
+}
+
+impl RewardTracker {
+    /// Returns true the first time it is called each day
+    pub fn claim_daily_login(&mut self, now: u64) -> bool {
+        let today = now / MS_PER_DAY;
+
+        if self.login_day == today {
+            return false;
+        }
+
+        self.login_day = today;
+        true
+    }
+
+    /// Returns true until the day's care rewards run out
+    pub fn claim_care(&mut self, now: u64, per_day: u32) -> bool {
+        let today = now / MS_PER_DAY;
+
+        if self.care_day != today {
+            self.care_day = today;
+            self.care_today = 0;
+        }
+
+        if self.care_today >= per_day {
+            return false;
+        }
+
+        self.care_today += 1;
+        true
+    }
+}
+
+/// Why coins couldn't be spent
+#[derive(Debug, Clone, PartialEq)]
+pub enum EconomyError {
+    UserNotFound,
+    UnknownItem,
+    NotForSale,
+    InvalidQuantity,
+    InsufficientFunds { balance: u64, price: u64 },
+}
+
+impl std::fmt::Display for EconomyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EconomyError::UserNotFound => write!(f, "User not found"),
+            EconomyError::UnknownItem => write!(f, "Unknown item"),
+            EconomyError::NotForSale => write!(f, "Item is not for sale"),
+            EconomyError::InvalidQuantity => write!(f, "Invalid quantity"),
+            EconomyError::InsufficientFunds { balance, price } => {
+                write!(f, "Costs {} coins, but only {} are available", price, balance)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::cooldowns::PetAction;
+    use crate::simulation::{add_user_with_pet, mock_state};
+
+    #[test]
+    fn test_replay_checks_balances() {
+        let mut ledger = vec![
+            LedgerEntry::new("alice".to_string(), 100, 100, LedgerReason::StartingCoins, 0),
+            LedgerEntry::new("bob".to_string(), 100, 100, LedgerReason::StartingCoins, 0),
+            LedgerEntry::new(
+                "alice".to_string(),
+                -30,
+                70,
+                LedgerReason::Purchase { item: "kibble".to_string(), quantity: 3 },
+                1,
+            ),
+        ];
+
+        let balances = replay(&ledger).unwrap();
+        assert_eq!(balances["alice"], 70);
+        assert_eq!(balances["bob"], 100);
+
+        // Tampered balances and overspending are both caught
+        ledger[2].balance = 80;
+        assert!(replay(&ledger).is_err());
+
+        ledger[2].amount = -130;
+        ledger[2].balance = 0;
+        assert!(replay(&ledger).is_err());
+    }
+
+    #[test]
+    fn test_purchases_take_coins_and_give_items() {
+        let (mut state, _) = mock_state(Config::default());
+        let (alice, _) = add_user_with_pet(&mut state, "alice");
+        state.credit_coins(&alice, 100, LedgerReason::StartingCoins);
+
+        // Kibble is 5 coins each
+        assert_eq!(state.buy_item(&alice, "kibble", 3), Ok(85));
+        assert_eq!(state.get_user_by_uuid(&alice).unwrap().item_count("kibble"), 3);
+
+        let purchase = state
+            .get_ledger(&alice)
+            .into_iter()
+            .find(|entry| matches!(entry.reason, LedgerReason::Purchase { .. }))
+            .unwrap();
+        assert_eq!(purchase.amount, -15);
+        assert_eq!(purchase.balance, 85);
+        assert_eq!(purchase.reason, LedgerReason::Purchase { item: "kibble".to_string(), quantity: 3 });
+    }
+
+    #[test]
+    fn test_purchases_without_enough_coins_change_nothing() {
+        let (mut state, _) = mock_state(Config::default());
+        let (alice, _) = add_user_with_pet(&mut state, "alice");
+        state.credit_coins(&alice, 10, LedgerReason::StartingCoins);
+
+        let ledger_before = state.ledger.len();
+
+        assert_eq!(
+            state.buy_item(&alice, "kibble", 3),
+            Err(EconomyError::InsufficientFunds { balance: 10, price: 15 })
+        );
+
+        let user = state.get_user_by_uuid(&alice).unwrap();
+        assert_eq!(user.get_coins(), 10);
+        assert_eq!(user.item_count("kibble"), 0);
+        assert_eq!(state.ledger.len(), ledger_before);
+    }
+
+    #[test]
+    fn test_ledger_matches_balances_after_rewards_and_purchases() {
+        let (mut state, _) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+
+        for user_uuid in [&alice, &bob] {
+            state.credit_coins(user_uuid, state.config.economy.starting_coins, LedgerReason::StartingCoins);
+            state.reward_daily_login(user_uuid);
+        }
+
+        state.record_care(&alice, &cat, PetAction::Feed);
+        state.record_care(&alice, &cat, PetAction::Pet);
+        state.buy_item(&alice, "kibble", 2).unwrap();
+        state.buy_item(&bob, "ball", 1).unwrap();
+        assert!(state.buy_item(&bob, "kibble", state.config.economy.max_purchase_quantity).is_err());
+
+        assert!(state.ledger.len() > 6);
+        assert_eq!(state.audit_coins(), Ok(()));
+
+        // A change missing from the ledger is caught
+        state.ledger.pop();
+        assert!(state.audit_coins().is_err());
+    }
+}
@@ -15,6 +15,7 @@ use sha2::{Digest, Sha256};
 /// Encrypts the given data using the given key and a random nonce.
 /// The nonce length is 12 bytes.
 /// The output is a base64 encoded string
+#[allow(dead_code)]
 pub fn encrypt<T: Serialize>(
     data: T,
     key: &[u8; 32],
@@ -45,6 +46,7 @@ pub fn encrypt<T: Serialize>(
 /// Decrypts the given base64 encoded string using the given key.
 /// The nonce length is 12 bytes.
 /// The input is a base64 encoded string
+#[allow(dead_code)]
 pub fn decrypt<T: for<'a> Deserialize<'a>>(
     data: &str,
     key: &[u8; 32],
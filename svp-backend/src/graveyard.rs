@@ -0,0 +1,192 @@
+/*
+
+This file handles the graveyard, where pets go when they die.
+
+Each user keeps their dead pets in a graveyard instead of losing them,
+and a pet can be revived for a while after it dies.
+
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::health::DeathCause;
+use crate::structs::Pet;
+
+const MS_PER_HOUR: u64 = 1000 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Grave {
+    pub pet: Pet,
+    // UUID of the user who owned the pet
+    pub owner: String,
+    pub cause: DeathCause,
+    pub died_at: u64,
+}
+
+/// Why a pet could not be revived
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReviveError {
+    NotFound,
+    NotOwner,
+    Disabled,
+    TooLate,
+}
+
+impl std::fmt::Display for ReviveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReviveError::NotFound => write!(f, "Grave not found"),
+            ReviveError::NotOwner => write!(f, "Pet belongs to another user"),
+            ReviveError::Disabled => write!(f, "Revival is disabled"),
+            ReviveError::TooLate => write!(f, "Pet has been dead too long to revive"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraveyardConfig {
+    // How long after death a pet can be revived, 0 turns revival off
+    pub revival_window_hours: u64,
+    // What a revived pet's stats are set to
+    pub revival_stats: f64,
+}
+
+impl Default for GraveyardConfig {
+    fn default() -> Self {
+        Self {
+            revival_window_hours: 24,
+            revival_stats: 50.0,
+        }
+    }
+}
+
+impl GraveyardConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=100.0).contains(&self.revival_stats) || self.revival_stats == 0.0 {
+            return Err("graveyard.revival_stats must be above 0 and at most 100".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+impl Grave {
+    pub fn new(pet: Pet, owner: String, cause: DeathCause, died_at: u64) -> Self {
+        Self {
+            pet,
+            owner,
+            cause,
+            died_at,
+        }
+    }
+
+    /// The last moment the pet can be revived, or None if revival is off
+    pub fn revivable_until(&self, config: &GraveyardConfig) -> Option<u64> {
+        if config.revival_window_hours == 0 {
+            return None;
+        }
+
+        Some(self.died_at + config.revival_window_hours * MS_PER_HOUR)
+    }
+
+    pub fn check_revivable(&self, now: u64, config: &GraveyardConfig) -> Result<(), ReviveError> {
+        match self.revivable_until(config) {
+            None => Err(ReviveError::Disabled),
+            Some(until) if now > until => Err(ReviveError::TooLate),
+            Some(_) => Ok(()),
+        }
+    }
+
+    pub fn for_user(&self, config: &Config) -> serde_json::Value {
+        serde_json::json!({
+            "pet": serde_json::from_str::<serde_json::Value>(&self.pet.for_public(self.died_at, config)).unwrap(),
+            "cause": self.cause,
+            "died_at": self.died_at,
+            "revivable_until": self.revivable_until(&config.graveyard),
+        })
+    }
+
+    /// A memorial page for the pet, shown to everyone
+    pub fn for_public(&self, config: &Config) -> String {
+        serde_json::json!({
+            "pet": serde_json::from_str::<serde_json::Value>(&self.pet.for_public(self.died_at, config)).unwrap(),
+            "owner": self.owner,
+            "cause": self.cause,
+            "died_at": self.died_at,
+        }).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::leaderboards::Board;
+    use crate::simulation::{add_user_with_pet, mock_state, START};
+    use crate::structs::AppState;
+
+    fn ranked(state: &AppState, pet_uuid: &str) -> bool {
+        state
+            .leaderboards
+            .get(Board::HighestLevel, Some("cat"))
+            .is_some_and(|ranking| ranking.page(0, usize::MAX).iter().any(|(_, uuid, _)| *uuid == pet_uuid))
+    }
+
+    #[test]
+    fn test_revival_window() {
+        let grave = Grave::new(Pet::new("Rex".to_string(), "dog".to_string(), 0, None, 0), "owner".to_string(), DeathCause::Starvation, 0);
+        let config = GraveyardConfig::default();
+
+        assert!(grave.check_revivable(config.revival_window_hours * MS_PER_HOUR, &config).is_ok());
+        assert_eq!(grave.check_revivable(config.revival_window_hours * MS_PER_HOUR + 1, &config), Err(ReviveError::TooLate));
+
+        let disabled = GraveyardConfig { revival_window_hours: 0, ..config };
+        assert_eq!(grave.check_revivable(0, &disabled), Err(ReviveError::Disabled));
+    }
+
+    #[test]
+    fn test_owners_can_revive_pets_that_died_recently() {
+        let (mut state, clock) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+
+        // Left alone, the cat starves after three days
+        clock.set(START + 80 * MS_PER_HOUR);
+        state.kill_unloved_pets();
+
+        let grave = state.get_grave_by_uuid(&cat).unwrap();
+        assert_eq!(grave.died_at, START + 72 * MS_PER_HOUR);
+        assert!(state.get_pet_by_uuid(&cat).is_none());
+        assert_eq!(state.get_pet_owner(&cat), None);
+        assert!(!ranked(&state, &cat));
+
+        assert_eq!(state.revive_pet(&bob, &cat).unwrap_err(), ReviveError::NotOwner);
+
+        let pet = state.revive_pet(&alice, &cat).unwrap();
+        assert!(!pet.is_dead());
+        assert!(state.get_grave_by_uuid(&cat).is_none());
+        assert_eq!(state.get_pet_owner(&cat), Some(alice.clone()));
+        assert!(state.get_user_by_uuid(&alice).unwrap().get_graveyard().is_empty());
+        assert!(ranked(&state, &cat));
+
+        assert_eq!(state.revive_pet(&alice, &cat).unwrap_err(), ReviveError::NotFound);
+    }
+
+    #[test]
+    fn test_pets_dead_too_long_stay_buried() {
+        let (mut state, clock) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+
+        // The window runs from when the pet died, not from when it was found
+        let died_at = START + 72 * MS_PER_HOUR;
+        clock.set(died_at + state.config.graveyard.revival_window_hours * MS_PER_HOUR + 1);
+        state.kill_unloved_pets();
+
+        assert_eq!(state.get_grave_by_uuid(&cat).unwrap().died_at, died_at);
+        assert_eq!(state.revive_pet(&alice, &cat).unwrap_err(), ReviveError::TooLate);
+        assert!(state.get_grave_by_uuid(&cat).is_some());
+        assert_eq!(state.get_user_by_uuid(&alice).unwrap().get_graveyard(), vec![cat.clone()]);
+        assert!(!ranked(&state, &cat));
+    }
+}
@@ -0,0 +1,154 @@
+/*
+
+This file handles pets growing up.
+
+Pets hatch from an egg and grow through baby, juvenile, adult and elder stages.
+Each species lists the forms it can grow into, and which form a pet takes depends on
+its level and how well it has been cared for. A form can change the pet's art and how
+quickly its stats decay.
+
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::needs::{DecayRates, PetStats};
+
+// How much each action moves care quality towards the pet's current stats
+const CARE_WEIGHT: f64 = 0.1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GrowthStage {
+    Egg,
+    Baby,
+    Juvenile,
+    Adult,
+    Elder,
+}
+
+impl GrowthStage {
+    pub fn next(&self) -> Option<GrowthStage> {
+        match self {
+            GrowthStage::Egg => Some(GrowthStage::Baby),
+            GrowthStage::Baby => Some(GrowthStage::Juvenile),
+            GrowthStage::Juvenile => Some(GrowthStage::Adult),
+            GrowthStage::Adult => Some(GrowthStage::Elder),
+            GrowthStage::Elder => None,
+        }
+    }
+}
+
+/// Pets saved before growth stages existed are treated as grown up
+pub fn legacy_stage() -> GrowthStage {
+    GrowthStage::Adult
+}
+
+/// A form a species can grow into
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Evolution {
+    pub stage: GrowthStage,
+    pub form: String,
+    pub min_level: u32,
+    // Care quality, from 0 to 100, needed to take this form
+    #[serde(default)]
+    pub min_care: f64,
+    // Images the pet can have in this form, empty for any of the species' images
+    #[serde(default)]
+    pub images: Vec<u64>,
+    // Multiplies the species' decay rates while in this form
+    #[serde(default)]
+    pub decay_rates: DecayRates,
+}
+
+impl Evolution {
+    pub fn allows_image(&self, image: u64) -> bool {
+        self.images.is_empty() || self.images.contains(&image)
+    }
+}
+
+/// A record of a pet growing into a new form
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EvolutionRecord {
+    pub timestamp: u64,
+    pub from: GrowthStage,
+    pub to: GrowthStage,
+    pub form: String,
+    pub level: u32,
+}
+
+/// Finds the form a pet grows into next, if it is ready.
+/// When more than one form fits, the first one listed wins, so better forms should be listed first.
+pub fn next_evolution(
+    evolutions: &[Evolution],
+    stage: GrowthStage,
+    level: u32,
+    care: f64,
+) -> Option<&Evolution> {
+    let next = stage.next()?;
+
+    evolutions
+        .iter()
+        .find(|evolution| evolution.stage == next && level >= evolution.min_level && care >= evolution.min_care)
+}
+
+/// Moves care quality towards how well looked after the pet is right now
+pub fn update_care(care: f64, stats: &PetStats) -> f64 {
+    let current = (stats.hunger + stats.happiness + stats.energy + stats.hygiene) / 4.0;
+
+    care * (1.0 - CARE_WEIGHT) + current * CARE_WEIGHT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::species::SpeciesError;
+    use crate::Pet;
+
+    fn evolution(stage: GrowthStage, form: &str, min_level: u32, min_care: f64) -> Evolution {
+        Evolution {
+            stage,
+            form: form.to_string(),
+            min_level,
+            min_care,
+            images: vec![],
+            decay_rates: DecayRates::default(),
+        }
+    }
+
+    #[test]
+    fn test_branches_depend_on_care() {
+        let evolutions = vec![
+            evolution(GrowthStage::Baby, "kitten", 2, 0.0),
+            evolution(GrowthStage::Adult, "sleek", 10, 70.0),
+            evolution(GrowthStage::Adult, "scruffy", 10, 0.0),
+        ];
+
+        assert_eq!(next_evolution(&evolutions, GrowthStage::Egg, 1, 100.0), None);
+        assert_eq!(next_evolution(&evolutions, GrowthStage::Egg, 2, 100.0).unwrap().form, "kitten");
+
+        // Stages can't be skipped
+        assert_eq!(next_evolution(&evolutions, GrowthStage::Baby, 10, 100.0), None);
+
+        assert_eq!(next_evolution(&evolutions, GrowthStage::Juvenile, 10, 90.0).unwrap().form, "sleek");
+        assert_eq!(next_evolution(&evolutions, GrowthStage::Juvenile, 10, 50.0).unwrap().form, "scruffy");
+    }
+
+    #[test]
+    fn test_grown_pets_keep_their_species() {
+        let config = Config::default();
+        let mut pet = Pet::new("Tom".to_string(), "cat".to_string(), 1, None, 0);
+
+        // An egg has no form yet, so it can still become something else
+        assert!(pet.set_species("dog".to_string()).is_ok());
+        assert!(pet.set_species("cat".to_string()).is_ok());
+
+        pet.add_experience(10_000, 0, &config);
+        assert!(pet.get_form().is_some());
+
+        assert_eq!(pet.set_species("dog".to_string()).unwrap_err(), SpeciesError::AlreadyGrown);
+        assert_eq!(pet.get_species(), "cat");
+        assert!(pet.set_species("cat".to_string()).is_ok());
+    }
+}
@@ -0,0 +1,301 @@
+/*
+
+This file handles the pet health state machine.
+
+Pets move along the chain healthy -> hungry -> sick -> critical -> dead
+as they are neglected, and back along it when they are cared for.
+
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::growth::GrowthStage;
+use crate::needs::{Need, NeedsConfig, PetStats, StatsSnapshot};
+
+const MS_PER_HOUR: u64 = 1000 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthState {
+    #[default]
+    Healthy,
+    Hungry,
+    Sick,
+    Critical,
+    Dead,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeathCause {
+    Starvation,
+    Neglect,
+    Illness,
+}
+
+/// Works out why a pet died from its stats at the time of death
+pub fn cause_of_death(stats: &PetStats, needs: &NeedsConfig) -> DeathCause {
+    if stats.hunger <= needs.death_threshold {
+        DeathCause::Starvation
+    } else if stats.happiness <= needs.death_threshold {
+        DeathCause::Neglect
+    } else {
+        DeathCause::Illness
+    }
+}
+
+/// Something that happened to a pet that its owner would want to hear about
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PetEvent {
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub kind: PetEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PetEventKind {
+    HealthChanged { from: HealthState, to: HealthState },
+    LevelUp { level: u32 },
+    Evolved { stage: GrowthStage, form: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HealthConfig {
+    // A healthy pet becomes hungry once its hunger drops below this
+    pub hungry_below: f64,
+    // A pet gets sick once its energy or hygiene drops to this
+    pub sick_stat_below: f64,
+    // How long a pet can stay in each state before getting worse
+    pub hungry_to_sick_hours: u64,
+    pub sick_to_critical_hours: u64,
+    pub critical_to_dead_hours: u64,
+    // How much energy resting restores
+    pub rest_restores: f64,
+    // How much energy and hygiene medicine restores, so a cured pet isn't straight back to being worn out
+    pub medicine_restores: f64,
+    // Number of events kept on each pet
+    pub max_events: usize,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            hungry_below: 40.0,
+            sick_stat_below: 10.0,
+            hungry_to_sick_hours: 24,
+            sick_to_critical_hours: 24,
+            critical_to_dead_hours: 24,
+            rest_restores: 60.0,
+            medicine_restores: 25.0,
+            max_events: 50,
+        }
+    }
+}
+
+impl HealthConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=100.0).contains(&self.hungry_below) || !(0.0..=100.0).contains(&self.sick_stat_below) {
+            return Err("health.hungry_below and health.sick_stat_below must be between 0 and 100".to_string());
+        }
+
+        if self.hungry_to_sick_hours == 0 || self.sick_to_critical_hours == 0 || self.critical_to_dead_hours == 0 {
+            return Err("health transition times must be greater than 0".to_string());
+        }
+
+        if !(0.0..=100.0).contains(&self.rest_restores) {
+            return Err("health.rest_restores must be between 0 and 100".to_string());
+        }
+
+        if self.medicine_restores <= 0.0 || self.medicine_restores > 100.0 {
+            return Err("health.medicine_restores must be greater than 0 and at most 100".to_string());
+        }
+
+        if self.max_events == 0 {
+            return Err("health.max_events must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Works out the next step along the health chain, and when it happened.
+/// Returns None if the pet stays in its current state.
+///
+/// Steps happen exactly when the pet has spent long enough in its state or a stat reached
+/// its threshold, so a pet that is only checked now and then, or was left alone while the
+/// server was down, ends up where it would have anyway.
+/// The time spent in each state is scaled by the species' lifespan modifier.
+pub fn next_state(
+    current: HealthState,
+    since: u64,
+    now: u64,
+    snapshot: &StatsSnapshot,
+    lifespan_modifier: f64,
+    config: &HealthConfig,
+    needs: &NeedsConfig,
+) -> Option<(HealthState, u64)> {
+    if current == HealthState::Dead {
+        return None;
+    }
+
+    let stats = snapshot.at(now, needs);
+
+    // When the first of the needs reached the threshold, if any has by now. Stats that were
+    // already past it count from when the pet got to its current state.
+    let reached = |needs_checked: &[Need], threshold: f64| {
+        needs_checked
+            .iter()
+            .filter_map(|need| snapshot.dropped_to(*need, threshold, needs))
+            .min()
+            .filter(|at| *at <= now)
+            .map(|at| at.max(since))
+    };
+
+    let after = |hours: u64| {
+        let at = since + (hours as f64 * lifespan_modifier * MS_PER_HOUR as f64) as u64;
+        (at <= now).then_some(at)
+    };
+
+    // Starving or completely unloved pets die as soon as it happens
+    let starved = reached(&[Need::Hunger, Need::Happiness], needs.death_threshold).map(|at| (HealthState::Dead, at));
+    let worn_out = reached(&[Need::Energy, Need::Hygiene], config.sick_stat_below).map(|at| (HealthState::Sick, at));
+    let hungry = stats.hunger < config.hungry_below;
+
+    let steps = match current {
+        HealthState::Healthy => vec![
+            worn_out,
+            reached(&[Need::Hunger], config.hungry_below).filter(|_| hungry).map(|at| (HealthState::Hungry, at)),
+        ],
+        HealthState::Hungry => vec![
+            worn_out,
+            // Fed back up, which happened when the stats were last saved
+            (!hungry).then(|| (HealthState::Healthy, snapshot.taken.max(since).min(now))),
+            after(config.hungry_to_sick_hours).map(|at| (HealthState::Sick, at)),
+        ],
+        HealthState::Sick => vec![after(config.sick_to_critical_hours).map(|at| (HealthState::Critical, at))],
+        HealthState::Critical => vec![after(config.critical_to_dead_hours).map(|at| (HealthState::Dead, at))],
+        HealthState::Dead => vec![],
+    };
+
+    // Whichever happened first, going by the order above for a tie, with dying first of all
+    std::iter::once(starved).chain(steps).flatten().min_by_key(|(_, at)| *at)
+}
+
+/// The state medicine moves a pet back to, or None if it isn't sick
+pub fn after_medicine(current: HealthState, stats: &PetStats, config: &HealthConfig) -> Option<HealthState> {
+    match current {
+        HealthState::Critical => Some(HealthState::Sick),
+        HealthState::Sick if stats.hunger < config.hungry_below => Some(HealthState::Hungry),
+        HealthState::Sick => Some(HealthState::Healthy),
+        _ => None,
+    }
+}
+
+/// The state resting moves a pet back to. Rest stabilises critical pets, but does not cure them.
+pub fn after_rest(current: HealthState) -> HealthState {
+    match current {
+        HealthState::Critical => HealthState::Sick,
+        state => state,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::needs::{DecayCurve, DecayRates};
+
+    const HOUR: u64 = MS_PER_HOUR;
+
+    /// Stats saved an hour in, which don't decay unless the needs config says so
+    fn stats(hunger: f64) -> StatsSnapshot {
+        StatsSnapshot {
+            stats: PetStats {
+                hunger,
+                ..PetStats::default()
+            },
+            taken: HOUR,
+            rates: DecayRates::default(),
+        }
+    }
+
+    fn still() -> NeedsConfig {
+        let none = DecayCurve::Linear { per_hour: 0.0 };
+
+        NeedsConfig {
+            hunger: none,
+            happiness: none,
+            energy: none,
+            hygiene: none,
+            ..NeedsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_chain_gets_worse_over_time() {
+        let config = HealthConfig::default();
+        let needs = still();
+
+        let (state, since) = next_state(HealthState::Healthy, 0, HOUR, &stats(30.0), 1.0, &config, &needs).unwrap();
+        assert_eq!((state, since), (HealthState::Hungry, HOUR));
+
+        // Nothing changes until the pet has been hungry long enough
+        assert!(next_state(state, since, 10 * HOUR, &stats(30.0), 1.0, &config, &needs).is_none());
+
+        let (state, since) = next_state(state, since, 60 * HOUR, &stats(30.0), 1.0, &config, &needs).unwrap();
+        assert_eq!((state, since), (HealthState::Sick, 25 * HOUR));
+
+        let (state, since) = next_state(state, since, 60 * HOUR, &stats(30.0), 1.0, &config, &needs).unwrap();
+        assert_eq!((state, since), (HealthState::Critical, 49 * HOUR));
+
+        let (state, _) = next_state(state, since, 80 * HOUR, &stats(30.0), 1.0, &config, &needs).unwrap();
+        assert_eq!(state, HealthState::Dead);
+    }
+
+    #[test]
+    fn test_feeding_cures_hunger_but_not_sickness() {
+        let config = HealthConfig::default();
+        let needs = still();
+
+        let next = next_state(HealthState::Hungry, 0, HOUR, &stats(90.0), 1.0, &config, &needs);
+        assert_eq!(next, Some((HealthState::Healthy, HOUR)));
+
+        assert!(next_state(HealthState::Sick, 0, HOUR, &stats(90.0), 1.0, &config, &needs).is_none());
+        assert_eq!(after_medicine(HealthState::Sick, &stats(90.0).stats, &config), Some(HealthState::Healthy));
+        assert_eq!(after_medicine(HealthState::Healthy, &stats(90.0).stats, &config), None);
+    }
+
+    #[test]
+    fn test_starving_pets_die() {
+        let config = HealthConfig::default();
+        let needs = still();
+
+        let next = next_state(HealthState::Healthy, 0, HOUR, &stats(0.0), 1.0, &config, &needs);
+        assert_eq!(next, Some((HealthState::Dead, HOUR)));
+    }
+
+    #[test]
+    fn test_steps_happen_when_stats_reach_thresholds() {
+        let config = HealthConfig::default();
+        let needs = NeedsConfig::default();
+        let fed = StatsSnapshot {
+            stats: PetStats::default(),
+            taken: 0,
+            rates: DecayRates::default(),
+        };
+
+        // Energy gets low at the same time hunger does, after 43.2 hours
+        let (state, since) = next_state(HealthState::Healthy, 0, 50 * HOUR, &fed, 1.0, &config, &needs).unwrap();
+        assert_eq!((state, since), (HealthState::Sick, 43 * HOUR + HOUR / 5));
+
+        let (state, since) = next_state(state, since, 100 * HOUR, &fed, 1.0, &config, &needs).unwrap();
+        assert_eq!((state, since), (HealthState::Critical, 67 * HOUR + HOUR / 5));
+
+        // However late the pet is checked, it died when its hunger ran out
+        let (state, since) = next_state(state, since, 100 * HOUR, &fed, 1.0, &config, &needs).unwrap();
+        assert_eq!((state, since), (HealthState::Dead, 72 * HOUR));
+        assert_eq!(cause_of_death(&fed.at(since, &needs), &needs), DeathCause::Starvation);
+    }
+}
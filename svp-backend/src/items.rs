@@ -0,0 +1,242 @@
+/*
+
+This file handles the item catalog and what items do to pets.
+
+Items are loaded from a data file. Food, toys and medicine change a pet's stats,
+with each counting as feeding, petting or giving medicine for cooldowns, and
+cosmetics are worn by the pet. Species can favour or refuse some foods.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use crate::cooldowns::PetAction;
+use crate::needs::{restore, PetStats, MAX_STAT};
+use crate::structs::PetActionError;
+
+// Used when no item file is given and there isn't one in the working directory
+const BUNDLED_ITEMS: &str = include_str!("../items.toml");
+
+pub const DEFAULT_ITEMS_PATH: &str = "items.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemKind {
+    Food,
+    Toy,
+    Medicine,
+    Cosmetic,
+}
+
+impl ItemKind {
+    /// The action using the item counts as, for cooldowns. Cosmetics can be changed at any time.
+    pub fn action(&self) -> Option<PetAction> {
+        match self {
+            ItemKind::Food => Some(PetAction::Feed),
+            ItemKind::Toy => Some(PetAction::Pet),
+            ItemKind::Medicine => Some(PetAction::Medicine),
+            ItemKind::Cosmetic => None,
+        }
+    }
+}
+
+/// How much an item changes each stat, which can be negative
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatChanges {
+    pub hunger: f64,
+    pub happiness: f64,
+    pub energy: f64,
+    pub hygiene: f64,
+}
+
+impl StatChanges {
+    fn is_valid(&self) -> bool {
+        [self.hunger, self.happiness, self.energy, self.hygiene]
+            .iter()
+            .all(|change| change.is_finite() && change.abs() <= MAX_STAT)
+    }
+
+    /// Applies the changes, scaled by the multiplier
+    pub fn apply(&self, stats: &mut PetStats, multiplier: f64) {
+        restore(&mut stats.hunger, self.hunger * multiplier);
+        restore(&mut stats.happiness, self.happiness * multiplier);
+        restore(&mut stats.energy, self.energy * multiplier);
+        restore(&mut stats.hygiene, self.hygiene * multiplier);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Item {
+    pub name: String,
+    pub kind: ItemKind,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub restores: StatChanges,
+    // Experience the pet gets on top of the usual amount for the action
+    #[serde(default)]
+    pub experience: u64,
+    // Coins it costs in the shop, or None if it isn't sold
+    #[serde(default)]
+    pub price: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemCatalog {
+    items: BTreeMap<String, Item>,
+}
+
+impl Default for ItemCatalog {
+    fn default() -> Self {
+        Self::from_toml(BUNDLED_ITEMS).expect("bundled item file is invalid")
+    }
+}
+
+impl ItemCatalog {
+    /// Loads the catalog from a file, falling back to the bundled one
+    /// if the default file doesn't exist
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if path == Path::new(DEFAULT_ITEMS_PATH) && !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read item file {}: {}", path.display(), e))?;
+
+        Self::from_toml(&contents)
+            .map_err(|e| format!("Invalid item file {}: {}", path.display(), e).into())
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, Box<dyn Error>> {
+        let catalog: Self = toml::from_str(contents)?;
+        catalog.validate()?;
+
+        Ok(catalog)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (id, item) in self.items.iter() {
+            if !item.restores.is_valid() {
+                return Err(format!("item {} changes a stat by more than 100", id));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Item> {
+        self.items.get(id)
+    }
+
+    /// Items that can be bought, by item id
+    pub fn for_sale(&self) -> BTreeMap<&String, &Item> {
+        self.items.iter().filter(|(_, item)| item.price.is_some()).collect()
+    }
+
+    pub fn is_food(&self, id: &str) -> bool {
+        self.get(id).is_some_and(|item| item.kind == ItemKind::Food)
+    }
+
+    pub fn for_public(&self) -> String {
+        serde_json::json!(self.items).to_string()
+    }
+}
+
+/// Settings for inventories and using items
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InventoryConfig {
+    // Items every new user starts with, by item id
+    pub starter_items: BTreeMap<String, u32>,
+    // Multiplies what a food restores when it is one of the species' favourites
+    pub favorite_multiplier: f64,
+}
+
+impl Default for InventoryConfig {
+    fn default() -> Self {
+        Self {
+            starter_items: [("kibble", 5), ("ball", 1), ("pill", 1)]
+                .iter()
+                .map(|(item, count)| (item.to_string(), *count))
+                .collect(),
+            favorite_multiplier: 1.5,
+        }
+    }
+}
+
+impl InventoryConfig {
+    pub fn validate(&self, catalog: &ItemCatalog) -> Result<(), String> {
+        if !self.favorite_multiplier.is_finite() || self.favorite_multiplier < 0.0 {
+            return Err("inventory.favorite_multiplier must not be negative".to_string());
+        }
+
+        if let Some(item) = self.starter_items.keys().find(|item| catalog.get(item).is_none()) {
+            return Err(format!("inventory.starter_items has unknown item {}", item));
+        }
+
+        Ok(())
+    }
+}
+
+/// How a species feels about a food
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preference {
+    Favorite,
+    Neutral,
+    Refused,
+}
+
+/// Why an item couldn't be used
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemError {
+    PetNotFound,
+    NotOwner,
+    UnknownItem,
+    NotInInventory,
+    Action(PetActionError),
+}
+
+impl From<PetActionError> for ItemError {
+    fn from(error: PetActionError) -> Self {
+        ItemError::Action(error)
+    }
+}
+
+impl std::fmt::Display for ItemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemError::PetNotFound => write!(f, "Pet not found"),
+            ItemError::NotOwner => write!(f, "Pet belongs to another user"),
+            ItemError::UnknownItem => write!(f, "Unknown item"),
+            ItemError::NotInInventory => write!(f, "Item is not in the inventory"),
+            ItemError::Action(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_catalog() {
+        let catalog = ItemCatalog::default();
+
+        assert!(catalog.is_food("kibble"));
+        assert!(!catalog.is_food("ball"));
+        assert!(InventoryConfig::default().validate(&catalog).is_ok());
+
+        let mut stats = PetStats { hunger: 50.0, ..PetStats::default() };
+        let changes = StatChanges { hunger: 20.0, energy: -10.0, ..StatChanges::default() };
+        changes.apply(&mut stats, 1.5);
+
+        assert_eq!(stats.hunger, 80.0);
+        assert_eq!(stats.energy, 85.0);
+    }
+}
@@ -0,0 +1,314 @@
+/*
+
+This file handles leaderboards for pets, users and pet yards.
+
+Each board is a ranking kept sorted as scores change, so showing a page of it doesn't
+need to look through every pet. Pet boards are kept for each species as well as overall.
+The rankings aren't saved: they are rebuilt from the state at startup and kept up to
+date from then on, as pets change, achievements are unlocked and pet yards are used.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeaderboardConfig {
+    // Entries per page when the client doesn't ask for a number
+    pub default_page_size: usize,
+    pub max_page_size: usize,
+}
+
+impl Default for LeaderboardConfig {
+    fn default() -> Self {
+        Self {
+            default_page_size: 20,
+            max_page_size: 100,
+        }
+    }
+}
+
+impl LeaderboardConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.default_page_size == 0 || self.default_page_size > self.max_page_size {
+            return Err("leaderboards.default_page_size must be between 1 and max_page_size".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Board {
+    // Living pets, by level and then experience
+    HighestLevel,
+    // Living pets, oldest first
+    LongestLiving,
+    // Users, by achievements unlocked
+    MostAchievements,
+    // Pet yards, by how much has been done in them
+    MostActiveYard,
+}
+
+impl Board {
+    pub const ALL: [Board; 4] = [Board::HighestLevel, Board::LongestLiving, Board::MostAchievements, Board::MostActiveYard];
+
+    /// Whether the board ranks pets, and so can be split by species
+    pub fn is_for_pets(&self) -> bool {
+        matches!(self, Board::HighestLevel | Board::LongestLiving)
+    }
+}
+
+impl std::str::FromStr for Board {
+    type Err = LeaderboardError;
+
+    fn from_str(board: &str) -> Result<Self, Self::Err> {
+        match board {
+            "highest_level" => Ok(Board::HighestLevel),
+            "longest_living" => Ok(Board::LongestLiving),
+            "most_achievements" => Ok(Board::MostAchievements),
+            "most_active_yard" => Ok(Board::MostActiveYard),
+            _ => Err(LeaderboardError::UnknownBoard),
+        }
+    }
+}
+
+/// Scores kept in order, highest first, with ties going to the lower UUID
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Ranking {
+    scores: HashMap<String, u64>,
+    order: BTreeSet<(Reverse<u64>, String)>,
+}
+
+impl Ranking {
+    pub fn set(&mut self, id: &str, score: u64) {
+        if let Some(old) = self.scores.insert(id.to_string(), score) {
+            self.order.remove(&(Reverse(old), id.to_string()));
+        }
+
+        self.order.insert((Reverse(score), id.to_string()));
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        if let Some(old) = self.scores.remove(id) {
+            self.order.remove(&(Reverse(old), id.to_string()));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// The ids and scores from offset on, with their rank starting from 1
+    pub fn page(&self, offset: usize, limit: usize) -> Vec<(usize, &String, u64)> {
+        self.order
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(limit)
+            .map(|(index, (Reverse(score), id))| (index + 1, id, *score))
+            .collect()
+    }
+}
+
+/// Score for the highest level board, so pets on the same level are split by experience
+pub fn level_score(level: u32, experience: u64) -> u64 {
+    ((level as u64) << 32) | experience.min(u32::MAX as u64)
+}
+
+/// Score for the longest living board, higher for pets born earlier
+pub fn age_score(born: u64) -> u64 {
+    u64::MAX - born
+}
+
+/// Every board, with the pet boards also split by species
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Leaderboards {
+    boards: HashMap<Board, Ranking>,
+    by_species: HashMap<(Board, String), Ranking>,
+    // The species each ranked pet is listed under, so it can be moved if it changes
+    pet_species: HashMap<String, String>,
+}
+
+impl Leaderboards {
+    /// Ranks a living pet, or moves it if it has changed species
+    pub fn set_pet(&mut self, pet: &str, species: &str, level_score: u64, age_score: u64) {
+        if self.pet_species.get(pet).is_some_and(|old| old != species) {
+            self.remove_pet(pet);
+        }
+
+        self.pet_species.insert(pet.to_string(), species.to_string());
+
+        for (board, score) in [(Board::HighestLevel, level_score), (Board::LongestLiving, age_score)] {
+            self.boards.entry(board).or_default().set(pet, score);
+            self.by_species.entry((board, species.to_string())).or_default().set(pet, score);
+        }
+    }
+
+    pub fn remove_pet(&mut self, pet: &str) {
+        let Some(species) = self.pet_species.remove(pet) else {
+            return;
+        };
+
+        for board in [Board::HighestLevel, Board::LongestLiving] {
+            if let Some(ranking) = self.boards.get_mut(&board) {
+                ranking.remove(pet);
+            }
+
+            if let Some(ranking) = self.by_species.get_mut(&(board, species.clone())) {
+                ranking.remove(pet);
+            }
+        }
+    }
+
+    /// Sets a user's or pet yard's score
+    pub fn set(&mut self, board: Board, id: &str, score: u64) {
+        self.boards.entry(board).or_default().set(id, score);
+    }
+
+    pub fn remove(&mut self, board: Board, id: &str) {
+        if let Some(ranking) = self.boards.get_mut(&board) {
+            ranking.remove(id);
+        }
+    }
+
+    /// The board overall, or for one species
+    pub fn get(&self, board: Board, species: Option<&str>) -> Option<&Ranking> {
+        match species {
+            Some(species) => self.by_species.get(&(board, species.to_string())),
+            None => self.boards.get(&board),
+        }
+    }
+}
+
+/// Why a leaderboard couldn't be shown
+#[derive(Debug, Clone, PartialEq)]
+pub enum LeaderboardError {
+    UnknownBoard,
+    // Only pet boards are split by species
+    NotBySpecies,
+    UnknownSpecies,
+}
+
+impl std::fmt::Display for LeaderboardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LeaderboardError::UnknownBoard => write!(f, "Unknown leaderboard"),
+            LeaderboardError::NotBySpecies => write!(f, "Only pet leaderboards can be split by species"),
+            LeaderboardError::UnknownSpecies => write!(f, "Unknown species"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::achievements::GameEvent;
+    use crate::config::Config;
+    use crate::simulation::{add_user_with_pet, mock_state, MS_PER_HOUR};
+    use crate::structs::AppState;
+
+    type Page = Vec<(usize, String, u64)>;
+
+    /// Every board, overall and for the species used here, with boards nobody is on left empty
+    fn pages(boards: &Leaderboards) -> Vec<(Board, Option<&'static str>, Page)> {
+        let mut pages = vec![];
+
+        for board in Board::ALL {
+            for species in [None, Some("cat"), Some("dog")] {
+                if species.is_some() && !board.is_for_pets() {
+                    continue;
+                }
+
+                let page = boards
+                    .get(board, species)
+                    .map(|ranking| ranking.page(0, usize::MAX).into_iter().map(|(rank, id, score)| (rank, id.clone(), score)).collect())
+                    .unwrap_or_default();
+                pages.push((board, species, page));
+            }
+        }
+
+        pages
+    }
+
+    fn assert_matches_rebuild(state: &AppState) {
+        let mut rebuilt = state.clone();
+        rebuilt.rebuild_leaderboards();
+
+        assert_eq!(pages(&state.leaderboards), pages(&rebuilt.leaderboards));
+    }
+
+    #[test]
+    fn test_rankings_stay_sorted_as_scores_change() {
+        let mut boards = Leaderboards::default();
+
+        boards.set_pet("rex", "dog", level_score(3, 10), age_score(1000));
+        boards.set_pet("tom", "cat", level_score(3, 50), age_score(2000));
+        boards.set_pet("fin", "fish", level_score(1, 0), age_score(500));
+
+        let ids = |ranking: &Ranking| ranking.page(0, 10).into_iter().map(|(_, id, _)| id.clone()).collect::<Vec<_>>();
+        let levels = boards.get(Board::HighestLevel, None).unwrap();
+        assert_eq!(ids(levels), vec!["tom", "rex", "fin"]);
+        assert_eq!(ids(boards.get(Board::LongestLiving, None).unwrap()), vec!["fin", "rex", "tom"]);
+
+        // Levelling up moves a pet up, and changing species moves it between species boards
+        boards.set_pet("fin", "cat", level_score(4, 0), age_score(500));
+        assert_eq!(boards.get(Board::HighestLevel, None).unwrap().page(0, 1)[0], (1, &"fin".to_string(), level_score(4, 0)));
+        assert_eq!(boards.get(Board::HighestLevel, Some("fish")).unwrap().len(), 0);
+        assert_eq!(ids(boards.get(Board::HighestLevel, Some("cat")).unwrap()), vec!["fin", "tom"]);
+
+        // Pages carry on the ranks from where the last one stopped
+        assert_eq!(boards.get(Board::HighestLevel, None).unwrap().page(1, 5)[0].0, 2);
+
+        boards.remove_pet("fin");
+        assert_eq!(ids(boards.get(Board::HighestLevel, Some("cat")).unwrap()), vec!["tom"]);
+        assert_eq!(boards.get(Board::LongestLiving, None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_live_boards_match_a_rebuild() {
+        let (mut state, clock) = mock_state(Config::default());
+        let (_, rex) = add_user_with_pet(&mut state, "bob");
+
+        // Bob's pet is left to starve, while the others are born later
+        clock.advance(70 * MS_PER_HOUR);
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (carol, fin) = add_user_with_pet(&mut state, "carol");
+        assert_matches_rebuild(&state);
+
+        // A level up, which also unlocks achievements
+        let mut pet = state.get_pet_by_uuid(&cat).unwrap().clone();
+        pet.add_experience(500, state.now(), &state.config);
+        let level = pet.get_level();
+        state.update_pet(pet);
+        state.record_event(&alice, GameEvent::LevelReached, level as u64);
+        assert_matches_rebuild(&state);
+
+        // A death and burial
+        clock.advance(3 * MS_PER_HOUR);
+        state.kill_unloved_pets();
+        assert!(state.get_grave_by_uuid(&rex).is_some());
+        assert_matches_rebuild(&state);
+
+        // A transfer
+        let offer = state.offer_pet_transfer(&alice, &cat, &carol).unwrap();
+        state.accept_pet_transfer(&carol, &offer.uuid).unwrap();
+        assert_matches_rebuild(&state);
+
+        // A change of species
+        let mut pet = state.get_pet_by_uuid(&fin).unwrap().clone();
+        pet.set_species("dog".to_string()).unwrap();
+        state.update_pet(pet);
+        assert_matches_rebuild(&state);
+
+        let ids = |species| {
+            state.leaderboards.get(Board::HighestLevel, species).unwrap().page(0, 10).into_iter().map(|(_, id, _)| id.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(None), vec![cat.clone(), fin.clone()]);
+        assert_eq!(ids(Some("cat")), vec![cat]);
+        assert_eq!(ids(Some("dog")), vec![fin]);
+    }
+}
@@ -0,0 +1,191 @@
+/*
+
+This file handles how pets level up.
+
+Each level needs a certain amount of experience to get through, set by a curve.
+Experience left over after a level up carries over into the next level.
+
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::cooldowns::PetAction;
+
+/// How much experience it takes to get through each level
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "curve", rename_all = "lowercase", deny_unknown_fields)]
+pub enum LevelCurve {
+    // Level 1 needs base, and each level after needs per_level more than the last
+    Linear { base: u64, per_level: u64 },
+    // Level 1 needs base, and each level after needs factor times the last
+    Exponential { base: u64, factor: f64 },
+    // Each level needs the amount at its position, levels past the end use the last amount
+    Table { levels: Vec<u64> },
+}
+
+impl LevelCurve {
+    /// Experience needed to go from the given level to the next one
+    pub fn experience_for(&self, level: u32) -> u64 {
+        let steps = level.saturating_sub(1);
+
+        match self {
+            LevelCurve::Linear { base, per_level } => {
+                base.saturating_add(per_level.saturating_mul(steps as u64))
+            }
+            // Casting saturates, so huge levels just need u64::MAX
+            LevelCurve::Exponential { base, factor } => {
+                (*base as f64 * factor.powi(steps as i32)).round() as u64
+            }
+            LevelCurve::Table { levels } => {
+                levels.get(steps as usize).or(levels.last()).copied().unwrap_or(u64::MAX)
+            }
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match self {
+            LevelCurve::Linear { base, .. } => *base > 0,
+            LevelCurve::Exponential { base, factor } => {
+                *base > 0 && factor.is_finite() && *factor >= 1.0
+            }
+            LevelCurve::Table { levels } => !levels.is_empty() && levels.iter().all(|amount| *amount > 0),
+        }
+    }
+}
+
+/// Experience given for each action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActionExperience {
+    pub feed: u64,
+    pub pet: u64,
+    pub medicine: u64,
+    pub rest: u64,
+    pub clean: u64,
+    // For a perfect score in a mini-game, scaled down for lower ones
+    pub play: u64,
+}
+
+impl ActionExperience {
+    pub fn for_action(&self, action: PetAction) -> u64 {
+        match action {
+            PetAction::Feed => self.feed,
+            PetAction::Pet => self.pet,
+            PetAction::Medicine => self.medicine,
+            PetAction::Rest => self.rest,
+            PetAction::Clean => self.clean,
+            PetAction::Play => self.play,
+        }
+    }
+}
+
+impl Default for ActionExperience {
+    fn default() -> Self {
+        Self {
+            feed: 1,
+            pet: 5,
+            medicine: 0,
+            rest: 0,
+            clean: 0,
+            play: 50,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LevelingConfig {
+    pub experience_curve: LevelCurve,
+    // Pets stop gaining experience once they reach this level
+    pub max_level: u32,
+    pub action_experience: ActionExperience,
+}
+
+impl Default for LevelingConfig {
+    fn default() -> Self {
+        // A flat 100 experience per level, as before
+        Self {
+            experience_curve: LevelCurve::Linear { base: 100, per_level: 0 },
+            max_level: 100,
+            action_experience: ActionExperience::default(),
+        }
+    }
+}
+
+impl LevelingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.experience_curve.is_valid() {
+            return Err("leveling.experience_curve must need more than 0 experience for every level".to_string());
+        }
+
+        if self.max_level == 0 {
+            return Err("leveling.max_level must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Experience needed to reach the next level, or None if the pet is at the level cap
+    pub fn experience_to_next(&self, level: u32) -> Option<u64> {
+        (level < self.max_level).then(|| self.experience_curve.experience_for(level))
+    }
+}
+
+/// Adds experience to a level and experience pair, levelling up as many times as it covers.
+/// Returns the new level and experience. Experience is dropped once the level cap is reached.
+pub fn gain_experience(level: u32, experience: u64, amount: u64, config: &LevelingConfig) -> (u32, u64) {
+    let mut level = level;
+    let mut experience = experience.saturating_add(amount);
+
+    while let Some(needed) = config.experience_to_next(level) {
+        if experience < needed {
+            return (level, experience);
+        }
+
+        experience -= needed;
+        level += 1;
+    }
+
+    (level, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curves() {
+        let linear = LevelCurve::Linear { base: 100, per_level: 50 };
+        assert_eq!(linear.experience_for(1), 100);
+        assert_eq!(linear.experience_for(3), 200);
+
+        let exponential = LevelCurve::Exponential { base: 100, factor: 1.5 };
+        assert_eq!(exponential.experience_for(1), 100);
+        assert_eq!(exponential.experience_for(3), 225);
+
+        let table = LevelCurve::Table { levels: vec![10, 20, 40] };
+        assert_eq!(table.experience_for(2), 20);
+        assert_eq!(table.experience_for(10), 40);
+    }
+
+    #[test]
+    fn test_experience_carries_over() {
+        let config = LevelingConfig::default();
+
+        // Large amounts no longer overflow, and cover several levels at once
+        assert_eq!(gain_experience(1, 90, 1000, &config), (11, 90));
+        assert_eq!(gain_experience(1, 0, 99, &config), (1, 99));
+    }
+
+    #[test]
+    fn test_level_cap() {
+        let config = LevelingConfig {
+            max_level: 3,
+            ..LevelingConfig::default()
+        };
+
+        assert_eq!(gain_experience(1, 0, 10_000, &config), (3, 0));
+        assert_eq!(gain_experience(3, 0, 50, &config), (3, 0));
+        assert_eq!(config.experience_to_next(3), None);
+    }
+}
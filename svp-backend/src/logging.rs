@@ -0,0 +1,278 @@
+/*
+
+This file sets up logging to stdout and to a log file.
+
+Logs can be compact text or JSON, the log file can be rotated by time or size,
+and tokens and passwords are redacted before anything is written.
+
+*/
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::config::{LogFormat, LogRotation, LoggingConfig};
+
+/// A subscriber layer that can be stacked with the logging layers
+pub type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+const REDACTED: &str = "[REDACTED]";
+
+// Tokens passed as the last path segment of the auth routes
+static TOKEN_PATH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(/auth/(?:logout|refresh_token)/[^/\s]+/)[^/\s?#&\x22]+").unwrap());
+
+// Secrets passed as JSON fields, query parameters or headers
+static SECRET_FIELD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)((?:password|token|x-auth-key)\\?"?\s*[:=]\s*\\?"?)[^"\\&\s,}]+"#).unwrap()
+});
+
+/// Replaces tokens and passwords in the text with a placeholder
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let text = TOKEN_PATH.replace_all(text, format!("${{1}}{}", REDACTED));
+
+    match SECRET_FIELD.replace_all(&text, format!("${{1}}{}", REDACTED)) {
+        Cow::Borrowed(_) => text,
+        Cow::Owned(redacted) => Cow::Owned(redacted),
+    }
+}
+
+/// Sets up the global subscriber, along with any extra layers such as the OpenTelemetry exporter.
+/// The returned guard must be kept alive until shutdown,
+/// dropping it flushes any logs still waiting to be written to the file.
+pub fn init(
+    config: &LoggingConfig,
+    log_file: &Path,
+    extra_layers: Vec<BoxedLayer>,
+) -> Result<WorkerGuard, Box<dyn Error>> {
+    let (file_writer, guard) = match config.rotation {
+        LogRotation::Size => tracing_appender::non_blocking(SizeRotatingWriter::new(
+            log_file.to_path_buf(),
+            config.max_file_size_mb * 1024 * 1024,
+            config.max_files,
+        )?),
+        rotation => tracing_appender::non_blocking(time_rotating_appender(
+            rotation,
+            config.max_files,
+            log_file,
+        )?),
+    };
+
+    // RUST_LOG takes priority over the configured level
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.level))?;
+
+    let mut layers = vec![
+        fmt_layer(config.format, RedactingMakeWriter(file_writer), false),
+        fmt_layer(config.format, RedactingMakeWriter(std::io::stdout), true),
+    ];
+    layers.extend(extra_layers);
+
+    let subscriber = tracing_subscriber::registry()
+        .with(layers)
+        .with(filter);
+
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    Ok(guard)
+}
+
+fn fmt_layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_target(false);
+
+    match format {
+        LogFormat::Compact => layer.compact().with_ansi(ansi).boxed(),
+        LogFormat::Json => layer.json().with_ansi(false).boxed(),
+    }
+}
+
+fn time_rotating_appender(
+    rotation: LogRotation,
+    max_files: usize,
+    log_file: &Path,
+) -> Result<RollingFileAppender, Box<dyn Error>> {
+    let directory = log_file.parent().unwrap_or(Path::new(""));
+    let file_name = log_file
+        .file_name()
+        .ok_or("log file path has no file name")?
+        .to_string_lossy()
+        .to_string();
+
+    let rotation = match rotation {
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        _ => Rotation::NEVER,
+    };
+
+    // Retention only applies to rotated files
+    let mut builder = RollingFileAppender::builder()
+        .rotation(rotation.clone())
+        .filename_prefix(file_name);
+
+    if rotation != Rotation::NEVER {
+        builder = builder.max_log_files(max_files);
+    }
+
+    Ok(builder.build(directory)?)
+}
+
+/// Wraps a writer so everything written through it is redacted first
+struct RedactingMakeWriter<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // Each log line arrives in a single write, so secrets are never split across calls
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Writes to a file, moving it aside once it reaches the size limit.
+/// Older files are kept as `<file>.1`, `<file>.2`, ... up to the retention limit.
+pub struct SizeRotatingWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl SizeRotatingWriter {
+    pub fn new(path: PathBuf, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            written,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+
+        // Drop the oldest file, then shift the rest up by one
+        let _ = std::fs::remove_file(self.rotated_path(self.max_files));
+
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+
+        std::fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+
+        Ok(())
+    }
+}
+
+impl Write for SizeRotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_token_paths() {
+        let uri = "https://localhost:3000/auth/refresh_token/user-uuid/secret-token";
+        assert_eq!(
+            redact(uri),
+            "https://localhost:3000/auth/refresh_token/user-uuid/[REDACTED]"
+        );
+    }
+
+    #[test]
+    fn test_redact_fields() {
+        let body = r#"{"username":"bob","password":"hunter2"}"#;
+        assert_eq!(redact(body), r#"{"username":"bob","password":"[REDACTED]"}"#);
+
+        let escaped = r#"{"body":"{\"password\":\"hunter2\"}"}"#;
+        assert!(!redact(escaped).contains("hunter2"));
+
+        let query = "/users/abc?token=abc123&page=2";
+        assert_eq!(redact(query), "/users/abc?token=[REDACTED]&page=2");
+    }
+
+    #[test]
+    fn test_redact_leaves_other_text_alone() {
+        let line = "request processed status=200 http.uri=/public/pet/1234";
+        assert!(matches!(redact(line), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_size_rotation_keeps_max_files() {
+        let dir = std::env::temp_dir().join(format!("svp-log-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("svp.log");
+
+        let mut writer = SizeRotatingWriter::new(path.clone(), 10, 2).unwrap();
+        for _ in 0..5 {
+            writer.write_all(b"0123456789").unwrap();
+        }
+
+        assert!(path.exists());
+        assert!(dir.join("svp.log.1").exists());
+        assert!(dir.join("svp.log.2").exists());
+        assert!(!dir.join("svp.log.3").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,10 +1,10 @@
 use aide::redoc::Redoc;
 use axum::{error_handling::HandleErrorLayer, http, Extension, Json};
-use axum_server::tls_rustls::RustlsConfig;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
 
 use aide::{
     axum::{
-        routing::{get, patch, post},
+        routing::{delete, get, patch, post},
         ApiRouter, IntoApiResponse,
     },
     openapi::OpenApi,
@@ -17,9 +17,8 @@ use axum::{
     response::Response,
 };
 use futures::future::{BoxFuture, FutureExt};
-use tracing_subscriber::layer::SubscriberExt;
 use std::{
-    sync::Arc,
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
     task::{Context, Poll},
 };
 use tower::{Layer, Service, ServiceBuilder, buffer::BufferLayer, limit::RateLimitLayer,};
@@ -27,33 +26,89 @@ use tower_http::{trace::TraceLayer, BoxError};
 
 use once_cell::sync::Lazy;
 use std::{net::SocketAddr, time::Duration};
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex, MutexGuard};
 use tokio::time;
 
 use tower_http::trace::{self};
-use tracing::{Span};
+use tracing::{Instrument, Span};
 
+mod achievements;
+mod art;
 mod auth;
+mod breeding;
+mod clock;
+mod config;
+mod contests;
+mod cooldowns;
+mod economy;
 mod encryption;
+mod graveyard;
+mod growth;
+mod health;
+mod items;
+mod leaderboards;
+mod leveling;
+mod logging;
+mod metrics;
+mod minigames;
+mod needs;
+mod quests;
 mod routes;
+#[cfg(test)]
+mod simulation;
+mod species;
 mod structs;
+mod telemetry;
+mod trading;
+mod transfers;
 mod utils;
+mod vacations;
 
 
 
+use crate::config::Config;
 use crate::structs::*;
 use crate::utils::*;
 
+use crate::metrics::RequestLabels;
 use crate::routes::routes_auth::*;
+use crate::routes::routes_graveyard::*;
+use crate::routes::routes_species::*;
+use crate::routes::routes_art::*;
+use crate::routes::routes_breeding::*;
+use crate::routes::routes_contests::*;
+use crate::routes::routes_health::*;
+use crate::routes::routes_items::*;
+use crate::routes::routes_leaderboards::*;
+use crate::routes::routes_minigames::*;
 use crate::routes::routes_pet_yards::*;
 use crate::routes::routes_pets::*;
 use crate::routes::routes_public::*;
+use crate::routes::routes_quests::*;
+use crate::routes::routes_shop::*;
+use crate::routes::routes_trading::*;
+use crate::routes::routes_transfers::*;
 use crate::routes::routes_users::*;
+use crate::routes::routes_vacations::*;
 
 // Create an arc mutex of AppState
 pub static APP_STATE: Lazy<Arc<Mutex<AppState>>> =
     Lazy::new(|| Arc::new(Mutex::new(AppState::new())));
 
+/// Locks the app state, tracing how long the wait for the lock took
+pub async fn lock_app_state() -> MutexGuard<'static, AppState> {
+    APP_STATE
+        .lock()
+        .instrument(tracing::info_span!("state.lock"))
+        .await
+}
+
+// Set once the saved state has been loaded, used by the readiness check
+pub static STATE_LOADED: AtomicBool = AtomicBool::new(false);
+
+// Makes sure only one save writes to the state file at a time
+static SAVE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
 #[tokio::main]
 async fn main() {
     aide::gen::on_error(|error| {
@@ -64,51 +119,65 @@ async fn main() {
 
     let mut api = OpenApi::default();
 
-    // Load state.json into APP_STATE
-    let state = std::fs::read_to_string("state.json");
+    // Load the config from the config file, environment and command line
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Set up trace exporting, if it is turned on
+    let (tracer_provider, telemetry_layers) = match telemetry::init(&config.telemetry) {
+        Ok(Some((provider, layer))) => (Some(provider), vec![layer]),
+        Ok(None) => (None, vec![]),
+        Err(e) => {
+            eprintln!("Failed to set up trace exporting: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Keep the guard alive so buffered logs are flushed on shutdown
+    let _log_guard = match logging::init(&config.logging, &config.paths.log_file, telemetry_layers) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Failed to set up logging: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Load the saved state into APP_STATE
+    let state = std::fs::read_to_string(&config.paths.state_file);
 
     if let Ok(state) = state {
         *APP_STATE.lock().await = serde_json::from_str(&state).unwrap();
     }
 
+    APP_STATE.lock().await.set_config(config.clone());
+    APP_STATE.lock().await.set_missing_births();
+    APP_STATE.lock().await.rebuild_leaderboards();
+
+    if let Err(e) = APP_STATE.lock().await.audit_coins() {
+        tracing::warn!("Coin balances don't match the ledger: {}", e);
+    }
+
+    STATE_LOADED.store(true, Ordering::SeqCst);
+
     // Decide on what address to run the server
-    let addr = if cfg!(debug_assertions) {
-        SocketAddr::from(([127, 0, 0, 1], 3000))
-    } else {
-        SocketAddr::from(([0, 0, 0, 0], 3000))
-    };
+    let addr = config.socket_addr();
 
     println!("Listening on {}", addr);
 
+    // Tells background tasks to stop once a shutdown signal arrives
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
     // Spawn pet killer
-    tokio::spawn(check_kill_pets());
-    
-    let file_appender = tracing_appender::rolling::never("", "svp.log");
-    let (file_writer, _guard) = tracing_appender::non_blocking(file_appender);
-    
-    // Create a layer for logging to a file
-    let file_layer = tracing_subscriber::fmt::layer()
-        .with_writer(file_writer)
-        .with_target(false)
-        .compact()
-        .with_ansi(false);
-
-    // Create a layer for logging to stdout
-    let stdout_layer = tracing_subscriber::fmt::layer()
-        .with_writer(std::io::stdout)
-        .with_target(false)
-        .compact()
-        .with_ansi(true);
-
-    // Combine the layers on top of a `Registry`
-    let subscriber = tracing_subscriber::registry()
-        .with(file_layer)
-        .with(stdout_layer)
-        .with(tracing_subscriber::EnvFilter::new("info"));
-
-    // Set the combined subscriber as the global default
-    tracing::subscriber::set_global_default(subscriber)
-        .expect("setting default subscriber failed");
+    let pet_killer = tokio::spawn(check_kill_pets(
+        APP_STATE.clone(),
+        Duration::from_secs(config.pets.kill_check_interval_secs),
+        shutdown_rx,
+    ));
 
     let trace_layer = TraceLayer::new_for_http()
         .make_span_with(CustomMakeSpan)
@@ -116,6 +185,9 @@ async fn main() {
 
     let app = ApiRouter::new()
         .route("/", get(index))
+        .route("/healthz", get(route_healthz))
+        .route("/readyz", get(route_readyz))
+        .route("/metrics", get(route_metrics))
         .route("/redoc", Redoc::new("/api.json").axum_route())
         // Routes for authentication
         .api_route("/auth/login", post(route_login))
@@ -144,7 +216,111 @@ async fn main() {
             "/users/:user_uuid/pets/:pet_uuid/pet",
             post(route_pet_pet)
         )
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/medicine",
+            post(route_give_medicine_to_pet)
+        )
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/rest",
+            post(route_rest_pet)
+        )
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/clean",
+            post(route_clean_pet)
+        )
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/use_item/:item_id",
+            post(route_use_item)
+        )
         .api_route("/users/:user_uuid/pets/new", post(route_create_pet))
+        .api_route("/users/:uuid/inventory", get(route_get_inventory))
+        .api_route("/users/:uuid/achievements", get(route_get_achievements))
+        // Routes for coins and the shop
+        .api_route("/users/:uuid/ledger", get(route_get_ledger))
+        .api_route("/users/:user_uuid/shop/:item_id", post(route_buy_item))
+        // Routes for mini-games
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/games/:kind",
+            post(route_start_game),
+        )
+        .api_route("/users/:uuid/games", get(route_get_games))
+        .api_route("/users/:user_uuid/games/:session_uuid/move", post(route_play_game))
+        // Routes for vacations and pet-sitters
+        .api_route("/users/:uuid/vacation", post(route_start_vacation).delete(route_end_vacation))
+        .api_route("/users/:uuid/sitters", get(route_get_pet_sittings).post(route_start_pet_sitting))
+        .api_route("/users/:user_uuid/sitters/:sitting_uuid", delete(route_end_pet_sitting))
+        // Routes for daily quests
+        .api_route("/users/:uuid/quests", get(route_get_quests))
+        .api_route("/users/:user_uuid/quests/:quest_id/claim", post(route_claim_quest))
+        // Routes for contests
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/challenge/:opponent_uuid",
+            post(route_challenge_pet),
+        )
+        .api_route("/users/:uuid/challenges", get(route_get_contest_challenges))
+        .api_route(
+            "/users/:user_uuid/challenges/:challenge_uuid/accept",
+            post(route_accept_contest_challenge),
+        )
+        .api_route(
+            "/users/:user_uuid/challenges/:challenge_uuid/decline",
+            post(route_decline_contest_challenge),
+        )
+        .api_route("/users/:uuid/battles", get(route_get_battle_logs))
+        // Routes for breeding
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/breed/:partner_uuid",
+            post(route_breed_pets),
+        )
+        .api_route("/users/:uuid/breeding_requests", get(route_get_breeding_requests))
+        .api_route(
+            "/users/:user_uuid/breeding_requests/:request_uuid/accept",
+            post(route_accept_breeding_request),
+        )
+        .api_route(
+            "/users/:user_uuid/breeding_requests/:request_uuid/decline",
+            post(route_decline_breeding_request),
+        )
+        // Routes for transferring and adopting pets
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/transfer/:to_user_uuid",
+            post(route_offer_pet_transfer),
+        )
+        .api_route("/users/:uuid/transfers", get(route_get_transfers))
+        .api_route(
+            "/users/:user_uuid/transfers/:offer_uuid/accept",
+            post(route_accept_pet_transfer),
+        )
+        .api_route(
+            "/users/:user_uuid/transfers/:offer_uuid/decline",
+            post(route_decline_pet_transfer),
+        )
+        .api_route(
+            "/users/:user_uuid/pets/:pet_uuid/surrender",
+            post(route_surrender_pet),
+        )
+        .api_route("/users/:user_uuid/adopt/:pet_uuid", post(route_adopt_pet))
+        // Routes for trading
+        .api_route("/users/:user_uuid/trade/:to_user_uuid", post(route_propose_trade))
+        .api_route("/users/:uuid/trades", get(route_get_trades))
+        .api_route(
+            "/users/:user_uuid/trades/:offer_uuid/accept",
+            post(route_accept_trade),
+        )
+        .api_route(
+            "/users/:user_uuid/trades/:offer_uuid/counter",
+            post(route_counter_trade),
+        )
+        .api_route(
+            "/users/:user_uuid/trades/:offer_uuid/decline",
+            post(route_decline_trade),
+        )
+        // Routes for the graveyard
+        .api_route("/users/:uuid/graveyard", get(route_get_graveyard))
+        .api_route(
+            "/users/:user_uuid/graveyard/:pet_uuid/revive",
+            post(route_revive_pet),
+        )
         // Routes for petyards
         .api_route(
             "/users/:user_uuid/pet_yards/:pet_yard_uuid",
@@ -168,6 +344,18 @@ async fn main() {
         .api_route("/public/user/:uuid", get(route_get_public_user))
         .api_route("/public/pet/:uuid", get(route_get_public_pet))
         .api_route("/public/pet_yard/:uuid", get(route_get_public_pet_yard))
+        .api_route("/public/memorial/:uuid", get(route_get_public_memorial))
+        .api_route("/public/adoption", get(route_get_adoption_center))
+        .api_route("/public/battles/:battle_uuid", get(route_get_public_battle_log))
+        .api_route("/public/leaderboards", get(route_get_leaderboards))
+        .api_route("/public/leaderboards/:board", get(route_get_leaderboard))
+        // Routes for the species registry
+        .api_route("/species", get(route_get_species))
+        // Routes for the item catalog
+        .api_route("/items", get(route_get_items))
+        .api_route("/shop", get(route_get_shop))
+        // Routes for the art catalog
+        .api_route("/art/:species/:index", get(route_get_art))
         .route("/api.json", get(route_api_json))
         .layer(AppStateSaverLayer)
         .layer(
@@ -178,25 +366,38 @@ async fn main() {
                         format!("Unhandled error: {}", err),
                     )
                 }))
-                .layer(BufferLayer::new(1024))
-                .layer(RateLimitLayer::new(100, Duration::from_secs(1))))
+                .layer(BufferLayer::new(config.rate_limit.buffer_size))
+                .layer(RateLimitLayer::new(
+                    config.rate_limit.requests,
+                    Duration::from_secs(config.rate_limit.period_secs),
+                )))
+        .layer(axum::middleware::from_fn(attach_request_labels))
         .layer(trace_layer);
 
     // If the paths are not found, create the pem files
-    if !std::path::Path::new("cert.pem").exists() {
+    if !config.paths.cert_file.exists() {
         let (cert, key) = create_cert().unwrap();
 
         // Write the pem files
-        std::fs::write("cert.pem", cert).unwrap();
-        std::fs::write("key.pem", key).unwrap();
+        std::fs::write(&config.paths.cert_file, cert).unwrap();
+        std::fs::write(&config.paths.key_file, key).unwrap();
     }
 
-    let config: RustlsConfig = RustlsConfig::from_pem_file("cert.pem", "key.pem")
+    let tls_config: RustlsConfig = RustlsConfig::from_pem_file(&config.paths.cert_file, &config.paths.key_file)
         .await
         .unwrap();
 
+    let handle = Handle::new();
+
+    tokio::spawn(shutdown_on_signal(
+        handle.clone(),
+        shutdown_tx,
+        Duration::from_secs(config.server.shutdown_timeout_secs),
+    ));
+
     // create app with bind_tls
-    axum_server::bind_rustls(addr, config)
+    axum_server::bind_rustls(addr, tls_config)
+        .handle(handle)
         .serve(
             app.finish_api_with(&mut api, api_docs)
                 .layer(Extension(api))
@@ -204,24 +405,107 @@ async fn main() {
         )
         .await
         .unwrap();
+
+    let saved = finish_jobs_and_save(pet_killer, &APP_STATE).await;
+
+    // Send any spans still waiting to be exported
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = tokio::task::spawn_blocking(move || provider.shutdown()).await {
+            tracing::warn!("Failed to flush traces: {}", e);
+        }
+    }
+
+    match saved {
+        Ok(()) => tracing::info!("Saved state, shutdown complete"),
+        Err(e) => {
+            tracing::error!("Failed to save state on shutdown: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
 
+/// Waits for either Ctrl+C or SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for Ctrl+C");
+    };
 
-async fn check_kill_pets() {
-    // Define a one-minute interval
-    let mut interval = time::interval(Duration::from_secs(60));
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
 
-    loop {
-        // Wait for the next interval tick
-        interval.tick().await;
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Stops background tasks and lets in-flight requests drain once a shutdown signal arrives
+async fn shutdown_on_signal(handle: Handle, shutdown_tx: watch::Sender<bool>, drain_timeout: Duration) {
+    shutdown_signal().await;
 
-        tracing::info!("Checking which pets to kill!");
+    tracing::info!(
+        "Shutdown signal received, draining connections for up to {}s",
+        drain_timeout.as_secs()
+    );
 
-        let mut state = APP_STATE.lock().await;
+    let _ = shutdown_tx.send(true);
+
+    handle.graceful_shutdown(Some(drain_timeout));
+}
+
+
+/// Waits for the background jobs to finish their current pass, which they do once told
+/// to shut down, then saves the state one last time
+async fn finish_jobs_and_save(jobs: tokio::task::JoinHandle<()>, state: &Mutex<AppState>) -> std::io::Result<()> {
+    let _ = jobs.await;
+
+    save_state(state).await
+}
 
-        state.kill_unloved_pets();
+async fn check_kill_pets(app_state: Arc<Mutex<AppState>>, period: Duration, mut shutdown: watch::Receiver<bool>) {
+    // Define the interval between checks
+    let mut interval = time::interval(period);
 
-        drop(state);
+    loop {
+        // Wait for the next interval tick, or stop if the server is shutting down
+        tokio::select! {
+            _ = interval.tick() => {},
+            _ = shutdown.changed() => {
+                tracing::info!("Stopping pet killer");
+                break;
+            }
+        }
+
+        async {
+            tracing::info!("Checking which pets to kill!");
+
+            let mut state = app_state
+                .lock()
+                .instrument(tracing::info_span!("state.lock"))
+                .await;
+
+            state.pause_vacationing_pets();
+            state.kill_unloved_pets();
+            state.expire_transfer_offers();
+            state.expire_breeding_requests();
+            state.expire_trade_offers();
+            state.expire_game_sessions();
+            state.expire_contest_challenges();
+            state.expire_pet_sittings();
+            state.record_pet_ages();
+        }
+        .instrument(tracing::info_span!("job.kill_unloved_pets"))
+        .await;
     }
 }
 
@@ -238,13 +522,20 @@ impl<B> trace::MakeSpan<B> for CustomMakeSpan {
             .map(SocketAddr::to_string)
             .unwrap_or_else(|| "-".to_string());
 
-        tracing::info_span!(
+        let span = tracing::info_span!(
             "request",
             http.method = %request.method(),
-            http.uri = %request.uri(),
+            http.uri = %logging::redact(&request.uri().to_string()),
             http.remote_addr = %remote_addr,
+            http.status_code = tracing::field::Empty,
+            http.response_content_length = tracing::field::Empty,
             otel.kind = "server",
-        )
+        );
+
+        // Continue the caller's trace if it sent a traceparent header
+        telemetry::set_parent_from_headers(&span, request.headers());
+
+        span
     }
 }
 
@@ -262,10 +553,14 @@ impl<B> trace::OnResponse<B> for CustomOnResponse {
 
         span.record(
             "http.status_code",
-            &tracing::field::display(status.as_u16()),
+            tracing::field::display(status.as_u16()),
         );
         span.record("http.response_content_length", length);
 
+        if let Some(labels) = response.extensions().get::<RequestLabels>() {
+            metrics::record_request(labels, status.as_u16(), latency);
+        }
+
         let latency_secs = latency.as_secs_f32();
 
         tracing::info!(
@@ -277,6 +572,24 @@ impl<B> trace::OnResponse<B> for CustomOnResponse {
     }
 }
 
+/// Copies the method and matched route onto the response, so `CustomOnResponse` can label its metrics
+async fn attach_request_labels(request: Request<BoxBody>, next: axum::middleware::Next) -> Response {
+    let labels = RequestLabels {
+        method: request.method().to_string(),
+        route: request
+            .extensions()
+            .get::<axum::extract::MatchedPath>()
+            .map(|path| path.as_str().to_string())
+            .unwrap_or_else(|| "unmatched".to_string()),
+    };
+
+    let mut response = next.run(request).await;
+
+    response.extensions_mut().insert(labels);
+
+    response
+}
+
 #[derive(Debug, Clone)]
 struct AppStateSaverLayer;
 
@@ -316,17 +629,37 @@ where
             let response = future.await?;
 
             // Save state to a file after request handling
-            save_state_to_file().await;
+            if let Err(e) = save_state_to_file().await {
+                tracing::error!("Failed to save state: {}", e);
+            }
 
             Ok(response)
         }.boxed()
     }
 }
 
-async fn save_state_to_file() {
-    let state = serde_json::to_string(&*APP_STATE.lock().await).unwrap();
+async fn save_state_to_file() -> std::io::Result<()> {
+    save_state(&APP_STATE).await
+}
+
+/// Writes the state to its state file
+#[tracing::instrument(name = "state.save", skip_all)]
+async fn save_state(state: &Mutex<AppState>) -> std::io::Result<()> {
+    let _save_guard = SAVE_LOCK.lock().await;
+
+    let _timer = metrics::STATE_SAVE_DURATION.start_timer();
+
+    let app_state = state
+        .lock()
+        .instrument(tracing::info_span!("state.lock"))
+        .await;
+
+    let path = app_state.config.paths.state_file.clone();
+    let state = serde_json::to_string(&*app_state)?;
 
-    std::fs::write("state.json", state).unwrap();
+    drop(app_state);
+
+    write_file_atomically(&path, state.as_bytes())
 }
 
 async fn route_api_json(Extension(api): Extension<OpenApi>) -> impl IntoApiResponse {
@@ -351,3 +684,70 @@ fn api_docs(api: TransformOpenApi) -> TransformOpenApi {
 async fn index() -> impl IntoApiResponse {
     "Hello, World!"
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::{add_user_with_pet, mock_state};
+
+    #[tokio::test]
+    async fn test_shutdown_stops_jobs_and_saves_state() {
+        let dir = std::env::temp_dir().join(format!("svp-shutdown-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut config = Config::default();
+        config.paths.state_file = dir.join("state.json");
+        let (mut state, _) = mock_state(config.clone());
+        let (user_uuid, pet_uuid) = add_user_with_pet(&mut state, "alice");
+        let state = Arc::new(Mutex::new(state));
+
+        // A long period, so the job is waiting on its next tick when the signal comes
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let jobs = tokio::spawn(check_kill_pets(state.clone(), Duration::from_secs(60 * 60), shutdown_rx));
+
+        shutdown_tx.send(true).unwrap();
+        time::timeout(Duration::from_secs(5), finish_jobs_and_save(jobs, &state))
+            .await
+            .expect("the jobs didn't stop")
+            .unwrap();
+
+        let saved: AppState = serde_json::from_str(&std::fs::read_to_string(&config.paths.state_file).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(saved.get_user_by_uuid(&user_uuid).is_some());
+        assert!(saved.get_pet_by_uuid(&pet_uuid).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_requests_by_route() {
+        use tower::ServiceExt;
+
+        async fn ping() -> &'static str {
+            "pong"
+        }
+
+        let app = axum::Router::new()
+            .route("/users/:uuid/ping", axum::routing::get(ping))
+            .route("/metrics", axum::routing::get(route_metrics))
+            .layer(axum::middleware::from_fn(attach_request_labels))
+            .layer(TraceLayer::new_for_http().on_response(CustomOnResponse));
+
+        let uuid = uuid::Uuid::new_v4().to_string();
+        let request = Request::get(format!("/users/{}/ping", uuid)).body(BoxBody::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let request = Request::get("/metrics").body(BoxBody::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        // Requests are counted under the route they matched, not the UUID in the path
+        let counter = body
+            .lines()
+            .find(|line| line.starts_with("svp_http_requests_total") && line.contains(r#"route="/users/:uuid/ping""#))
+            .expect("the request wasn't counted");
+        assert!(counter.contains(r#"method="GET""#) && counter.contains(r#"status="200""#));
+        assert!(!body.contains(&uuid));
+    }
+}
@@ -0,0 +1,107 @@
+/*
+
+This file defines the Prometheus metrics exported on /metrics.
+
+*/
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts,
+    Registry, TextEncoder,
+};
+use std::time::Duration;
+
+use crate::structs::AppState;
+
+pub static REGISTRY: Lazy<Registry> = Lazy::new(|| {
+    let registry = Registry::new();
+
+    registry.register(Box::new(HTTP_REQUESTS.clone())).unwrap();
+    registry.register(Box::new(HTTP_REQUEST_DURATION.clone())).unwrap();
+    registry.register(Box::new(PETS_KILLED.clone())).unwrap();
+    registry.register(Box::new(STATE_SAVE_DURATION.clone())).unwrap();
+    registry.register(Box::new(ACTIVE_SESSIONS.clone())).unwrap();
+    registry.register(Box::new(USERS.clone())).unwrap();
+    registry.register(Box::new(PETS.clone())).unwrap();
+    registry.register(Box::new(PET_YARDS.clone())).unwrap();
+
+    registry
+});
+
+pub static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    IntCounterVec::new(
+        Opts::new("svp_http_requests_total", "Number of HTTP requests handled"),
+        &["method", "route", "status"],
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    HistogramVec::new(
+        HistogramOpts::new(
+            "svp_http_request_duration_seconds",
+            "Time taken to handle HTTP requests",
+        ),
+        &["method", "route"],
+    )
+    .unwrap()
+});
+
+pub static PETS_KILLED: Lazy<IntCounter> = Lazy::new(|| {
+    IntCounter::new("svp_pets_killed_total", "Number of pets killed for being unloved").unwrap()
+});
+
+pub static STATE_SAVE_DURATION: Lazy<Histogram> = Lazy::new(|| {
+    Histogram::with_opts(HistogramOpts::new(
+        "svp_state_save_duration_seconds",
+        "Time taken to save the app state to disk",
+    ))
+    .unwrap()
+});
+
+// The gauges below are refreshed from the app state on every scrape
+pub static ACTIVE_SESSIONS: Lazy<IntGauge> = Lazy::new(|| {
+    IntGauge::new("svp_active_sessions", "Number of unexpired user tokens").unwrap()
+});
+
+pub static USERS: Lazy<IntGauge> =
+    Lazy::new(|| IntGauge::new("svp_users", "Number of users").unwrap());
+
+pub static PETS: Lazy<IntGauge> =
+    Lazy::new(|| IntGauge::new("svp_pets", "Number of living pets").unwrap());
+
+pub static PET_YARDS: Lazy<IntGauge> =
+    Lazy::new(|| IntGauge::new("svp_pet_yards", "Number of pet yards").unwrap());
+
+/// Labels for a request, attached to the response so they can be read once it is done
+#[derive(Debug, Clone)]
+pub struct RequestLabels {
+    pub method: String,
+    pub route: String,
+}
+
+pub fn record_request(labels: &RequestLabels, status: u16, latency: Duration) {
+    HTTP_REQUESTS
+        .with_label_values(&[&labels.method, &labels.route, &status.to_string()])
+        .inc();
+
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[&labels.method, &labels.route])
+        .observe(latency.as_secs_f64());
+}
+
+/// Updates the gauges from the app state, then renders every metric in the text format
+pub fn render(app_state: &AppState) -> String {
+    let now = app_state.now();
+    ACTIVE_SESSIONS.set(app_state.tokens.values().filter(|token| token.is_valid(now)).count() as i64);
+    USERS.set(app_state.users.len() as i64);
+    PETS.set(app_state.pets.len() as i64);
+    PET_YARDS.set(app_state.pet_yards.len() as i64);
+
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .unwrap();
+
+    String::from_utf8(buffer).unwrap()
+}
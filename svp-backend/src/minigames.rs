@@ -0,0 +1,423 @@
+/*
+
+This file handles mini-games users play with their pets.
+
+Games are played on the server: a session holds the game's state, like the number to guess,
+and the client only sends moves. The server decides when the game is over and scores it
+from 0 to 100, so a client can't claim a score it didn't earn. Starting a game counts as the
+play action for cooldowns, and the score decides how much experience and happiness the pet gets.
+
+*/
+
+use rand::Rng;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::structs::PetActionError;
+
+pub const MAX_SCORE: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MinigameConfig {
+    // How long a game can go without being finished
+    pub session_expiry_secs: u64,
+    // Happiness for a perfect score, scaled down for lower ones
+    pub happiness_reward: f64,
+    // Energy playing takes, whatever the score
+    pub energy_cost: f64,
+    // Number guessing picks from 1 to this
+    pub highest_number: u32,
+    pub max_guesses: u32,
+    // Rock paper scissors rounds to win (or lose) the game
+    pub rounds_to_win: u32,
+    // Fetch scores nothing if the catch is this far off
+    pub fetch_window_ms: u64,
+}
+
+impl Default for MinigameConfig {
+    fn default() -> Self {
+        Self {
+            session_expiry_secs: 60 * 10,
+            happiness_reward: 20.0,
+            energy_cost: 10.0,
+            highest_number: 100,
+            max_guesses: 7,
+            rounds_to_win: 2,
+            fetch_window_ms: 1000,
+        }
+    }
+}
+
+impl MinigameConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.session_expiry_secs == 0 {
+            return Err("minigames.session_expiry_secs must be greater than 0".to_string());
+        }
+
+        if !self.happiness_reward.is_finite() || self.happiness_reward < 0.0 {
+            return Err("minigames.happiness_reward must not be negative".to_string());
+        }
+
+        if !self.energy_cost.is_finite() || self.energy_cost < 0.0 {
+            return Err("minigames.energy_cost must not be negative".to_string());
+        }
+
+        if self.highest_number < 2 || self.max_guesses == 0 || self.rounds_to_win == 0 || self.fetch_window_ms == 0 {
+            return Err("minigames.highest_number must be at least 2, and max_guesses, rounds_to_win and fetch_window_ms above 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameKind {
+    NumberGuess,
+    RockPaperScissors,
+    Fetch,
+}
+
+impl std::str::FromStr for GameKind {
+    type Err = GameError;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "number_guess" => Ok(GameKind::NumberGuess),
+            "rock_paper_scissors" => Ok(GameKind::RockPaperScissors),
+            "fetch" => Ok(GameKind::Fetch),
+            _ => Err(GameError::UnknownGame),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Hand {
+    Rock,
+    Paper,
+    Scissors,
+}
+
+impl Hand {
+    const ALL: [Hand; 3] = [Hand::Rock, Hand::Paper, Hand::Scissors];
+
+    fn beats(&self, other: Hand) -> bool {
+        matches!(
+            (self, other),
+            (Hand::Rock, Hand::Scissors) | (Hand::Paper, Hand::Rock) | (Hand::Scissors, Hand::Paper)
+        )
+    }
+}
+
+/// A move sent by the client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMove {
+    // Number guessing
+    Guess(u32),
+    // Rock paper scissors
+    Hand(Hand),
+    // Fetch, throwing the ball and then catching it when the pet brings it back
+    Throw,
+    Catch,
+}
+
+/// What happened after a move
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum MoveResult {
+    Higher,
+    Lower,
+    Correct,
+    OutOfGuesses { number: u32 },
+    Round { player: Hand, pet: Hand, won: Option<bool> },
+    Thrown { back_in_ms: u64 },
+    Caught { off_by_ms: u64 },
+}
+
+/// A move's result, with the score and experience given once the game is over
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveOutcome {
+    pub result: MoveResult,
+    pub score: Option<u32>,
+    pub experience: u64,
+    pub session: GameSession,
+}
+
+/// Everything the server keeps about a game in progress, including what the client can't see
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "game", rename_all = "snake_case")]
+pub enum GameState {
+    NumberGuess { number: u32, guesses: u32 },
+    RockPaperScissors { wins: u32, losses: u32 },
+    // When the pet gets back with the ball, once it has been thrown
+    Fetch { back_at: Option<u64> },
+}
+
+impl GameState {
+    fn new(kind: GameKind, config: &MinigameConfig, rng: &mut impl Rng) -> Self {
+        match kind {
+            GameKind::NumberGuess => GameState::NumberGuess {
+                number: rng.gen_range(1..=config.highest_number),
+                guesses: 0,
+            },
+            GameKind::RockPaperScissors => GameState::RockPaperScissors { wins: 0, losses: 0 },
+            GameKind::Fetch => GameState::Fetch { back_at: None },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSession {
+    pub uuid: String,
+    pub user: String,
+    pub pet: String,
+    pub kind: GameKind,
+    pub state: GameState,
+    pub created: u64,
+    pub expires: u64,
+}
+
+impl GameSession {
+    pub fn new(user: String, pet: String, kind: GameKind, now: u64, config: &MinigameConfig, rng: &mut impl Rng) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            user,
+            pet,
+            kind,
+            state: GameState::new(kind, config, rng),
+            created: now,
+            expires: now + config.session_expiry_secs * 1000,
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.expires
+    }
+
+    /// Plays a move, returning what happened and the score if the game is over
+    pub fn play(
+        &mut self,
+        game_move: GameMove,
+        now: u64,
+        config: &MinigameConfig,
+        rng: &mut impl Rng,
+    ) -> Result<(MoveResult, Option<u32>), GameError> {
+        match (&mut self.state, game_move) {
+            (GameState::NumberGuess { number, guesses }, GameMove::Guess(guess)) => {
+                *guesses += 1;
+
+                if guess == *number {
+                    // Full marks for the first guess, down to a little for the last
+                    let left = (config.max_guesses + 1).saturating_sub(*guesses).min(config.max_guesses);
+                    return Ok((MoveResult::Correct, Some(MAX_SCORE * left / config.max_guesses)));
+                }
+
+                if *guesses >= config.max_guesses {
+                    return Ok((MoveResult::OutOfGuesses { number: *number }, Some(0)));
+                }
+
+                let result = if guess < *number { MoveResult::Higher } else { MoveResult::Lower };
+                Ok((result, None))
+            }
+            (GameState::RockPaperScissors { wins, losses }, GameMove::Hand(player)) => {
+                let pet = Hand::ALL[rng.gen_range(0..Hand::ALL.len())];
+
+                let won = if player.beats(pet) {
+                    *wins += 1;
+                    Some(true)
+                } else if pet.beats(player) {
+                    *losses += 1;
+                    Some(false)
+                } else {
+                    None
+                };
+
+                let score = if *wins >= config.rounds_to_win {
+                    Some(MAX_SCORE * *wins / (*wins + *losses))
+                } else if *losses >= config.rounds_to_win {
+                    Some(0)
+                } else {
+                    None
+                };
+
+                Ok((MoveResult::Round { player, pet, won }, score))
+            }
+            (GameState::Fetch { back_at: back_at @ None }, GameMove::Throw) => {
+                let back_in_ms = rng.gen_range(1000..=3000);
+                *back_at = Some(now + back_in_ms);
+
+                Ok((MoveResult::Thrown { back_in_ms }, None))
+            }
+            (GameState::Fetch { back_at: Some(back_at) }, GameMove::Catch) => {
+                // Timed by the server, so only when the catch arrives counts
+                let off_by_ms = now.abs_diff(*back_at);
+                let window = config.fetch_window_ms;
+                let score = MAX_SCORE as u64 * (window - off_by_ms.min(window)) / window;
+
+                Ok((MoveResult::Caught { off_by_ms }, Some(score as u32)))
+            }
+            _ => Err(GameError::InvalidMove),
+        }
+    }
+
+    /// The session without anything that would give the game away
+    pub fn for_user(&self, config: &MinigameConfig) -> serde_json::Value {
+        let progress = match &self.state {
+            GameState::NumberGuess { guesses, .. } => serde_json::json!({
+                "highest_number": config.highest_number,
+                "guesses_left": config.max_guesses.saturating_sub(*guesses),
+            }),
+            GameState::RockPaperScissors { wins, losses } => serde_json::json!({
+                "wins": wins,
+                "losses": losses,
+                "rounds_to_win": config.rounds_to_win,
+            }),
+            GameState::Fetch { back_at } => serde_json::json!({
+                "thrown": back_at.is_some(),
+            }),
+        };
+
+        serde_json::json!({
+            "uuid": self.uuid,
+            "pet": self.pet,
+            "kind": self.kind,
+            "progress": progress,
+            "created": self.created,
+            "expires": self.expires,
+        })
+    }
+}
+
+/// Why a game couldn't be started or played
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameError {
+    PetNotFound,
+    NotOwner,
+    UnknownGame,
+    SessionNotFound,
+    // The pet is already in a game
+    AlreadyPlaying,
+    // The move doesn't fit the game, or comes at the wrong time
+    InvalidMove,
+    Expired,
+    Action(PetActionError),
+}
+
+impl From<PetActionError> for GameError {
+    fn from(error: PetActionError) -> Self {
+        GameError::Action(error)
+    }
+}
+
+impl std::fmt::Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameError::PetNotFound => write!(f, "Pet not found"),
+            GameError::NotOwner => write!(f, "Pet belongs to another user"),
+            GameError::UnknownGame => write!(f, "Unknown game"),
+            GameError::SessionNotFound => write!(f, "Game not found"),
+            GameError::AlreadyPlaying => write!(f, "Pet is already playing a game"),
+            GameError::InvalidMove => write!(f, "That move can't be played now"),
+            GameError::Expired => write!(f, "Game has expired"),
+            GameError::Action(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::leveling;
+    use crate::needs::PetStats;
+    use crate::simulation::{add_user_with_pet, mock_state};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_games_are_scored_by_the_server() {
+        let config = MinigameConfig::default();
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let mut guessing = GameSession::new("user".to_string(), "pet".to_string(), GameKind::NumberGuess, 0, &config, &mut rng);
+        let GameState::NumberGuess { number, .. } = guessing.state else {
+            panic!("wrong game");
+        };
+        assert_eq!(guessing.play(GameMove::Hand(Hand::Rock), 0, &config, &mut rng), Err(GameError::InvalidMove));
+
+        // A wrong guess gives a hint, and the right one on the second guess loses a little
+        let wrong = if number == 1 { 2 } else { number - 1 };
+        assert!(guessing.play(GameMove::Guess(wrong), 0, &config, &mut rng).unwrap().1.is_none());
+        assert_eq!(guessing.play(GameMove::Guess(number), 0, &config, &mut rng).unwrap(), (MoveResult::Correct, Some(85)));
+
+        // Fetch is timed from when the moves arrive
+        let mut fetch = GameSession::new("user".to_string(), "pet".to_string(), GameKind::Fetch, 0, &config, &mut rng);
+        assert_eq!(fetch.play(GameMove::Catch, 0, &config, &mut rng), Err(GameError::InvalidMove));
+
+        let (MoveResult::Thrown { back_in_ms }, None) = fetch.play(GameMove::Throw, 1000, &config, &mut rng).unwrap() else {
+            panic!("ball wasn't thrown");
+        };
+        let (_, score) = fetch.play(GameMove::Catch, 1000 + back_in_ms + 250, &config, &mut rng).unwrap();
+        assert_eq!(score, Some(75));
+    }
+
+    #[test]
+    fn test_finished_games_reward_the_pet_once() {
+        let (mut state, _) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+        let config = state.config.clone();
+
+        let mut pet = state.get_pet_by_uuid(&cat).unwrap().clone();
+        let stats = PetStats { happiness: 50.0, ..pet.current_stats(state.now(), &config) };
+        pet.set_stats(stats, state.now());
+        state.update_pet(pet);
+
+        assert_eq!(state.start_game(&bob, &cat, GameKind::NumberGuess).unwrap_err(), GameError::NotOwner);
+        let session = state.start_game(&alice, &cat, GameKind::NumberGuess).unwrap();
+        assert_eq!(state.start_game(&alice, &cat, GameKind::Fetch).unwrap_err(), GameError::AlreadyPlaying);
+
+        // Only the server knows the number, and the client only sends guesses
+        let GameState::NumberGuess { number, .. } = state.game_sessions[&session.uuid].state else {
+            panic!("wrong game");
+        };
+        assert_eq!(state.play_game(&bob, &session.uuid, GameMove::Guess(number)).unwrap_err(), GameError::SessionNotFound);
+
+        let before = state.get_pet_by_uuid(&cat).unwrap().clone();
+        let outcome = state.play_game(&alice, &session.uuid, GameMove::Guess(number)).unwrap();
+        assert_eq!(outcome.score, Some(MAX_SCORE));
+        assert_eq!(outcome.experience, config.leveling.action_experience.play);
+
+        let pet = state.get_pet_by_uuid(&cat).unwrap();
+        let stats = pet.current_stats(state.now(), &config);
+        assert_eq!(stats.happiness, 50.0 + config.minigames.happiness_reward);
+        assert_eq!(stats.energy, before.current_stats(state.now(), &config).energy);
+        assert_eq!(
+            (pet.get_level(), pet.get_experience()),
+            leveling::gain_experience(before.get_level(), before.get_experience(), outcome.experience, &config.leveling)
+        );
+
+        // A finished game can't be scored again
+        assert_eq!(state.play_game(&alice, &session.uuid, GameMove::Guess(number)).unwrap_err(), GameError::SessionNotFound);
+    }
+
+    #[test]
+    fn test_expired_games_are_refused() {
+        let (mut state, clock) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+
+        let session = state.start_game(&alice, &cat, GameKind::Fetch).unwrap();
+
+        clock.advance(state.config.minigames.session_expiry_secs * 1000 + 1);
+        assert_eq!(state.play_game(&alice, &session.uuid, GameMove::Throw).unwrap_err(), GameError::Expired);
+        assert_eq!(state.play_game(&alice, &session.uuid, GameMove::Throw).unwrap_err(), GameError::SessionNotFound);
+        assert_eq!(state.get_pet_by_uuid(&cat).unwrap().get_experience(), 0);
+
+        // Which leaves the pet free to play again
+        assert!(state.start_game(&alice, &cat, GameKind::Fetch).is_ok());
+    }
+}
@@ -0,0 +1,354 @@
+/*
+
+This file handles a pet's needs, which decay over time.
+
+Stats are stored as a snapshot with the time it was taken,
+and the current values are worked out from the time passed since then.
+
+*/
+
+use serde::{Deserialize, Serialize};
+
+pub const MAX_STAT: f64 = 100.0;
+pub const MIN_STAT: f64 = 0.0;
+
+const MS_PER_HOUR: f64 = 1000.0 * 60.0 * 60.0;
+
+/// How full each of a pet's needs is, from 0 (empty) to 100 (fully satisfied).
+/// A hunger of 100 means the pet is full, and 0 means it is starving.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PetStats {
+    pub hunger: f64,
+    pub happiness: f64,
+    pub energy: f64,
+    pub hygiene: f64,
+}
+
+impl Default for PetStats {
+    fn default() -> Self {
+        Self {
+            hunger: MAX_STAT,
+            happiness: MAX_STAT,
+            energy: MAX_STAT,
+            hygiene: MAX_STAT,
+        }
+    }
+}
+
+impl PetStats {
+    pub fn get(&self, need: Need) -> f64 {
+        match need {
+            Need::Hunger => self.hunger,
+            Need::Happiness => self.happiness,
+            Need::Energy => self.energy,
+            Need::Hygiene => self.hygiene,
+        }
+    }
+
+    /// Returns the stats after the given amount of time has passed,
+    /// with each stat's decay sped up or slowed down by its rate
+    pub fn decayed(&self, elapsed_ms: u64, config: &NeedsConfig, rates: &DecayRates) -> Self {
+        let hours = elapsed_ms as f64 / MS_PER_HOUR;
+
+        Self {
+            hunger: config.hunger.apply(self.hunger, hours * rates.hunger),
+            happiness: config.happiness.apply(self.happiness, hours * rates.happiness),
+            energy: config.energy.apply(self.energy, hours * rates.energy),
+            hygiene: config.hygiene.apply(self.hygiene, hours * rates.hygiene),
+        }
+    }
+}
+
+/// One of a pet's needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Need {
+    Hunger,
+    Happiness,
+    Energy,
+    Hygiene,
+}
+
+/// A pet's stats as they were saved, and how quickly they decay from there
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatsSnapshot {
+    pub stats: PetStats,
+    // When the stats were saved
+    pub taken: u64,
+    pub rates: DecayRates,
+}
+
+impl StatsSnapshot {
+    pub fn at(&self, now: u64, config: &NeedsConfig) -> PetStats {
+        self.stats.decayed(now.saturating_sub(self.taken), config, &self.rates)
+    }
+
+    /// The first millisecond the need was at or below the threshold, or None if it never gets there.
+    /// Found from the curve, then nudged so it matches what `at` works out to the millisecond.
+    pub fn dropped_to(&self, need: Need, threshold: f64, config: &NeedsConfig) -> Option<u64> {
+        let reached = |elapsed: u64| self.at(self.taken + elapsed, config).get(need) <= threshold;
+
+        if reached(0) {
+            return Some(self.taken);
+        }
+
+        let rate = self.rates.get(need);
+        let elapsed = config.curve(need).hours_until(self.stats.get(need), threshold)? / rate * MS_PER_HOUR;
+        if !elapsed.is_finite() || elapsed >= (u64::MAX - self.taken) as f64 / 2.0 {
+            return None;
+        }
+
+        let mut elapsed = elapsed.ceil() as u64;
+        while elapsed > 0 && reached(elapsed - 1) {
+            elapsed -= 1;
+        }
+        while !reached(elapsed) {
+            elapsed += 1;
+        }
+
+        Some(self.taken + elapsed)
+    }
+}
+
+/// How quickly each stat decays compared to the configured curves, 1.0 being normal speed
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DecayRates {
+    pub hunger: f64,
+    pub happiness: f64,
+    pub energy: f64,
+    pub hygiene: f64,
+}
+
+impl Default for DecayRates {
+    fn default() -> Self {
+        Self {
+            hunger: 1.0,
+            happiness: 1.0,
+            energy: 1.0,
+            hygiene: 1.0,
+        }
+    }
+}
+
+impl DecayRates {
+    /// Combines two sets of rates, such as a species' and its form's
+    pub fn scaled(&self, other: &DecayRates) -> Self {
+        Self {
+            hunger: self.hunger * other.hunger,
+            happiness: self.happiness * other.happiness,
+            energy: self.energy * other.energy,
+            hygiene: self.hygiene * other.hygiene,
+        }
+    }
+
+    pub fn get(&self, need: Need) -> f64 {
+        match need {
+            Need::Hunger => self.hunger,
+            Need::Happiness => self.happiness,
+            Need::Energy => self.energy,
+            Need::Hygiene => self.hygiene,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        [self.hunger, self.happiness, self.energy, self.hygiene]
+            .iter()
+            .all(|rate| rate.is_finite() && *rate >= 0.0)
+    }
+}
+
+/// Adds to a stat, keeping it between 0 and 100
+pub fn restore(stat: &mut f64, amount: f64) {
+    *stat = (*stat + amount).clamp(MIN_STAT, MAX_STAT);
+}
+
+/// How quickly a stat drops over time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "curve", rename_all = "lowercase", deny_unknown_fields)]
+pub enum DecayCurve {
+    // Loses the same amount every hour
+    Linear { per_hour: f64 },
+    // Loses half of what is left every half life, so it slows down as it empties
+    Exponential { half_life_hours: f64 },
+}
+
+impl DecayCurve {
+    pub fn apply(&self, value: f64, hours: f64) -> f64 {
+        let decayed = match *self {
+            DecayCurve::Linear { per_hour } => value - per_hour * hours,
+            DecayCurve::Exponential { half_life_hours } => {
+                value * 0.5_f64.powf(hours / half_life_hours)
+            }
+        };
+
+        decayed.clamp(MIN_STAT, MAX_STAT)
+    }
+
+    /// Hours of decay at normal speed for the value to drop to the target, or None if it never does
+    pub fn hours_until(&self, value: f64, target: f64) -> Option<f64> {
+        if value <= target {
+            return Some(0.0);
+        }
+
+        match *self {
+            DecayCurve::Linear { per_hour } if per_hour > 0.0 => Some((value - target) / per_hour),
+            // Halving never quite gets to zero
+            DecayCurve::Exponential { half_life_hours } if target > MIN_STAT => {
+                Some(half_life_hours * (value / target).log2())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            DecayCurve::Linear { per_hour } => per_hour.is_finite() && per_hour >= 0.0,
+            DecayCurve::Exponential { half_life_hours } => {
+                half_life_hours.is_finite() && half_life_hours > 0.0
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NeedsConfig {
+    pub hunger: DecayCurve,
+    pub happiness: DecayCurve,
+    pub energy: DecayCurve,
+    pub hygiene: DecayCurve,
+    // How much each action restores
+    pub feed_restores: f64,
+    pub pet_restores: f64,
+    pub clean_restores: f64,
+    // A pet dies once its hunger or happiness drops to this
+    pub death_threshold: f64,
+}
+
+impl Default for NeedsConfig {
+    fn default() -> Self {
+        // Hunger and happiness run out after 3 and 7 days, matching the old death rules
+        Self {
+            hunger: DecayCurve::Linear { per_hour: MAX_STAT / 72.0 },
+            happiness: DecayCurve::Linear { per_hour: MAX_STAT / 168.0 },
+            energy: DecayCurve::Linear { per_hour: MAX_STAT / 48.0 },
+            hygiene: DecayCurve::Linear { per_hour: MAX_STAT / 96.0 },
+            feed_restores: 50.0,
+            pet_restores: 50.0,
+            clean_restores: 60.0,
+            death_threshold: MIN_STAT,
+        }
+    }
+}
+
+impl NeedsConfig {
+    pub fn curve(&self, need: Need) -> &DecayCurve {
+        match need {
+            Need::Hunger => &self.hunger,
+            Need::Happiness => &self.happiness,
+            Need::Energy => &self.energy,
+            Need::Hygiene => &self.hygiene,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, curve) in [
+            ("hunger", &self.hunger),
+            ("happiness", &self.happiness),
+            ("energy", &self.energy),
+            ("hygiene", &self.hygiene),
+        ] {
+            if !curve.is_valid() {
+                return Err(format!("needs.{} has an invalid decay rate", name));
+            }
+        }
+
+        for (name, amount) in [
+            ("feed_restores", self.feed_restores),
+            ("pet_restores", self.pet_restores),
+            ("clean_restores", self.clean_restores),
+        ] {
+            if !(MIN_STAT..=MAX_STAT).contains(&amount) {
+                return Err(format!("needs.{} must be between 0 and 100", name));
+            }
+        }
+
+        if !(MIN_STAT..MAX_STAT).contains(&self.death_threshold) {
+            return Err("needs.death_threshold must be at least 0 and below 100".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 1000 * 60 * 60;
+
+    #[test]
+    fn test_linear_decay() {
+        let config = NeedsConfig::default();
+        let stats = PetStats::default().decayed(36 * HOUR, &config, &DecayRates::default());
+
+        assert!((stats.hunger - 50.0).abs() < 1e-9);
+        assert!(stats.happiness > stats.hunger);
+
+        // Stats never go below zero
+        let stats = PetStats::default().decayed(1000 * HOUR, &config, &DecayRates::default());
+        assert_eq!(stats.hunger, MIN_STAT);
+
+        // A species that gets hungry twice as fast is half empty in half the time
+        let rates = DecayRates { hunger: 2.0, ..DecayRates::default() };
+        let stats = PetStats::default().decayed(18 * HOUR, &config, &rates);
+        assert!((stats.hunger - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_exponential_decay() {
+        let curve = DecayCurve::Exponential { half_life_hours: 10.0 };
+
+        assert!((curve.apply(80.0, 10.0) - 40.0).abs() < 1e-9);
+        assert!((curve.apply(80.0, 20.0) - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_curves_from_toml() {
+        let config: NeedsConfig = toml::from_str(
+            r#"
+            hunger = { curve = "exponential", half_life_hours = 24.0 }
+            energy = { curve = "linear", per_hour = 2.0 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.hunger, DecayCurve::Exponential { half_life_hours: 24.0 });
+        assert_eq!(config.energy, DecayCurve::Linear { per_hour: 2.0 });
+        assert_eq!(config.happiness, NeedsConfig::default().happiness);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_stats_drop_to_thresholds_when_the_curve_says() {
+        let config = NeedsConfig::default();
+        let snapshot = StatsSnapshot {
+            stats: PetStats::default(),
+            taken: 1000,
+            rates: DecayRates { happiness: 0.0, ..DecayRates::default() },
+        };
+
+        // Hunger runs out after 72 hours, and is half gone after 36
+        assert_eq!(snapshot.dropped_to(Need::Hunger, MIN_STAT, &config), Some(1000 + 72 * HOUR));
+        let half = snapshot.dropped_to(Need::Hunger, 50.0, &config).unwrap();
+        assert!(snapshot.at(half, &config).hunger <= 50.0 && snapshot.at(half - 1, &config).hunger > 50.0);
+
+        // Needs that don't decay never get there, and ones already there got there straight away
+        assert_eq!(snapshot.dropped_to(Need::Happiness, 50.0, &config), None);
+        assert_eq!(snapshot.dropped_to(Need::Energy, MAX_STAT, &config), Some(1000));
+
+        let curve = DecayCurve::Exponential { half_life_hours: 10.0 };
+        assert!((curve.hours_until(80.0, 20.0).unwrap() - 20.0).abs() < 1e-9);
+        assert_eq!(curve.hours_until(80.0, MIN_STAT), None);
+    }
+}
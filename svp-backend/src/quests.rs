@@ -0,0 +1,378 @@
+/*
+
+This file handles daily quests and streaks.
+
+Each user gets a few quests every day (in UTC), picked from the configured templates.
+The picks are seeded by the user and the day, so they don't change if they're generated again.
+Quests count the same events as achievements, and give experience, coins or items once claimed.
+Claiming every quest on consecutive days builds a streak, which is lost if a day is missed.
+
+*/
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::achievements::GameEvent;
+use crate::items::ItemCatalog;
+
+const MS_PER_DAY: u64 = 1000 * 60 * 60 * 24;
+
+/// What finishing a quest gives
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuestReward {
+    // Experience for the pet the user picks when claiming
+    pub experience: u64,
+    pub coins: u64,
+    // Item counts, by item id
+    pub items: BTreeMap<String, u32>,
+}
+
+/// A kind of quest that can be picked for a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuestTemplate {
+    pub id: String,
+    // {count} is replaced with how many times the event has to happen
+    pub description: String,
+    pub event: GameEvent,
+    pub min_count: u64,
+    pub max_count: u64,
+    #[serde(default)]
+    pub reward: QuestReward,
+}
+
+impl QuestTemplate {
+    fn new(id: &str, description: &str, event: GameEvent, counts: (u64, u64), reward: QuestReward) -> Self {
+        Self {
+            id: id.to_string(),
+            description: description.to_string(),
+            event,
+            min_count: counts.0,
+            max_count: counts.1,
+            reward,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuestConfig {
+    pub quests_per_day: usize,
+    // Coins for each day of the streak when the day's last quest is claimed
+    pub streak_bonus_coins: u64,
+    pub max_streak_bonus: u64,
+    pub templates: Vec<QuestTemplate>,
+}
+
+impl Default for QuestConfig {
+    fn default() -> Self {
+        let reward = |experience: u64, item: Option<(&str, u32)>| QuestReward {
+            experience,
+            coins: 0,
+            items: item.map(|(item, count)| (item.to_string(), count)).into_iter().collect(),
+        };
+
+        Self {
+            quests_per_day: 3,
+            streak_bonus_coins: 10,
+            max_streak_bonus: 100,
+            templates: vec![
+                QuestTemplate::new("feed", "Feed your pets {count} times", GameEvent::PetFed, (3, 5), reward(20, Some(("kibble", 2)))),
+                QuestTemplate::new("pet", "Pet your pets {count} times", GameEvent::PetPetted, (5, 10), reward(30, None)),
+                QuestTemplate::new("rest", "Let your pets rest {count} times", GameEvent::PetRested, (1, 2), reward(15, None)),
+                QuestTemplate::new("yard_mate", "Pet a yard-mate's pet {count} times", GameEvent::YardMatePetted, (1, 3), reward(40, Some(("ball", 1)))),
+                QuestTemplate::new("play", "Play {count} mini-games with your pets", GameEvent::GamePlayed, (1, 3), reward(20, None)),
+                QuestTemplate::new("shop", "Buy {count} things from the shop", GameEvent::ItemBought, (1, 1), reward(10, Some(("fish", 1)))),
+            ],
+        }
+    }
+}
+
+impl QuestConfig {
+    pub fn validate(&self, catalog: &ItemCatalog) -> Result<(), String> {
+        if self.quests_per_day > self.templates.len() {
+            return Err("quests.quests_per_day can't be more than the number of templates".to_string());
+        }
+
+        for (index, template) in self.templates.iter().enumerate() {
+            if self.templates[..index].iter().any(|other| other.id == template.id) {
+                return Err(format!("quests.templates has more than one quest called {}", template.id));
+            }
+
+            if template.min_count == 0 || template.min_count > template.max_count {
+                return Err(format!("quest {} needs 0 < min_count <= max_count", template.id));
+            }
+
+            // Levels and ages don't add up over a day, so they'd be done as soon as they were given
+            if !template.event.is_counted() {
+                return Err(format!("quest {} has to use an event that is counted", template.id));
+            }
+
+            if let Some(item) = template.reward.items.keys().find(|item| catalog.get(item).is_none()) {
+                return Err(format!("quest {} rewards unknown item {}", template.id, item));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A quest given to a user for the day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Quest {
+    pub id: String,
+    pub description: String,
+    pub event: GameEvent,
+    pub target: u64,
+    pub progress: u64,
+    pub reward: QuestReward,
+    pub claimed: bool,
+}
+
+impl Quest {
+    pub fn is_complete(&self) -> bool {
+        self.progress >= self.target
+    }
+}
+
+/// Picks the user's quests for a day, always the same for the same user and day
+pub fn generate(user_uuid: &str, day: u64, config: &QuestConfig) -> Vec<Quest> {
+    let user_bits = Uuid::parse_str(user_uuid)
+        .map(|uuid| {
+            let bits = uuid.as_u128();
+            (bits as u64) ^ ((bits >> 64) as u64)
+        })
+        .unwrap_or(0);
+    let mut rng = StdRng::seed_from_u64(user_bits ^ day.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+    config
+        .templates
+        .choose_multiple(&mut rng, config.quests_per_day)
+        .map(|template| {
+            let target = rng.gen_range(template.min_count..=template.max_count);
+
+            Quest {
+                id: template.id.clone(),
+                description: template.description.replace("{count}", &target.to_string()),
+                event: template.event,
+                target,
+                progress: 0,
+                reward: template.reward.clone(),
+                claimed: false,
+            }
+        })
+        .collect()
+}
+
+/// A user's quests for the day and their streak
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct QuestLog {
+    // Days since the epoch the quests are for
+    pub day: u64,
+    pub quests: Vec<Quest>,
+    // Days in a row every quest was claimed
+    pub streak: u32,
+    pub best_streak: u32,
+    // The last day every quest was claimed
+    pub last_completed_day: Option<u64>,
+}
+
+impl QuestLog {
+    /// Moves on to today's quests if the day has changed, ending the streak if a day was missed
+    pub fn refresh(&mut self, user_uuid: &str, now: u64, config: &QuestConfig) {
+        let today = now / MS_PER_DAY;
+
+        if self.day == today && !self.quests.is_empty() {
+            return;
+        }
+
+        self.day = today;
+        self.quests = generate(user_uuid, today, config);
+
+        if self.last_completed_day.is_none_or(|day| day + 1 < today) {
+            self.streak = 0;
+        }
+    }
+
+    /// Counts an event towards any of today's quests that are waiting on it
+    pub fn record(&mut self, event: GameEvent, value: u64) {
+        for quest in self.quests.iter_mut().filter(|quest| quest.event == event) {
+            quest.progress = quest.progress.saturating_add(value).min(quest.target);
+        }
+    }
+
+    /// Marks a finished quest as claimed, returning it.
+    /// Claiming the day's last quest adds to the streak.
+    pub fn claim(&mut self, quest_id: &str) -> Result<Quest, QuestError> {
+        let quest = self
+            .quests
+            .iter_mut()
+            .find(|quest| quest.id == quest_id)
+            .ok_or(QuestError::NotFound)?;
+
+        if quest.claimed {
+            return Err(QuestError::AlreadyClaimed);
+        }
+
+        if !quest.is_complete() {
+            return Err(QuestError::NotComplete);
+        }
+
+        quest.claimed = true;
+        let quest = quest.clone();
+
+        if self.quests.iter().all(|quest| quest.claimed) {
+            self.streak = match self.last_completed_day {
+                Some(day) if day + 1 == self.day => self.streak + 1,
+                _ => 1,
+            };
+            self.best_streak = self.best_streak.max(self.streak);
+            self.last_completed_day = Some(self.day);
+        }
+
+        Ok(quest)
+    }
+
+    /// Whether claiming the quest just finished the day
+    pub fn finished_today(&self) -> bool {
+        self.last_completed_day == Some(self.day)
+    }
+}
+
+/// Why a quest couldn't be claimed
+#[derive(Debug, Clone, PartialEq)]
+pub enum QuestError {
+    NotFound,
+    NotComplete,
+    AlreadyClaimed,
+    // The quest gives experience, so a pet has to be chosen
+    PetRequired,
+    PetNotFound,
+    PetDead,
+}
+
+impl std::fmt::Display for QuestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuestError::NotFound => write!(f, "Quest not found"),
+            QuestError::NotComplete => write!(f, "Quest isn't finished yet"),
+            QuestError::AlreadyClaimed => write!(f, "Quest has already been claimed"),
+            QuestError::PetRequired => write!(f, "Choose a pet to get the experience"),
+            QuestError::PetNotFound => write!(f, "Pet not found"),
+            QuestError::PetDead => write!(f, "Pet has died"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::economy::LedgerReason;
+    use crate::simulation::{add_user_with_pet, mock_state};
+    use crate::structs::AppState;
+
+    const USER: &str = "0b7e9c5e-2a9f-4a43-9d4c-31a1d5e0c0aa";
+
+    fn complete_all(log: &mut QuestLog) {
+        for index in 0..log.quests.len() {
+            let quest = log.quests[index].clone();
+            log.record(quest.event, quest.target);
+            log.claim(&quest.id).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_quests_are_seeded_by_user_and_day() {
+        let config = QuestConfig::default();
+        assert!(config.validate(&ItemCatalog::default()).is_ok());
+
+        assert_eq!(generate(USER, 100, &config), generate(USER, 100, &config));
+        assert_eq!(generate(USER, 100, &config).len(), config.quests_per_day);
+    }
+
+    #[test]
+    fn test_streaks_break_after_a_missed_day() {
+        let config = QuestConfig::default();
+        let mut log = QuestLog::default();
+
+        log.refresh(USER, 10 * MS_PER_DAY, &config);
+        assert_eq!(log.claim(&log.quests[0].id.clone()), Err(QuestError::NotComplete));
+        complete_all(&mut log);
+        assert_eq!(log.streak, 1);
+
+        log.refresh(USER, 11 * MS_PER_DAY, &config);
+        complete_all(&mut log);
+        assert_eq!(log.streak, 2);
+
+        // Skipping day 12 loses the streak
+        log.refresh(USER, 13 * MS_PER_DAY, &config);
+        assert_eq!(log.streak, 0);
+        complete_all(&mut log);
+        assert_eq!(log.streak, 1);
+        assert_eq!(log.best_streak, 2);
+    }
+
+    /// Coins the user has been paid for quests and streaks, leaving out achievements
+    fn quest_coins(state: &AppState, user_uuid: &str) -> i64 {
+        state
+            .get_ledger(user_uuid)
+            .into_iter()
+            .filter(|entry| matches!(entry.reason, LedgerReason::Quest { .. } | LedgerReason::QuestStreak { .. }))
+            .map(|entry| entry.amount)
+            .sum()
+    }
+
+    #[test]
+    fn test_claiming_quests_pays_out_and_builds_streaks() {
+        let quests = QuestConfig {
+            quests_per_day: 1,
+            streak_bonus_coins: 10,
+            max_streak_bonus: 15,
+            templates: vec![QuestTemplate::new(
+                "feed",
+                "Feed your pets {count} times",
+                GameEvent::PetFed,
+                (2, 2),
+                QuestReward { experience: 5, coins: 3, items: BTreeMap::from([("kibble".to_string(), 1)]) },
+            )],
+        };
+        let (mut state, clock) = mock_state(Config { quests, ..Config::default() });
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+
+        // Day one
+        state.record_event(&alice, GameEvent::PetFed, 1);
+        assert_eq!(state.claim_quest(&alice, "feed", None).unwrap_err(), QuestError::NotComplete);
+        state.record_event(&alice, GameEvent::PetFed, 1);
+
+        let log = state.claim_quest(&alice, "feed", None).unwrap();
+        assert_eq!(log.streak, 1);
+        assert_eq!(quest_coins(&state, &alice), 3 + 10);
+        assert_eq!(state.get_user_by_uuid(&alice).unwrap().item_count("kibble"), 1);
+        assert_eq!(state.get_pet_by_uuid(&cat).unwrap().get_experience(), 5);
+
+        assert_eq!(state.claim_quest(&alice, "feed", None).unwrap_err(), QuestError::AlreadyClaimed);
+        assert_eq!(quest_coins(&state, &alice), 13);
+
+        // Day two carries the streak on, with a bigger bonus up to the cap
+        clock.advance(MS_PER_DAY);
+        assert_eq!(state.claim_quest(&alice, "feed", None).unwrap_err(), QuestError::NotComplete);
+        state.record_event(&alice, GameEvent::PetFed, 2);
+        assert_eq!(state.claim_quest(&alice, "feed", None).unwrap().streak, 2);
+        assert_eq!(quest_coins(&state, &alice), 13 + 3 + 15);
+
+        // Missing day three starts it over
+        clock.advance(2 * MS_PER_DAY);
+        state.record_event(&alice, GameEvent::PetFed, 2);
+        let log = state.claim_quest(&alice, "feed", Some(cat.clone())).unwrap();
+        assert_eq!(log.streak, 1);
+        assert_eq!(log.best_streak, 2);
+        assert_eq!(quest_coins(&state, &alice), 31 + 3 + 10);
+        assert_eq!(state.get_pet_by_uuid(&cat).unwrap().get_experience(), 15);
+    }
+}
@@ -2,4 +2,24 @@ pub mod routes_auth;
 pub mod routes_users;
 pub mod routes_pets;
 pub mod routes_pet_yards;
-pub mod routes_public;
\ No newline at end of file
+pub mod routes_public;
+pub mod routes_health;
+pub mod routes_graveyard;
+pub mod routes_species;
+pub mod routes_art;
+pub mod routes_breeding;
+pub mod routes_transfers;
+pub mod routes_items;
+pub mod routes_shop;
+pub mod routes_trading;
+pub mod routes_quests;
+pub mod routes_minigames;
+pub mod routes_contests;
+pub mod routes_leaderboards;
+pub mod routes_vacations;
+
+/// Seconds until an action is available again, for a Retry-After header. Rounded up so
+/// clients retrying after this many seconds aren't too early.
+pub(crate) fn retry_after_secs(available_at: u64, now: u64) -> u64 {
+    available_at.saturating_sub(now).div_ceil(1000)
+}
@@ -0,0 +1,38 @@
+use crate::art::Mood;
+use aide::axum::IntoApiResponse;
+use axum::extract::{Path, Query};
+use axum::http::{Response, StatusCode};
+use crate::lock_app_state;
+use serde::Deserialize;
+use schemars::JsonSchema;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ArtQuery {
+    mood: Option<Mood>,
+}
+
+/// Handles getting a picture from the art catalog, optionally drawn in a mood
+pub async fn route_get_art(Path((species, index)): Path<(String, u64)>, query: Query<ArtQuery>) -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    let art = match query.mood {
+        Some(mood) => app_state.config.art.render(&species, index, mood),
+        None => app_state.config.art.get(&species, index).cloned(),
+    };
+
+    match art {
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("Art not found".to_string()) // Convert to String
+                .unwrap()
+        }
+        Some(art) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", "text/plain; charset=utf-8")
+                .body(art)
+                .unwrap()
+        }
+    }
+}
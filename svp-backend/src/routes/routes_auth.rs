@@ -1,8 +1,8 @@
-use crate::{auth::*, User};
+use crate::auth::*;
 use aide::axum::IntoApiResponse;
 use axum::extract::{Path, Json};
-use axum::http::{Response, StatusCode, HeaderMap};
-use crate::APP_STATE;
+use axum::http::{Response, StatusCode};
+use crate::lock_app_state;
 use serde::Deserialize;
 use schemars::JsonSchema;
 
@@ -20,7 +20,7 @@ pub async fn route_login(payload: Json<Login>) -> impl IntoApiResponse  {
     let password = payload.password.clone();
     
     // Get json from request
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     let user = app_state.get_user_by_username(&username);
 
@@ -50,10 +50,13 @@ pub async fn route_login(payload: Json<Login>) -> impl IntoApiResponse  {
 
     drop(app_state);
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let token = app_state.create_token(&user);
 
+    app_state.reward_daily_login(&user.get_uuid());
+    let user = app_state.get_user_by_uuid(&user.get_uuid()).unwrap_or(&user);
+
     let response_body = user.for_user_with_token(token);
 
     Response::builder()
@@ -84,6 +87,7 @@ pub async fn route_logout(token: Path<String>) -> impl IntoApiResponse  {
 
 /// Handles the verification of a token.
 /// The user must provide their token.
+#[allow(dead_code)]
 pub async fn route_verify(Path((uuid, token)): Path<(String, String)>) -> impl IntoApiResponse  {
     verify(uuid.to_string(), token.to_string()).await
 }
@@ -0,0 +1,137 @@
+use crate::auth::*;
+use crate::breeding::BreedError;
+use crate::structs::BreedOutcome;
+use aide::axum::IntoApiResponse;
+use axum::extract::{Path, Json};
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+use crate::routes::retry_after_secs;
+use serde::Deserialize;
+use schemars::JsonSchema;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BreedPayload {
+    // Name for the egg
+    name: Option<String>,
+}
+
+/// Handles breeding two adult pets. If the user owns both, the egg is laid straight away,
+/// otherwise the partner's owner is sent a breeding request.
+pub async fn route_breed_pets(headers: HeaderMap, Path((user_uuid, pet_uuid, partner_uuid)): Path<(String, String, String)>, payload: Json<BreedPayload>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.breed_pets(&user_uuid, &pet_uuid, &partner_uuid, payload.name.clone()) {
+        Ok(BreedOutcome::Egg(egg)) => {
+            Response::builder()
+                .status(StatusCode::CREATED)
+                .body(egg.for_user(app_state.now(), &app_state.config))
+                .unwrap()
+        }
+        Ok(BreedOutcome::Requested(request)) => {
+            Response::builder()
+                .status(StatusCode::ACCEPTED)
+                .body(request.for_user().to_string())
+                .unwrap()
+        }
+        Err(e) => breed_error_response(e, app_state.now()),
+    }
+}
+
+/// Handles listing the breeding requests a user has made or been sent
+pub async fn route_get_breeding_requests(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let requests: Vec<serde_json::Value> = app_state
+        .get_breeding_requests(&uuid)
+        .iter()
+        .map(|request| request.for_user())
+        .collect();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(requests).to_string())
+        .unwrap()
+}
+
+/// Handles agreeing to let another user's pet breed with one of the user's pets
+pub async fn route_accept_breeding_request(headers: HeaderMap, Path((user_uuid, request_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.accept_breeding_request(&user_uuid, &request_uuid) {
+        Ok(egg) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(egg.for_public(app_state.now(), &app_state.config))
+                .unwrap()
+        }
+        Err(e) => breed_error_response(e, app_state.now()),
+    }
+}
+
+/// Handles turning down a breeding request, or cancelling one the user made
+pub async fn route_decline_breeding_request(headers: HeaderMap, Path((user_uuid, request_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.decline_breeding_request(&user_uuid, &request_uuid) {
+        Ok(()) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body("Breeding request declined".to_string())
+                .unwrap()
+        }
+        Err(e) => breed_error_response(e, app_state.now()),
+    }
+}
+
+fn breed_error_response(error: BreedError, now: u64) -> Response<String> {
+    let mut response = Response::builder();
+
+    let status = match error {
+        BreedError::NotFound => StatusCode::NOT_FOUND,
+        BreedError::NotOwner | BreedError::NotInSameYard => StatusCode::FORBIDDEN,
+        BreedError::SamePet | BreedError::NotAdult | BreedError::DifferentSpecies => StatusCode::CONFLICT,
+        BreedError::Dead | BreedError::Expired => StatusCode::GONE,
+        BreedError::Cooldown { available_at } => {
+            response = response.header("Retry-After", retry_after_secs(available_at, now));
+            StatusCode::TOO_MANY_REQUESTS
+        }
+        BreedError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+    };
+
+    response
+        .status(status)
+        .body(error.to_string())
+        .unwrap()
+}
@@ -0,0 +1,149 @@
+use crate::auth::*;
+use crate::contests::ContestError;
+use aide::axum::IntoApiResponse;
+use axum::extract::Path;
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+
+/// Handles challenging another user's pet to a contest. Both pets have to be in
+/// a pet yard both owners belong to, and the other owner has to accept.
+pub async fn route_challenge_pet(headers: HeaderMap, Path((user_uuid, pet_uuid, opponent_uuid)): Path<(String, String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.challenge_pet(&user_uuid, &pet_uuid, &opponent_uuid) {
+        Ok(challenge) => {
+            Response::builder()
+                .status(StatusCode::ACCEPTED)
+                .body(challenge.for_user().to_string())
+                .unwrap()
+        }
+        Err(e) => contest_error_response(e),
+    }
+}
+
+/// Handles listing the contest challenges a user has made or been sent
+pub async fn route_get_contest_challenges(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let challenges: Vec<serde_json::Value> = app_state
+        .get_contest_challenges(&uuid)
+        .iter()
+        .map(|challenge| challenge.for_user())
+        .collect();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(challenges).to_string())
+        .unwrap()
+}
+
+/// Handles accepting a challenge to one of the user's pets, which holds the contest
+/// and returns its battle log
+pub async fn route_accept_contest_challenge(headers: HeaderMap, Path((user_uuid, challenge_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.accept_contest_challenge(&user_uuid, &challenge_uuid) {
+        Ok(log) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(serde_json::json!(log).to_string())
+                .unwrap()
+        }
+        Err(e) => contest_error_response(e),
+    }
+}
+
+/// Handles turning down a contest challenge, or cancelling one the user made
+pub async fn route_decline_contest_challenge(headers: HeaderMap, Path((user_uuid, challenge_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.decline_contest_challenge(&user_uuid, &challenge_uuid) {
+        Ok(()) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body("Challenge declined".to_string())
+                .unwrap()
+        }
+        Err(e) => contest_error_response(e),
+    }
+}
+
+/// Handles listing the contests the user's pets have been in, most recent first
+pub async fn route_get_battle_logs(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(app_state.get_battle_logs(&uuid)).to_string())
+        .unwrap()
+}
+
+/// Handles showing a contest's battle log to anyone, so it can be shared and played back
+pub async fn route_get_public_battle_log(battle_uuid: Path<String>) -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    match app_state.get_battle_log(&battle_uuid) {
+        Some(log) => Response::builder()
+            .status(StatusCode::OK)
+            .body(serde_json::json!(log).to_string())
+            .unwrap(),
+        None => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("Battle not found".to_string())
+            .unwrap(),
+    }
+}
+
+fn contest_error_response(error: ContestError) -> Response<String> {
+    let status = match error {
+        ContestError::NotFound => StatusCode::NOT_FOUND,
+        ContestError::NotOwner | ContestError::NotInSameYard => StatusCode::FORBIDDEN,
+        ContestError::SameOwner | ContestError::AlreadyChallenged => StatusCode::CONFLICT,
+        ContestError::Dead | ContestError::Expired => StatusCode::GONE,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(error.to_string())
+        .unwrap()
+}
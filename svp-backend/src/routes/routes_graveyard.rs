@@ -0,0 +1,75 @@
+use crate::auth::*;
+use crate::graveyard::ReviveError;
+use aide::axum::IntoApiResponse;
+use axum::extract::Path;
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+
+/// Handles getting the dead pets in a user's graveyard
+pub async fn route_get_graveyard(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let user = app_state.get_user_by_uuid(&uuid);
+
+    if user.is_none() {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("User not found".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let graves: Vec<serde_json::Value> = user
+        .unwrap()
+        .get_graveyard()
+        .iter()
+        .filter_map(|pet_uuid| app_state.get_grave_by_uuid(pet_uuid))
+        .map(|grave| grave.for_user(&app_state.config))
+        .collect();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(graves).to_string())
+        .unwrap()
+}
+
+/// Handles bringing a pet back from the graveyard, if it died recently enough
+pub async fn route_revive_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.revive_pet(&user_uuid, &pet_uuid) {
+        Ok(pet) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(pet.for_user(app_state.now(), &app_state.config))
+                .unwrap()
+        }
+        Err(e) => {
+            let status = match e {
+                ReviveError::NotFound | ReviveError::NotOwner => StatusCode::NOT_FOUND,
+                ReviveError::Disabled => StatusCode::FORBIDDEN,
+                ReviveError::TooLate => StatusCode::GONE,
+            };
+
+            Response::builder()
+                .status(status)
+                .body(e.to_string())
+                .unwrap()
+        }
+    }
+}
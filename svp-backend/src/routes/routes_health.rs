@@ -0,0 +1,89 @@
+use aide::axum::IntoApiResponse;
+use axum::http::{header, Response, StatusCode};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use crate::{lock_app_state, metrics, STATE_LOADED};
+
+
+/// Liveness check, the server is up if it can answer at all
+pub async fn route_healthz() -> impl IntoApiResponse {
+    Response::builder()
+        .status(StatusCode::OK)
+        .body("OK".to_string())
+        .unwrap()
+}
+
+/// Readiness check, the server is ready once the state is loaded and the state file can be written
+pub async fn route_readyz() -> impl IntoApiResponse {
+    if !STATE_LOADED.load(Ordering::SeqCst) {
+        return Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body("State not loaded".to_string())
+            .unwrap();
+    }
+
+    let state_file = lock_app_state().await.config.paths.state_file.clone();
+
+    if let Err(e) = check_writable(&state_file) {
+        tracing::warn!("Storage is not writable: {}", e);
+        return Response::builder()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .body("Storage not writable".to_string())
+            .unwrap();
+    }
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body("Ready".to_string())
+        .unwrap()
+}
+
+/// Exports metrics in the Prometheus text format
+pub async fn route_metrics() -> impl IntoApiResponse {
+    let app_state = lock_app_state().await;
+
+    let body = metrics::render(&app_state);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; version=0.0.4")
+        .body(body)
+        .unwrap()
+}
+
+/// Writes and removes a probe file next to the given file
+fn check_writable(file: &Path) -> std::io::Result<()> {
+    let mut probe = file.as_os_str().to_owned();
+    probe.push(".probe");
+
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn test_not_ready_when_state_cant_be_saved() {
+        STATE_LOADED.store(true, Ordering::SeqCst);
+
+        let set_state_file = |path: std::path::PathBuf| async move {
+            let mut app_state = lock_app_state().await;
+            let mut config = (*app_state.config).clone();
+            config.paths.state_file = path;
+            app_state.set_config(config);
+        };
+
+        // The state file's directory doesn't exist, so nothing can be written there
+        let missing = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        set_state_file(missing.join("state.json")).await;
+        let response = route_readyz().await.into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        set_state_file(std::env::temp_dir().join(format!("svp-ready-test-{}.json", uuid::Uuid::new_v4()))).await;
+        let response = route_readyz().await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
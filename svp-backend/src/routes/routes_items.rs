@@ -0,0 +1,43 @@
+use crate::auth::*;
+use aide::axum::IntoApiResponse;
+use axum::extract::Path;
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+
+/// Handles listing every item in the catalog
+pub async fn route_get_items() -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(app_state.config.items.for_public())
+        .unwrap()
+}
+
+/// Handles getting how many of each item a user has
+pub async fn route_get_inventory(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    match app_state.get_user_by_uuid(&uuid) {
+        Some(user) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(serde_json::json!(user.get_inventory()).to_string())
+                .unwrap()
+        }
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("User not found".to_string()) // Convert to String
+                .unwrap()
+        }
+    }
+}
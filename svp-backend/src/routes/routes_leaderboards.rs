@@ -0,0 +1,70 @@
+use crate::leaderboards::{Board, LeaderboardError};
+use aide::axum::IntoApiResponse;
+use axum::extract::{Path, Query};
+use axum::http::{Response, StatusCode};
+use crate::lock_app_state;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct LeaderboardQuery {
+    // Only pet leaderboards can be split by species
+    species: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+/// Handles listing the leaderboards and how many are ranked on each
+pub async fn route_get_leaderboards() -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    let boards: Vec<serde_json::Value> = Board::ALL
+        .iter()
+        .map(|board| {
+            serde_json::json!({
+                "board": board,
+                "by_species": board.is_for_pets(),
+                "total": app_state.leaderboards.get(*board, None).map_or(0, |ranking| ranking.len()),
+            })
+        })
+        .collect();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(boards).to_string())
+        .unwrap()
+}
+
+/// Handles getting a page of a leaderboard
+pub async fn route_get_leaderboard(board: Path<String>, query: Query<LeaderboardQuery>) -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    let board = match board.parse::<Board>() {
+        Ok(board) => board,
+        Err(e) => return leaderboard_error_response(e),
+    };
+
+    let config = &app_state.config.leaderboards;
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(config.default_page_size).clamp(1, config.max_page_size);
+
+    match app_state.get_leaderboard(board, query.species.as_deref(), offset, limit) {
+        Ok(page) => Response::builder()
+            .status(StatusCode::OK)
+            .body(page.to_string())
+            .unwrap(),
+        Err(e) => leaderboard_error_response(e),
+    }
+}
+
+fn leaderboard_error_response(error: LeaderboardError) -> Response<String> {
+    let status = match error {
+        LeaderboardError::UnknownBoard | LeaderboardError::UnknownSpecies => StatusCode::NOT_FOUND,
+        LeaderboardError::NotBySpecies => StatusCode::BAD_REQUEST,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(error.to_string())
+        .unwrap()
+}
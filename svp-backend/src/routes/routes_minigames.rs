@@ -0,0 +1,113 @@
+use crate::auth::*;
+use crate::minigames::{GameError, GameKind, GameMove};
+use crate::routes::routes_pets::pet_action_error_response;
+use aide::axum::IntoApiResponse;
+use axum::extract::{Path, Json};
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+
+/// Handles starting a mini-game with a pet.
+/// The game is one of number_guess, rock_paper_scissors or fetch.
+pub async fn route_start_game(headers: HeaderMap, Path((user_uuid, pet_uuid, kind)): Path<(String, String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    let kind: GameKind = match kind.parse() {
+        Ok(kind) => kind,
+        Err(e) => return game_error_response(e, app_state.now()),
+    };
+
+    match app_state.start_game(&user_uuid, &pet_uuid, kind) {
+        Ok(session) => Response::builder()
+            .status(StatusCode::OK)
+            .body(session.for_user(&app_state.config.minigames).to_string())
+            .unwrap(),
+        Err(e) => game_error_response(e, app_state.now()),
+    }
+}
+
+/// Handles playing a move in one of the user's games
+pub async fn route_play_game(headers: HeaderMap, Path((user_uuid, session_uuid)): Path<(String, String)>, payload: Json<GameMove>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.play_game(&user_uuid, &session_uuid, payload.0) {
+        Ok(outcome) => {
+            let config = &app_state.config;
+            let now = app_state.now();
+
+            let body = match outcome.score {
+                // The game is over, so show the rewarded pet instead of the session
+                Some(score) => {
+                    let pet = app_state
+                        .get_pet_by_uuid(&outcome.session.pet)
+                        .map(|pet| serde_json::from_str::<serde_json::Value>(&pet.for_user(now, config)).unwrap_or_default());
+
+                    serde_json::json!({
+                        "result": outcome.result,
+                        "score": score,
+                        "experience": outcome.experience,
+                        "pet": pet,
+                    })
+                }
+                None => serde_json::json!({
+                    "result": outcome.result,
+                    "session": outcome.session.for_user(&config.minigames),
+                }),
+            };
+
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(body.to_string())
+                .unwrap()
+        }
+        Err(e) => game_error_response(e, app_state.now()),
+    }
+}
+
+/// Handles listing the user's unfinished games
+pub async fn route_get_games(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(app_state.get_game_sessions(&uuid)).to_string())
+        .unwrap()
+}
+
+fn game_error_response(error: GameError, now: u64) -> Response<String> {
+    let status = match error {
+        GameError::Action(e) => return pet_action_error_response(e, now),
+        GameError::PetNotFound | GameError::SessionNotFound | GameError::UnknownGame => StatusCode::NOT_FOUND,
+        GameError::NotOwner => StatusCode::FORBIDDEN,
+        GameError::AlreadyPlaying | GameError::InvalidMove => StatusCode::CONFLICT,
+        GameError::Expired => StatusCode::GONE,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(error.to_string())
+        .unwrap()
+}
@@ -1,8 +1,9 @@
 use crate::{auth::*, PetYard};
+use crate::achievements::GameEvent;
 use aide::axum::IntoApiResponse;
 use axum::extract::{Path, Json};
 use axum::http::{Response, StatusCode, HeaderMap};
-use crate::APP_STATE;
+use crate::lock_app_state;
 use serde::Deserialize;
 use schemars::JsonSchema;
 
@@ -15,20 +16,23 @@ pub async fn route_get_pet_yard(headers: HeaderMap, Path((user_uuid, pet_yard_uu
             .unwrap();
     }
 
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     let pet_yard = app_state.get_pet_yard_by_uuid(&pet_yard_uuid);
 
-    if pet_yard.is_none() {
-        Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body("Pet yard not found".to_string()) // Convert to String
-            .unwrap()
-    } else {
-        Response::builder()
-            .status(StatusCode::OK)
-            .body(serde_json::to_string(pet_yard.unwrap()).unwrap()) // Convert to String
-            .unwrap()
+    match pet_yard {
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("Pet yard not found".to_string()) // Convert to String
+                .unwrap()
+        }
+        Some(pet_yard) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(serde_json::to_string(pet_yard).unwrap()) // Convert to String
+                .unwrap()
+        }
     }
 }
 
@@ -47,7 +51,7 @@ pub async fn route_update_pet_yard(headers: HeaderMap, Path((user_uuid, pet_yard
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet_yard = app_state.get_pet_yard_by_uuid(&pet_yard_uuid);
 
@@ -64,8 +68,8 @@ pub async fn route_update_pet_yard(headers: HeaderMap, Path((user_uuid, pet_yard
         pet_yard.set_name(payload.name.clone().unwrap());
     }
 
-    if payload.image.is_some() {
-        pet_yard.set_image(payload.image.unwrap());
+    if let Some(image) = payload.image {
+        pet_yard.set_image(image);
     }
 
     app_state.update_pet_yard(pet_yard.clone());
@@ -85,7 +89,7 @@ pub async fn route_delete_pet_yard(headers: HeaderMap, Path((user_uuid, pet_yard
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet_yard = app_state.get_pet_yard_by_uuid(&pet_yard_uuid);
 
@@ -122,7 +126,7 @@ pub async fn route_create_pet_yard(headers: HeaderMap, user_uuid: Path<String>,
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet_yard = PetYard::new(payload.name.clone().unwrap(), user_uuid.to_string(), payload.image.unwrap());
 
@@ -150,7 +154,7 @@ pub async fn route_add_member_to_pet_yard(headers: HeaderMap, Path((user_uuid, p
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet_yard = app_state.get_pet_yard_by_uuid(&pet_yard_uuid);
 
@@ -163,10 +167,16 @@ pub async fn route_add_member_to_pet_yard(headers: HeaderMap, Path((user_uuid, p
 
     let mut pet_yard = pet_yard.unwrap().to_owned();
 
-    pet_yard.add_member(member_uuid);
+    let joined = !pet_yard.get_members().contains(&member_uuid);
+
+    pet_yard.add_member(member_uuid.clone());
 
     app_state.update_pet_yard(pet_yard.clone());
 
+    if joined {
+        app_state.record_event(&member_uuid, GameEvent::JoinedYard, 1);
+    }
+
     Response::builder()
         .status(StatusCode::OK)
         .body(serde_json::to_string(&pet_yard).unwrap()) // Convert to String
@@ -182,7 +192,7 @@ pub async fn route_remove_member_from_pet_yard(headers: HeaderMap, Path((user_uu
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet_yard = app_state.get_pet_yard_by_uuid(&pet_yard_uuid);
 
@@ -215,7 +225,7 @@ pub async fn route_add_pet_to_pet_yard(headers: HeaderMap, Path((user_uuid, pet_
     }
 
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
 
     let pet_yard = app_state.get_pet_yard_by_uuid(&pet_yard_uuid);
@@ -248,7 +258,7 @@ pub async fn route_remove_pet_from_pet_yard(headers: HeaderMap, Path((user_uuid,
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet_yard = app_state.get_pet_yard_by_uuid(&pet_yard_uuid);
 
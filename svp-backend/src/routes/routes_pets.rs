@@ -1,8 +1,13 @@
-use crate::{auth::*, Pet};
+use crate::{auth::*, Pet, PetActionError};
+use crate::breeding::Genes;
+use crate::cooldowns::PetAction;
+use crate::items::ItemError;
+use crate::achievements::GameEvent;
 use aide::axum::IntoApiResponse;
 use axum::extract::{Path, Json};
 use axum::http::{Response, StatusCode, HeaderMap};
-use crate::APP_STATE;
+use crate::lock_app_state;
+use crate::routes::retry_after_secs;
 use serde::Deserialize;
 use schemars::JsonSchema;
 
@@ -16,20 +21,23 @@ pub async fn route_get_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path
             .unwrap();
     }
 
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     let pet = app_state.get_pet_by_uuid(&pet_uuid);
 
-    if pet.is_none() {
-        Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body("Pet not found".to_string()) // Convert to String
-            .unwrap()
-    } else {
-        Response::builder()
-            .status(StatusCode::OK)
-            .body(serde_json::to_string(pet.unwrap()).unwrap()) // Convert to String
-            .unwrap()
+    match pet {
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("Pet not found".to_string()) // Convert to String
+                .unwrap()
+        }
+        Some(pet) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(pet.for_user(app_state.now(), &app_state.config)) // Convert to String
+                .unwrap()
+        }
     }
 }
 
@@ -53,7 +61,7 @@ pub async fn route_update_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): P
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet = app_state.get_pet_by_uuid(&pet_uuid);
 
@@ -66,16 +74,34 @@ pub async fn route_update_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): P
 
     let mut pet = pet.unwrap().to_owned();
 
+    // The image has to suit the species, whichever of the two is changing
+    let species = payload.species.clone().unwrap_or(pet.get_species());
+    let image = payload.image.unwrap_or(pet.get_image());
+
+    if payload.species.is_some() || payload.image.is_some() {
+        if let Err(e) = app_state.config.species.check(&species, pet.get_form().as_deref(), image) {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(e.to_string())
+                .unwrap();
+        }
+    }
+
     if payload.name.is_some() {
         pet.set_name(payload.name.clone().unwrap());
     }
 
-    if payload.image.is_some() {
-        pet.set_image(payload.image.unwrap());
+    if let Some(image) = payload.image {
+        pet.set_image(image);
     }
 
     if payload.species.is_some() {
-        pet.set_species(payload.species.clone().unwrap());
+        if let Err(e) = pet.set_species(payload.species.clone().unwrap()) {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(e.to_string())
+                .unwrap();
+        }
     }
 
     if payload.pet_yard.is_some() {
@@ -86,7 +112,7 @@ pub async fn route_update_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): P
 
     Response::builder()
         .status(StatusCode::OK)
-        .body(serde_json::to_string(&pet).unwrap()) // Convert to String
+        .body(pet.for_user(app_state.now(), &app_state.config)) // Convert to String
         .unwrap()
 }
 
@@ -99,7 +125,7 @@ pub async fn route_delete_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): P
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet = app_state.get_pet_by_uuid(&pet_uuid);
 
@@ -136,9 +162,29 @@ pub async fn route_create_pet(headers: HeaderMap, user_uuid: Path<String>, paylo
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let (Some(name), Some(species), Some(image)) = (payload.name.clone(), payload.species.clone(), payload.image) else {
+        return Response::builder()
+            .status(StatusCode::BAD_REQUEST)
+            .body("Name, species and image are required".to_string())
+            .unwrap();
+    };
+
+    let mut app_state = lock_app_state().await;
+
+    let base_stats = match app_state.config.species.check(&species, None, image) {
+        Ok(species) => species.base_stats,
+        Err(e) => {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(e.to_string())
+                .unwrap();
+        }
+    };
 
-    let pet = Pet::new(payload.name.clone().unwrap(), payload.species.clone().unwrap(), payload.image.unwrap(), payload.pet_yard.clone());
+    let now = app_state.now();
+    let mut pet = Pet::new(name, species, image, payload.pet_yard.clone(), now);
+    pet.set_stats(base_stats, now);
+    pet.set_genes(Genes::random(&app_state.config.breeding));
 
     app_state.update_pet(pet.clone());
 
@@ -148,10 +194,11 @@ pub async fn route_create_pet(headers: HeaderMap, user_uuid: Path<String>, paylo
     user.add_pet(pet.get_uuid());
 
     app_state.update_user(user);
+    app_state.record_event(&user_uuid, GameEvent::PetCreated, 1);
 
     Response::builder()
         .status(StatusCode::OK)
-        .body(serde_json::to_string(&pet).unwrap()) // Convert to String
+        .body(pet.for_user(app_state.now(), &app_state.config)) // Convert to String
         .unwrap()
 }
 
@@ -164,7 +211,7 @@ pub async fn route_feed_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Pat
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet = app_state.get_pet_by_uuid(&pet_uuid);
 
@@ -175,15 +222,25 @@ pub async fn route_feed_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Pat
             .unwrap();
     }
 
+    if !app_state.can_care_for(&user_uuid, &pet_uuid) {
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body("Only the pet's owner, yard-mates and sitters can do that".to_string())
+            .unwrap();
+    }
+
     let mut pet = pet.unwrap().to_owned();
 
-    pet.feed();
+    if let Err(e) = pet.feed(app_state.now(), &app_state.config) {
+        return pet_action_error_response(e, app_state.now());
+    }
 
     app_state.update_pet(pet.clone());
+    app_state.record_care(&user_uuid, &pet_uuid, PetAction::Feed);
 
     Response::builder()
         .status(StatusCode::OK)
-        .body(serde_json::to_string(&pet).unwrap()) // Convert to String
+        .body(pet.for_user(app_state.now(), &app_state.config)) // Convert to String
         .unwrap()
 }
 
@@ -196,7 +253,7 @@ pub async fn route_pet_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let pet = app_state.get_pet_by_uuid(&pet_uuid);
 
@@ -207,14 +264,206 @@ pub async fn route_pet_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path
             .unwrap();
     }
 
+    if !app_state.can_care_for(&user_uuid, &pet_uuid) {
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body("Only the pet's owner, yard-mates and sitters can do that".to_string())
+            .unwrap();
+    }
+
     let mut pet = pet.unwrap().to_owned();
 
-    pet.pet();
+    if let Err(e) = pet.pet(app_state.now(), &app_state.config) {
+        return pet_action_error_response(e, app_state.now());
+    }
 
     app_state.update_pet(pet.clone());
+    app_state.record_care(&user_uuid, &pet_uuid, PetAction::Pet);
 
     Response::builder()
         .status(StatusCode::OK)
-        .body(serde_json::to_string(&pet).unwrap()) // Convert to String
+        .body(pet.for_user(app_state.now(), &app_state.config)) // Convert to String
         .unwrap()
-}
\ No newline at end of file
+}
+
+/// Gives medicine to a sick or critical pet, moving it back along the health chain
+pub async fn route_give_medicine_to_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    let pet = app_state.get_pet_by_uuid(&pet_uuid);
+
+    if pet.is_none() {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("Pet not found".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    if !app_state.can_care_for(&user_uuid, &pet_uuid) {
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body("Only the pet's owner, yard-mates and sitters can do that".to_string())
+            .unwrap();
+    }
+
+    let mut pet = pet.unwrap().to_owned();
+
+    if let Err(e) = pet.give_medicine(app_state.now(), &app_state.config) {
+        return pet_action_error_response(e, app_state.now());
+    }
+
+    app_state.update_pet(pet.clone());
+    app_state.record_care(&user_uuid, &pet_uuid, PetAction::Medicine);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(pet.for_user(app_state.now(), &app_state.config)) // Convert to String
+        .unwrap()
+}
+
+/// Lets a pet rest, restoring its energy
+pub async fn route_rest_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    let pet = app_state.get_pet_by_uuid(&pet_uuid);
+
+    if pet.is_none() {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("Pet not found".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    if !app_state.can_care_for(&user_uuid, &pet_uuid) {
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body("Only the pet's owner, yard-mates and sitters can do that".to_string())
+            .unwrap();
+    }
+
+    let mut pet = pet.unwrap().to_owned();
+
+    if let Err(e) = pet.rest(app_state.now(), &app_state.config) {
+        return pet_action_error_response(e, app_state.now());
+    }
+
+    app_state.update_pet(pet.clone());
+    app_state.record_care(&user_uuid, &pet_uuid, PetAction::Rest);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(pet.for_user(app_state.now(), &app_state.config)) // Convert to String
+        .unwrap()
+}
+
+/// Cleans a pet, restoring its hygiene
+pub async fn route_clean_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    let pet = app_state.get_pet_by_uuid(&pet_uuid);
+
+    if pet.is_none() {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("Pet not found".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    if !app_state.can_care_for(&user_uuid, &pet_uuid) {
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body("Only the pet's owner, yard-mates and sitters can do that".to_string())
+            .unwrap();
+    }
+
+    let mut pet = pet.unwrap().to_owned();
+
+    if let Err(e) = pet.clean(app_state.now(), &app_state.config) {
+        return pet_action_error_response(e, app_state.now());
+    }
+
+    app_state.update_pet(pet.clone());
+    app_state.record_care(&user_uuid, &pet_uuid, PetAction::Clean);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(pet.for_user(app_state.now(), &app_state.config)) // Convert to String
+        .unwrap()
+}
+
+/// Handles using an item from the user's inventory on one of their pets
+pub async fn route_use_item(headers: HeaderMap, Path((user_uuid, pet_uuid, item_id)): Path<(String, String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.use_item(&user_uuid, &pet_uuid, &item_id) {
+        Ok(pet) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(pet.for_user(app_state.now(), &app_state.config))
+                .unwrap()
+        }
+        Err(ItemError::Action(e)) => pet_action_error_response(e, app_state.now()),
+        Err(e) => {
+            let status = match e {
+                ItemError::PetNotFound | ItemError::UnknownItem => StatusCode::NOT_FOUND,
+                ItemError::NotOwner => StatusCode::FORBIDDEN,
+                ItemError::NotInInventory | ItemError::Action(_) => StatusCode::CONFLICT,
+            };
+
+            Response::builder()
+                .status(status)
+                .body(e.to_string())
+                .unwrap()
+        }
+    }
+}
+
+pub(crate) fn pet_action_error_response(error: PetActionError, now: u64) -> Response<String> {
+    let mut response = Response::builder();
+
+    let status = match error {
+        PetActionError::Dead => StatusCode::GONE,
+        PetActionError::NotSick | PetActionError::Refused => StatusCode::CONFLICT,
+        PetActionError::Cooldown { available_at } => {
+            response = response.header("Retry-After", retry_after_secs(available_at, now));
+            StatusCode::TOO_MANY_REQUESTS
+        }
+    };
+
+    response
+        .status(status)
+        .body(error.to_string())
+        .unwrap()
+}
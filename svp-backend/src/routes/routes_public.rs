@@ -1,61 +1,91 @@
 use aide::axum::IntoApiResponse;
 use axum::extract::Path;
 use axum::http::{Response, StatusCode};
-use crate::APP_STATE;
+use crate::lock_app_state;
 
 
 
 
 pub async fn route_get_public_user(uuid: Path<String>) -> impl IntoApiResponse  {
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     let user = app_state.get_user_by_uuid(&uuid);
 
-    if user.is_none() {
-        Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body("User not found".to_string()) // Convert to String
-            .unwrap()
-    } else {
-        Response::builder()
-            .status(StatusCode::OK)
-            .body(user.unwrap().for_public()) // Convert to String
-            .unwrap()
+    match user {
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("User not found".to_string()) // Convert to String
+                .unwrap()
+        }
+        Some(user) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(user.for_public()) // Convert to String
+                .unwrap()
+        }
     }
 }
 
 pub async fn route_get_public_pet(pet_uuid: Path<String>) -> impl IntoApiResponse  {
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     let pet = app_state.get_pet_by_uuid(&pet_uuid);
 
-    if pet.is_none() {
-        Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body("Pet not found".to_string()) // Convert to String
-            .unwrap()
-    } else {
-        Response::builder()
-            .status(StatusCode::OK)
-            .body(pet.unwrap().for_public()) // Convert to String
-            .unwrap()
+    match pet {
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("Pet not found".to_string()) // Convert to String
+                .unwrap()
+        }
+        Some(pet) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(pet.for_public(app_state.now(), &app_state.config)) // Convert to String
+                .unwrap()
+        }
     }
 }
 
 pub async fn route_get_public_pet_yard(pet_yard_uuid: Path<String>) -> impl IntoApiResponse  {
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     let pet_yard = app_state.get_pet_yard_by_uuid(&pet_yard_uuid);
 
-    if pet_yard.is_none() {
-        Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body("Pet yard not found".to_string()) // Convert to String
-            .unwrap()
-    } else {
-        Response::builder()
-            .status(StatusCode::OK)
-            .body(pet_yard.unwrap().for_public()) // Convert to String
-            .unwrap()
+    match pet_yard {
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("Pet yard not found".to_string()) // Convert to String
+                .unwrap()
+        }
+        Some(pet_yard) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(pet_yard.for_public()) // Convert to String
+                .unwrap()
+        }
+    }
+}
+
+pub async fn route_get_public_memorial(pet_uuid: Path<String>) -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    let grave = app_state.get_grave_by_uuid(&pet_uuid);
+
+    match grave {
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("Memorial not found".to_string()) // Convert to String
+                .unwrap()
+        }
+        Some(grave) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(grave.for_public(&app_state.config)) // Convert to String
+                .unwrap()
+        }
     }
 }
\ No newline at end of file
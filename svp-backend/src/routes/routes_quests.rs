@@ -0,0 +1,81 @@
+use crate::auth::*;
+use crate::quests::QuestError;
+use aide::axum::IntoApiResponse;
+use axum::extract::{Path, Json};
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+use serde::Deserialize;
+use schemars::JsonSchema;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct ClaimQuestPayload {
+    // UUID of the pet that gets the experience, can be left out if the user only has one
+    pet: Option<String>,
+}
+
+/// Handles listing the user's quests for today and their streak
+pub async fn route_get_quests(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let Some(user) = app_state.get_user_by_uuid(&uuid) else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("User not found".to_string()) // Convert to String
+            .unwrap();
+    };
+
+    let quests = user.get_quests(app_state.now(), &app_state.config.quests);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(quests).to_string())
+        .unwrap()
+}
+
+/// Handles claiming the reward for a finished quest
+pub async fn route_claim_quest(headers: HeaderMap, Path((user_uuid, quest_id)): Path<(String, String)>, payload: Json<ClaimQuestPayload>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.claim_quest(&user_uuid, &quest_id, payload.pet.clone()) {
+        Ok(quests) => {
+            let user = app_state.get_user_by_uuid(&user_uuid).unwrap();
+
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(serde_json::json!({
+                    "quests": quests,
+                    "coins": user.get_coins(),
+                    "inventory": user.get_inventory(),
+                }).to_string())
+                .unwrap()
+        }
+        Err(e) => {
+            let status = match e {
+                QuestError::NotFound | QuestError::PetNotFound => StatusCode::NOT_FOUND,
+                QuestError::NotComplete | QuestError::AlreadyClaimed | QuestError::PetDead => StatusCode::CONFLICT,
+                QuestError::PetRequired => StatusCode::BAD_REQUEST,
+            };
+
+            Response::builder()
+                .status(status)
+                .body(e.to_string())
+                .unwrap()
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::auth::*;
+use crate::economy::EconomyError;
+use aide::axum::IntoApiResponse;
+use axum::extract::{Path, Json};
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+use serde::Deserialize;
+use schemars::JsonSchema;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct BuyPayload {
+    // How many to buy, 1 if left out
+    quantity: Option<u32>,
+}
+
+/// Handles listing the items for sale and their prices
+pub async fn route_get_shop() -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(app_state.config.items.for_sale()).to_string())
+        .unwrap()
+}
+
+/// Handles buying items from the shop with the user's coins
+pub async fn route_buy_item(headers: HeaderMap, Path((user_uuid, item_id)): Path<(String, String)>, payload: Json<BuyPayload>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.buy_item(&user_uuid, &item_id, payload.quantity.unwrap_or(1)) {
+        Ok(balance) => {
+            let user = app_state.get_user_by_uuid(&user_uuid).unwrap();
+
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(serde_json::json!({
+                    "coins": balance,
+                    "inventory": user.get_inventory(),
+                }).to_string())
+                .unwrap()
+        }
+        Err(e) => {
+            let status = match e {
+                EconomyError::UserNotFound | EconomyError::UnknownItem => StatusCode::NOT_FOUND,
+                EconomyError::NotForSale | EconomyError::InsufficientFunds { .. } => StatusCode::CONFLICT,
+                EconomyError::InvalidQuantity => StatusCode::BAD_REQUEST,
+            };
+
+            Response::builder()
+                .status(status)
+                .body(e.to_string())
+                .unwrap()
+        }
+    }
+}
+
+/// Handles listing every change to the user's coin balance
+pub async fn route_get_ledger(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let Some(user) = app_state.get_user_by_uuid(&uuid) else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("User not found".to_string()) // Convert to String
+            .unwrap();
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!({
+            "coins": user.get_coins(),
+            "entries": app_state.get_ledger(&uuid),
+        }).to_string())
+        .unwrap()
+}
@@ -0,0 +1,13 @@
+use aide::axum::IntoApiResponse;
+use axum::http::{Response, StatusCode};
+use crate::lock_app_state;
+
+/// Handles listing the species pets can be, along with their traits
+pub async fn route_get_species() -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(app_state.config.species.for_public())
+        .unwrap()
+}
@@ -0,0 +1,155 @@
+use crate::auth::*;
+use crate::trading::{TradeBundle, TradeError};
+use aide::axum::IntoApiResponse;
+use axum::extract::{Path, Json};
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+use serde::Deserialize;
+use schemars::JsonSchema;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct TradePayload {
+    // What the user gives
+    #[serde(default)]
+    offered: TradeBundle,
+    // What the user wants in return
+    #[serde(default)]
+    requested: TradeBundle,
+}
+
+/// Handles offering another user a trade
+pub async fn route_propose_trade(headers: HeaderMap, Path((user_uuid, to_user_uuid)): Path<(String, String)>, payload: Json<TradePayload>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    let Json(payload) = payload;
+
+    match app_state.propose_trade(&user_uuid, &to_user_uuid, payload.offered, payload.requested) {
+        Ok(offer) => {
+            Response::builder()
+                .status(StatusCode::CREATED)
+                .body(offer.for_user().to_string())
+                .unwrap()
+        }
+        Err(e) => trade_error_response(e),
+    }
+}
+
+/// Handles listing the trade offers a user has made or been sent
+pub async fn route_get_trades(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let offers: Vec<serde_json::Value> = app_state
+        .get_trade_offers(&uuid)
+        .iter()
+        .map(|offer| offer.for_user())
+        .collect();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(offers).to_string())
+        .unwrap()
+}
+
+/// Handles accepting a trade offer, which swaps everything in it straight away
+pub async fn route_accept_trade(headers: HeaderMap, Path((user_uuid, offer_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.accept_trade(&user_uuid, &offer_uuid) {
+        Ok(_) => {
+            let user = app_state.get_user_by_uuid(&user_uuid).unwrap();
+
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(user.for_user())
+                .unwrap()
+        }
+        Err(e) => trade_error_response(e),
+    }
+}
+
+/// Handles answering a trade offer with a different one
+pub async fn route_counter_trade(headers: HeaderMap, Path((user_uuid, offer_uuid)): Path<(String, String)>, payload: Json<TradePayload>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    let Json(payload) = payload;
+
+    match app_state.counter_trade(&user_uuid, &offer_uuid, payload.offered, payload.requested) {
+        Ok(offer) => {
+            Response::builder()
+                .status(StatusCode::CREATED)
+                .body(offer.for_user().to_string())
+                .unwrap()
+        }
+        Err(e) => trade_error_response(e),
+    }
+}
+
+/// Handles turning down a trade offer, or cancelling one the user made
+pub async fn route_decline_trade(headers: HeaderMap, Path((user_uuid, offer_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.decline_trade(&user_uuid, &offer_uuid) {
+        Ok(()) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body("Trade offer declined".to_string())
+                .unwrap()
+        }
+        Err(e) => trade_error_response(e),
+    }
+}
+
+fn trade_error_response(error: TradeError) -> Response<String> {
+    let status = match error {
+        TradeError::NotFound => StatusCode::NOT_FOUND,
+        TradeError::SelfTrade | TradeError::EmptyTrade | TradeError::UnknownItem(_) => StatusCode::BAD_REQUEST,
+        TradeError::TooManyOffers => StatusCode::TOO_MANY_REQUESTS,
+        TradeError::Missing { .. } | TradeError::PetNotTradable(_) => StatusCode::CONFLICT,
+        TradeError::Expired => StatusCode::GONE,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(error.to_string())
+        .unwrap()
+}
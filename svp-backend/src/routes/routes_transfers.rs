@@ -0,0 +1,185 @@
+use crate::auth::*;
+use crate::transfers::TransferError;
+use aide::axum::IntoApiResponse;
+use axum::extract::Path;
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+
+/// Handles offering one of the user's pets to another user
+pub async fn route_offer_pet_transfer(headers: HeaderMap, Path((user_uuid, pet_uuid, to_user_uuid)): Path<(String, String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.offer_pet_transfer(&user_uuid, &pet_uuid, &to_user_uuid) {
+        Ok(offer) => {
+            Response::builder()
+                .status(StatusCode::CREATED)
+                .body(offer.for_user().to_string())
+                .unwrap()
+        }
+        Err(e) => transfer_error_response(e),
+    }
+}
+
+/// Handles listing the user's open transfer offers, and the pets they have given away or taken in
+pub async fn route_get_transfers(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let offers: Vec<serde_json::Value> = app_state
+        .get_transfer_offers(&uuid)
+        .iter()
+        .map(|offer| offer.for_user())
+        .collect();
+
+    let history = app_state.get_transfer_history(&uuid);
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!({
+            "offers": offers,
+            "history": history,
+        }).to_string())
+        .unwrap()
+}
+
+/// Handles taking a pet the user was offered
+pub async fn route_accept_pet_transfer(headers: HeaderMap, Path((user_uuid, offer_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.accept_pet_transfer(&user_uuid, &offer_uuid) {
+        Ok(pet) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(pet.for_user(app_state.now(), &app_state.config))
+                .unwrap()
+        }
+        Err(e) => transfer_error_response(e),
+    }
+}
+
+/// Handles turning down a transfer offer, or cancelling one the user made
+pub async fn route_decline_pet_transfer(headers: HeaderMap, Path((user_uuid, offer_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.decline_pet_transfer(&user_uuid, &offer_uuid) {
+        Ok(()) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body("Transfer offer declined".to_string())
+                .unwrap()
+        }
+        Err(e) => transfer_error_response(e),
+    }
+}
+
+/// Handles giving up one of the user's pets to the adoption center
+pub async fn route_surrender_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.surrender_pet(&user_uuid, &pet_uuid) {
+        Ok(listing) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(serde_json::json!(listing).to_string())
+                .unwrap()
+        }
+        Err(e) => transfer_error_response(e),
+    }
+}
+
+/// Handles listing the pets waiting in the adoption center
+pub async fn route_get_adoption_center() -> impl IntoApiResponse  {
+    let app_state = lock_app_state().await;
+
+    let listings: Vec<serde_json::Value> = app_state
+        .get_adoption_listings()
+        .iter()
+        .map(|(listing, pet)| {
+            serde_json::json!({
+                "listed_at": listing.listed_at,
+                "pet": serde_json::from_str::<serde_json::Value>(&pet.for_public(app_state.now(), &app_state.config)).unwrap(),
+            })
+        })
+        .collect();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(listings).to_string())
+        .unwrap()
+}
+
+/// Handles adopting a pet from the adoption center
+pub async fn route_adopt_pet(headers: HeaderMap, Path((user_uuid, pet_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.adopt_pet(&user_uuid, &pet_uuid) {
+        Ok(pet) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(pet.for_user(app_state.now(), &app_state.config))
+                .unwrap()
+        }
+        Err(e) => transfer_error_response(e),
+    }
+}
+
+fn transfer_error_response(error: TransferError) -> Response<String> {
+    let status = match error {
+        TransferError::NotFound => StatusCode::NOT_FOUND,
+        TransferError::NotOwner => StatusCode::FORBIDDEN,
+        TransferError::SelfTransfer | TransferError::AlreadyOffered => StatusCode::CONFLICT,
+        TransferError::Dead | TransferError::Expired => StatusCode::GONE,
+    };
+
+    Response::builder()
+        .status(status)
+        .body(error.to_string())
+        .unwrap()
+}
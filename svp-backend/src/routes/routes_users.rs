@@ -2,7 +2,7 @@ use crate::auth::*;
 use aide::axum::IntoApiResponse;
 use axum::extract::{Path, Json};
 use axum::http::{Response, StatusCode, HeaderMap};
-use crate::APP_STATE;
+use crate::lock_app_state;
 use serde::Deserialize;
 use schemars::JsonSchema;
 
@@ -16,20 +16,23 @@ pub async fn route_get_user(headers: HeaderMap, uuid: Path<String>) -> impl Into
             .unwrap();
     }
 
-    let app_state = APP_STATE.lock().await;
+    let app_state = lock_app_state().await;
 
     let user = app_state.get_user_by_uuid(&uuid);
 
-    if user.is_none() {
-        Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body("User not found".to_string()) // Convert to String
-            .unwrap()
-    } else {
-        Response::builder()
-            .status(StatusCode::OK)
-            .body(user.unwrap().for_user()) // Convert to String
-            .unwrap()
+    match user {
+        None => {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body("User not found".to_string()) // Convert to String
+                .unwrap()
+        }
+        Some(user) => {
+            Response::builder()
+                .status(StatusCode::OK)
+                .body(user.for_user()) // Convert to String
+                .unwrap()
+        }
     }
 }
 
@@ -50,7 +53,7 @@ pub async fn route_update_user(headers: HeaderMap, uuid: Path<String>, payload:
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let user = app_state.get_user_by_uuid(&uuid);
 
@@ -89,7 +92,7 @@ pub async fn route_delete_user(headers: HeaderMap, uuid: Path<String>) -> impl I
             .unwrap();
     }
 
-    let mut app_state = APP_STATE.lock().await;
+    let mut app_state = lock_app_state().await;
 
     let user = app_state.get_user_by_uuid(&uuid);
 
@@ -108,4 +111,33 @@ pub async fn route_delete_user(headers: HeaderMap, uuid: Path<String>) -> impl I
         .status(StatusCode::OK)
         .body("User deleted".to_string()) // Convert to String
         .unwrap()
-}
\ No newline at end of file
+}
+/// Handles listing every achievement, with the user's progress towards each and when they unlocked it
+pub async fn route_get_achievements(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let Some(user) = app_state.get_user_by_uuid(&uuid) else {
+        return Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body("User not found".to_string()) // Convert to String
+            .unwrap();
+    };
+
+    let achievements = app_state
+        .config
+        .achievements
+        .for_user(user.get_achievement_progress(), user.get_achievements());
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(achievements.to_string())
+        .unwrap()
+}
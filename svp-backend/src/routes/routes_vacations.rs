@@ -0,0 +1,150 @@
+use crate::auth::*;
+use crate::vacations::VacationError;
+use aide::axum::IntoApiResponse;
+use axum::extract::{Path, Json};
+use axum::http::{Response, StatusCode, HeaderMap};
+use crate::lock_app_state;
+use crate::routes::retry_after_secs;
+use serde::Deserialize;
+use schemars::JsonSchema;
+
+#[derive(Deserialize, JsonSchema)]
+pub struct VacationPayload {
+    days: u64,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct PetSittingPayload {
+    // UUID of the user looking after the pets
+    sitter: String,
+    pets: Vec<String>,
+    days: u64,
+}
+
+/// Handles going on vacation, which puts the user's pets on hold
+pub async fn route_start_vacation(headers: HeaderMap, uuid: Path<String>, payload: Json<VacationPayload>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.start_vacation(&uuid, payload.days) {
+        Ok(vacation) => Response::builder()
+            .status(StatusCode::OK)
+            .body(serde_json::json!(vacation).to_string())
+            .unwrap(),
+        Err(e) => vacation_error_response(e, app_state.now()),
+    }
+}
+
+/// Handles coming back from vacation early
+pub async fn route_end_vacation(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.end_vacation(&uuid) {
+        Ok(()) => Response::builder()
+            .status(StatusCode::OK)
+            .body("Welcome back".to_string())
+            .unwrap(),
+        Err(e) => vacation_error_response(e, app_state.now()),
+    }
+}
+
+/// Handles listing the pet-sittings the user has set up or is doing
+pub async fn route_get_pet_sittings(headers: HeaderMap, uuid: Path<String>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let app_state = lock_app_state().await;
+
+    let sittings: Vec<serde_json::Value> = app_state
+        .get_pet_sittings(&uuid)
+        .into_iter()
+        .map(|sitting| sitting.for_user())
+        .collect();
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .body(serde_json::json!(sittings).to_string())
+        .unwrap()
+}
+
+/// Handles letting another user feed and pet some of the user's pets
+pub async fn route_start_pet_sitting(headers: HeaderMap, uuid: Path<String>, payload: Json<PetSittingPayload>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.start_pet_sitting(&uuid, &payload.sitter, payload.pets.clone(), payload.days) {
+        Ok(sitting) => Response::builder()
+            .status(StatusCode::OK)
+            .body(sitting.for_user().to_string())
+            .unwrap(),
+        Err(e) => vacation_error_response(e, app_state.now()),
+    }
+}
+
+/// Handles ending a pet-sitting, by either the owner or the sitter
+pub async fn route_end_pet_sitting(headers: HeaderMap, Path((user_uuid, sitting_uuid)): Path<(String, String)>) -> impl IntoApiResponse  {
+    // Verify token
+    if !verify_token_header(&headers, &user_uuid).await {
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body("Unauthorized".to_string()) // Convert to String
+            .unwrap();
+    }
+
+    let mut app_state = lock_app_state().await;
+
+    match app_state.end_pet_sitting(&user_uuid, &sitting_uuid) {
+        Ok(()) => Response::builder()
+            .status(StatusCode::OK)
+            .body("Pet-sitting ended".to_string())
+            .unwrap(),
+        Err(e) => vacation_error_response(e, app_state.now()),
+    }
+}
+
+fn vacation_error_response(error: VacationError, now: u64) -> Response<String> {
+    let mut response = Response::builder();
+
+    let status = match error {
+        VacationError::UserNotFound | VacationError::PetNotFound | VacationError::SittingNotFound => StatusCode::NOT_FOUND,
+        VacationError::InvalidLength | VacationError::SameUser | VacationError::NoPets => StatusCode::BAD_REQUEST,
+        VacationError::NotOwner => StatusCode::FORBIDDEN,
+        VacationError::AlreadyAway | VacationError::NotAway | VacationError::TooManySittings => StatusCode::CONFLICT,
+        VacationError::TooSoon { available_at } => {
+            response = response.header("Retry-After", retry_after_secs(available_at, now));
+            StatusCode::TOO_MANY_REQUESTS
+        }
+    };
+
+    response
+        .status(status)
+        .body(error.to_string())
+        .unwrap()
+}
@@ -0,0 +1,239 @@
+/*
+
+This file simulates pets' lives over weeks, with a mock clock standing in for the real one.
+
+Each test moves the clock on an hour at a time and runs the same jobs the server runs
+periodically, so whole lifetimes of decay, sickness and death play out in milliseconds.
+
+*/
+
+use std::sync::Arc;
+
+use crate::clock::{MockClock, SharedClock};
+use crate::config::Config;
+use crate::cooldowns::PetAction;
+use crate::health::{DeathCause, HealthState};
+use crate::structs::{AppState, Pet, PetActionError, User};
+
+pub(crate) const MS_PER_HOUR: u64 = 1000 * 60 * 60;
+pub(crate) const MS_PER_DAY: u64 = MS_PER_HOUR * 24;
+// 2023-11-14, an arbitrary place to start
+pub(crate) const START: u64 = 1_700_000_000_000;
+
+/// An empty state running on a mock clock, for tests elsewhere as well as here
+pub(crate) fn mock_state(config: Config) -> (AppState, Arc<MockClock>) {
+    let clock = Arc::new(MockClock::new(START));
+
+    let mut state = AppState::new();
+    state.set_config(config);
+    state.set_clock(SharedClock::new(clock.clone()));
+
+    (state, clock)
+}
+
+/// Adds a user with a cat of their own, returning the user's and the cat's UUIDs
+pub(crate) fn add_user_with_pet(state: &mut AppState, name: &str) -> (String, String) {
+    let now = state.now();
+
+    let mut user = User::new(name.to_string(), format!("{}@example.com", name), "password".to_string(), now);
+    let mut pet = Pet::new(format!("{}'s cat", name), "cat".to_string(), 1, None, now);
+    pet.set_stats(state.config.species.traits("cat").base_stats, now);
+    user.add_pet(pet.get_uuid());
+
+    let (user_uuid, pet_uuid) = (user.get_uuid(), pet.get_uuid());
+    state.update_user(user);
+    state.update_pet(pet);
+
+    (user_uuid, pet_uuid)
+}
+
+/// A state with one user and their cat, running on a mock clock
+fn setup(config: Config) -> (AppState, Arc<MockClock>, String, String) {
+    let (mut state, clock) = mock_state(config);
+    let (user_uuid, pet_uuid) = add_user_with_pet(&mut state, "alice");
+
+    (state, clock, user_uuid, pet_uuid)
+}
+
+/// The jobs the server runs every kill check
+fn run_jobs(state: &mut AppState) {
+    state.pause_vacationing_pets();
+    state.kill_unloved_pets();
+    state.expire_transfer_offers();
+    state.expire_breeding_requests();
+    state.expire_trade_offers();
+    state.expire_game_sessions();
+    state.expire_contest_challenges();
+    state.expire_pet_sittings();
+    state.record_pet_ages();
+}
+
+/// Moves the clock on an hour at a time, running the jobs after each one
+fn pass_hours(state: &mut AppState, clock: &MockClock, hours: u64) {
+    for _ in 0..hours {
+        clock.advance(MS_PER_HOUR);
+        run_jobs(state);
+    }
+}
+
+/// Does what the pet routes do for an action, skipping it if it's on cooldown
+fn care(state: &mut AppState, user_uuid: &str, pet_uuid: &str, action: PetAction) {
+    let now = state.now();
+    let mut pet = state.get_pet_by_uuid(pet_uuid).unwrap().clone();
+
+    let result = match action {
+        PetAction::Feed => pet.feed(now, &state.config),
+        PetAction::Pet => pet.pet(now, &state.config),
+        PetAction::Rest => pet.rest(now, &state.config),
+        PetAction::Medicine => pet.give_medicine(now, &state.config),
+        PetAction::Clean => pet.clean(now, &state.config),
+        PetAction::Play => unreachable!("games are played through sessions"),
+    };
+
+    match result {
+        Ok(()) => {
+            state.update_pet(pet);
+            state.record_care(user_uuid, pet_uuid, action);
+        }
+        Err(PetActionError::Cooldown { .. }) => {}
+        Err(e) => panic!("couldn't {:?} the pet: {}", action, e),
+    }
+}
+
+fn health(state: &AppState, pet_uuid: &str) -> HealthState {
+    state.get_pet_by_uuid(pet_uuid).unwrap().get_health()
+}
+
+#[test]
+fn test_neglected_pets_starve_after_three_days() {
+    let (mut state, clock, user_uuid, pet_uuid) = setup(Config::default());
+
+    pass_hours(&mut state, &clock, 24);
+    assert_eq!(health(&state, &pet_uuid), HealthState::Healthy);
+
+    // Worn out before it gets hungry, then worse a day later
+    pass_hours(&mut state, &clock, 24);
+    assert_eq!(health(&state, &pet_uuid), HealthState::Sick);
+    pass_hours(&mut state, &clock, 20);
+    assert_eq!(health(&state, &pet_uuid), HealthState::Critical);
+
+    // Hunger runs out after 72 hours
+    pass_hours(&mut state, &clock, 4);
+    assert!(state.get_pet_by_uuid(&pet_uuid).is_none());
+
+    let grave = state.get_grave_by_uuid(&pet_uuid).unwrap();
+    assert_eq!(grave.cause, DeathCause::Starvation);
+    assert_eq!(grave.died_at, START + 72 * MS_PER_HOUR);
+    assert_eq!(state.get_user_by_uuid(&user_uuid).unwrap().get_graveyard(), vec![pet_uuid]);
+}
+
+#[test]
+fn test_pets_die_when_their_stats_run_out_however_rarely_they_are_checked() {
+    let (mut state, clock, _, pet_uuid) = setup(Config::default());
+
+    // Checked every 10 hours, so the check after the pet starves comes 8 hours late
+    for _ in 0..8 {
+        clock.advance(10 * MS_PER_HOUR);
+        run_jobs(&mut state);
+    }
+
+    let grave = state.get_grave_by_uuid(&pet_uuid).unwrap();
+    assert_eq!(grave.cause, DeathCause::Starvation);
+    assert_eq!(grave.died_at, START + 72 * MS_PER_HOUR);
+}
+
+#[test]
+fn test_pets_cared_for_every_few_hours_live_for_weeks() {
+    let (mut state, clock, user_uuid, pet_uuid) = setup(Config::default());
+
+    for _ in 0..(4 * 7 * 4) {
+        pass_hours(&mut state, &clock, 6);
+
+        for action in [PetAction::Feed, PetAction::Pet, PetAction::Rest, PetAction::Clean] {
+            care(&mut state, &user_uuid, &pet_uuid, action);
+        }
+
+        assert_eq!(health(&state, &pet_uuid), HealthState::Healthy);
+    }
+
+    let pet = state.get_pet_by_uuid(&pet_uuid).unwrap();
+    assert_eq!(pet.age_days(state.now()), 28);
+    assert!(pet.get_level() > 1);
+    assert!(state.graves.is_empty());
+}
+
+#[test]
+fn test_medicine_keeps_worn_out_pets_from_falling_straight_back_ill() {
+    let (mut state, clock, user_uuid, pet_uuid) = setup(Config::default());
+
+    // Worn out after 43.2 hours
+    pass_hours(&mut state, &clock, 44);
+    assert_eq!(health(&state, &pet_uuid), HealthState::Sick);
+
+    care(&mut state, &user_uuid, &pet_uuid, PetAction::Medicine);
+    assert_eq!(health(&state, &pet_uuid), HealthState::Hungry);
+
+    pass_hours(&mut state, &clock, 1);
+    assert_eq!(health(&state, &pet_uuid), HealthState::Hungry);
+}
+
+#[test]
+fn test_vacations_hold_pets_until_their_owner_is_back() {
+    let (mut state, clock, user_uuid, pet_uuid) = setup(Config::default());
+
+    state.start_vacation(&user_uuid, 14).unwrap();
+
+    // Two weeks without anyone feeding it, and nothing has changed
+    let before = state.get_pet_by_uuid(&pet_uuid).unwrap().current_stats(START, &state.config);
+    pass_hours(&mut state, &clock, 14 * 24);
+    let pet = state.get_pet_by_uuid(&pet_uuid).unwrap();
+    assert_eq!(pet.current_stats(state.now(), &state.config), before);
+    assert_eq!(pet.get_health(), HealthState::Healthy);
+
+    // Once the vacation is over, the pet needs looking after again
+    assert!(state.start_vacation(&user_uuid, 1).is_err());
+    pass_hours(&mut state, &clock, 3 * 24);
+    assert!(state.get_pet_by_uuid(&pet_uuid).is_none());
+    assert_eq!(state.get_grave_by_uuid(&pet_uuid).unwrap().died_at, START + 17 * MS_PER_DAY);
+}
+
+#[test]
+fn test_tokens_expire_after_their_lifetime() {
+    let (mut state, clock, user_uuid, _) = setup(Config::default());
+
+    let user = state.get_user_by_uuid(&user_uuid).unwrap().clone();
+    let token = state.create_token(&user);
+    let lifetime = state.config.auth.token_lifetime_ms();
+
+    clock.advance(lifetime - 1);
+    assert!(state.tokens[&token].is_valid(state.now()));
+
+    clock.advance(1);
+    assert!(!state.tokens[&token].is_valid(state.now()));
+}
+
+#[test]
+fn test_retry_after_and_births_come_from_the_state_clock() {
+    let (mut state, clock, _, pet_uuid) = setup(Config::default());
+
+    let mut pet = state.get_pet_by_uuid(&pet_uuid).unwrap().clone();
+    pet.feed(state.now(), &state.config).unwrap();
+    let error = pet.feed(state.now(), &state.config).unwrap_err();
+    let PetActionError::Cooldown { available_at } = error else {
+        panic!("expected a cooldown, got {}", error);
+    };
+
+    // Far from the real time, the header still counts down from the mock clock
+    clock.advance(1);
+    let response = crate::routes::routes_pets::pet_action_error_response(error, state.now());
+    let expected = (available_at - START - 1).div_ceil(1000).to_string();
+    assert_eq!(response.headers()["Retry-After"], expected.as_str());
+
+    // Pets saved before births were tracked are born when they're loaded
+    let mut saved = serde_json::to_value(state.get_pet_by_uuid(&pet_uuid).unwrap()).unwrap();
+    saved.as_object_mut().unwrap().remove("born");
+    state.update_pet(serde_json::from_value(saved).unwrap());
+    clock.advance(MS_PER_DAY);
+    state.set_missing_births();
+    assert_eq!(state.get_pet_by_uuid(&pet_uuid).unwrap().age_days(state.now()), 0);
+}
@@ -0,0 +1,291 @@
+/*
+
+This file handles the species registry, which lists the species pets can be.
+
+Each species has its own image variants, starting stats, decay rates and lifespan,
+all loaded from a data file so new species can be added without changing the code.
+
+*/
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use crate::art::ArtCatalog;
+use crate::contests::ContestTraits;
+use crate::growth::Evolution;
+use crate::items::{ItemCatalog, Preference};
+use crate::needs::{DecayRates, PetStats, MAX_STAT, MIN_STAT};
+
+// Used when no species file is given and there isn't one in the working directory
+const BUNDLED_SPECIES: &str = include_str!("../species.toml");
+
+pub const DEFAULT_SPECIES_PATH: &str = "species.toml";
+
+static UNKNOWN_SPECIES: Lazy<Species> = Lazy::new(Species::default);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Species {
+    // Valid values for a pet's image
+    pub images: Vec<u64>,
+    #[serde(default)]
+    pub base_stats: PetStats,
+    #[serde(default)]
+    pub decay_rates: DecayRates,
+    // Scales how long the pet can spend in each state on the health chain
+    #[serde(default = "default_lifespan_modifier")]
+    pub lifespan_modifier: f64,
+    // Forms the species can grow into
+    #[serde(default)]
+    pub evolutions: Vec<Evolution>,
+    // Foods that restore more than usual, by item id
+    #[serde(default)]
+    pub favorite_foods: Vec<String>,
+    // Foods the species won't eat
+    #[serde(default)]
+    pub refused_foods: Vec<String>,
+    // How the species does in contests
+    #[serde(default)]
+    pub contest_traits: ContestTraits,
+}
+
+fn default_lifespan_modifier() -> f64 {
+    1.0
+}
+
+impl Default for Species {
+    /// Traits used for pets whose species is no longer in the registry
+    fn default() -> Self {
+        Self {
+            images: vec![],
+            base_stats: PetStats::default(),
+            decay_rates: DecayRates::default(),
+            lifespan_modifier: default_lifespan_modifier(),
+            evolutions: vec![],
+            favorite_foods: vec![],
+            refused_foods: vec![],
+            contest_traits: ContestTraits::default(),
+        }
+    }
+}
+
+impl Species {
+    pub fn validate(&self, name: &str) -> Result<(), String> {
+        if self.images.is_empty() {
+            return Err(format!("species {} has no images", name));
+        }
+
+        let stats = [
+            self.base_stats.hunger,
+            self.base_stats.happiness,
+            self.base_stats.energy,
+            self.base_stats.hygiene,
+        ];
+        if stats.iter().any(|stat| !(MIN_STAT..=MAX_STAT).contains(stat)) {
+            return Err(format!("species {} has base stats outside 0 to 100", name));
+        }
+
+        if !self.decay_rates.is_valid() {
+            return Err(format!("species {} has an invalid decay rate", name));
+        }
+
+        if !self.lifespan_modifier.is_finite() || self.lifespan_modifier <= 0.0 {
+            return Err(format!("species {} must have a lifespan_modifier above 0", name));
+        }
+
+        if !self.contest_traits.is_valid() {
+            return Err(format!("species {} must have contest_traits above 0", name));
+        }
+
+        for (index, evolution) in self.evolutions.iter().enumerate() {
+            if self.evolutions[..index].iter().any(|other| other.form == evolution.form) {
+                return Err(format!("species {} has more than one form called {}", name, evolution.form));
+            }
+
+            if !(MIN_STAT..=MAX_STAT).contains(&evolution.min_care) || !evolution.decay_rates.is_valid() {
+                return Err(format!("species {} form {} has an invalid min_care or decay rate", name, evolution.form));
+            }
+
+            if evolution.images.iter().any(|image| !self.images.contains(image)) {
+                return Err(format!("species {} form {} has images the species doesn't", name, evolution.form));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn food_preference(&self, item: &str) -> Preference {
+        if self.refused_foods.iter().any(|food| food == item) {
+            Preference::Refused
+        } else if self.favorite_foods.iter().any(|food| food == item) {
+            Preference::Favorite
+        } else {
+            Preference::Neutral
+        }
+    }
+
+    pub fn form(&self, form: &str) -> Option<&Evolution> {
+        self.evolutions.iter().find(|evolution| evolution.form == form)
+    }
+
+    /// The species' decay rates, adjusted for the form the pet has taken
+    pub fn decay_rates_for(&self, form: Option<&str>) -> DecayRates {
+        match form.and_then(|form| self.form(form)) {
+            Some(evolution) => self.decay_rates.scaled(&evolution.decay_rates),
+            None => self.decay_rates,
+        }
+    }
+}
+
+/// Why a species and image pair was rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpeciesError {
+    UnknownSpecies,
+    InvalidImage,
+    AlreadyGrown,
+}
+
+impl std::fmt::Display for SpeciesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpeciesError::UnknownSpecies => write!(f, "Unknown species"),
+            SpeciesError::InvalidImage => write!(f, "Invalid image for species"),
+            SpeciesError::AlreadyGrown => write!(f, "Pets can't change species once they have grown into a form"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SpeciesRegistry {
+    species: BTreeMap<String, Species>,
+}
+
+impl Default for SpeciesRegistry {
+    fn default() -> Self {
+        Self::from_toml(BUNDLED_SPECIES).expect("bundled species file is invalid")
+    }
+}
+
+impl SpeciesRegistry {
+    /// Loads the registry from a file, falling back to the bundled one
+    /// if the default file doesn't exist
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if path == Path::new(DEFAULT_SPECIES_PATH) && !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read species file {}: {}", path.display(), e))?;
+
+        Self::from_toml(&contents)
+            .map_err(|e| format!("Invalid species file {}: {}", path.display(), e).into())
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, Box<dyn Error>> {
+        let registry: Self = toml::from_str(contents)?;
+        registry.validate()?;
+
+        Ok(registry)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.species.is_empty() {
+            return Err("the species registry is empty".to_string());
+        }
+
+        for (name, species) in self.species.iter() {
+            species.validate(name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks every species has art for each of its images
+    pub fn validate_art(&self, art: &ArtCatalog) -> Result<(), String> {
+        for (name, species) in self.species.iter() {
+            if !art.has_species(name) {
+                return Err(format!("species {} has no art", name));
+            }
+
+            if let Some(image) = species.images.iter().find(|image| art.get(name, **image).is_none()) {
+                return Err(format!("species {} has no art for image {}", name, image));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks every species' food preferences are foods in the catalog
+    pub fn validate_items(&self, items: &ItemCatalog) -> Result<(), String> {
+        for (name, species) in self.species.iter() {
+            let foods = species.favorite_foods.iter().chain(species.refused_foods.iter());
+
+            if let Some(food) = foods.into_iter().find(|food| !items.is_food(food)) {
+                return Err(format!("species {} has a preference for {}, which isn't a food", name, food));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Species> {
+        self.species.get(name)
+    }
+
+    /// The species' traits, or neutral ones if it is unknown
+    pub fn traits(&self, name: &str) -> &Species {
+        self.get(name).unwrap_or(&UNKNOWN_SPECIES)
+    }
+
+    /// Checks the species exists and the image is one of its variants,
+    /// and one the pet's form allows if it has one
+    pub fn check(&self, name: &str, form: Option<&str>, image: u64) -> Result<&Species, SpeciesError> {
+        let species = self.get(name).ok_or(SpeciesError::UnknownSpecies)?;
+
+        if !species.images.contains(&image) {
+            return Err(SpeciesError::InvalidImage);
+        }
+
+        if let Some(evolution) = form.and_then(|form| species.form(form)) {
+            if !evolution.allows_image(image) {
+                return Err(SpeciesError::InvalidImage);
+            }
+        }
+
+        Ok(species)
+    }
+
+    pub fn for_public(&self) -> String {
+        serde_json::json!(self.species).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundled_registry() {
+        let registry = SpeciesRegistry::default();
+
+        assert!(registry.check("cat", None, 1).is_ok());
+        assert_eq!(registry.check("cat", None, 2).unwrap_err(), SpeciesError::InvalidImage);
+        assert_eq!(registry.check("dragon", None, 1).unwrap_err(), SpeciesError::UnknownSpecies);
+
+        // Forms limit which images can be used
+        assert!(registry.check("cat", Some("kitten"), 5).is_ok());
+        assert_eq!(registry.check("cat", Some("kitten"), 369).unwrap_err(), SpeciesError::InvalidImage);
+        assert_eq!(registry.traits("dragon"), &Species::default());
+    }
+
+    #[test]
+    fn test_invalid_species_are_rejected() {
+        assert!(SpeciesRegistry::from_toml("[cat]\nimages = []").is_err());
+        assert!(SpeciesRegistry::from_toml("[cat]\nimages = [1]\nlifespan_modifier = 0.0").is_err());
+        assert!(SpeciesRegistry::from_toml("[cat]\nimages = [1]\ncolour = \"red\"").is_err());
+    }
+}
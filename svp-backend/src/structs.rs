@@ -1,18 +1,81 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use rand::Rng;
+use std::sync::Arc;
 use uuid::Uuid;
 
 
+use crate::achievements::{self, EventProgress, GameEvent};
+use crate::art::{self, Mood};
+use crate::breeding::{BreedError, BreedingRequest, Genes};
+use crate::clock::SharedClock;
+use crate::config::Config;
+use crate::contests::{self, BattleLog, ContestChallenge, ContestError, ContestRecord, ContestResult, Contestant};
+use crate::cooldowns::{self, ActionLog, PetAction};
+use crate::economy::{self, EconomyError, LedgerEntry, LedgerReason, RewardTracker};
 use crate::encryption::hash;
-
-const EXP_PER_LEVEL: u8 = 100;
-
-
-#[derive(Default, Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Clone)]
+use crate::graveyard::{Grave, ReviveError};
+use crate::growth::{self, EvolutionRecord, GrowthStage};
+use crate::health::{self, HealthState, PetEvent, PetEventKind};
+use crate::items::{Item, ItemError, ItemKind, Preference};
+use crate::leaderboards::{self, Board, LeaderboardError, Leaderboards, Ranking};
+use crate::leveling;
+use crate::minigames::{GameError, GameKind, GameMove, GameSession, MoveOutcome, MAX_SCORE};
+use crate::needs::{restore, PetStats, StatsSnapshot};
+use crate::species::SpeciesError;
+use crate::quests::{QuestConfig, QuestError, QuestLog, QuestReward};
+use crate::trading::{TradeBundle, TradeError, TradeOffer};
+use crate::transfers::{AdoptionListing, TransferError, TransferEvent, TransferOffer, TransferRecord};
+use crate::vacations::{self, PetSitting, Vacation, VacationError};
+
+
+#[derive(Default, Debug, serde::Serialize, serde::Deserialize, PartialEq, Clone)]
 pub struct AppState {
     pub users: HashMap<String, User>,
     pub pets: HashMap<String, Pet>,
     pub pet_yards: HashMap<String, PetYard>,
     pub tokens: HashMap<String, UserToken>,
+    // Dead pets, by pet UUID
+    #[serde(default)]
+    pub graves: HashMap<String, Grave>,
+    // Breeding requests waiting for an answer, by request UUID
+    #[serde(default)]
+    pub breeding_requests: HashMap<String, BreedingRequest>,
+    // Transfer offers waiting for an answer, by offer UUID
+    #[serde(default)]
+    pub transfer_offers: HashMap<String, TransferOffer>,
+    // Pets nobody owns, waiting to be adopted, by pet UUID
+    #[serde(default)]
+    pub adoption_center: HashMap<String, AdoptionListing>,
+    // Every change of owner, oldest first
+    #[serde(default)]
+    pub transfer_log: Vec<TransferRecord>,
+    // Every change to a coin balance, oldest first
+    #[serde(default)]
+    pub ledger: Vec<LedgerEntry>,
+    // Trade offers waiting for an answer, by offer UUID
+    #[serde(default)]
+    pub trade_offers: HashMap<String, TradeOffer>,
+    // Mini-games being played, by session UUID
+    #[serde(default)]
+    pub game_sessions: HashMap<String, GameSession>,
+    // Contest challenges waiting for an answer, by challenge UUID
+    #[serde(default)]
+    pub contest_challenges: HashMap<String, ContestChallenge>,
+    // Every contest held, oldest first
+    #[serde(default)]
+    pub battle_logs: Vec<BattleLog>,
+    // Users looking after other users' pets, by sitting UUID
+    #[serde(default)]
+    pub pet_sittings: HashMap<String, PetSitting>,
+    // Rankings for the public leaderboards, rebuilt when the state is loaded
+    #[serde(skip)]
+    pub leaderboards: Leaderboards,
+    // Server config, loaded at startup rather than saved with the state
+    #[serde(skip)]
+    pub config: Arc<Config>,
+    // Where the time comes from, the system clock unless a test sets another
+    #[serde(skip)]
+    pub clock: SharedClock,
 }
 
 impl AppState {
@@ -22,11 +85,52 @@ impl AppState {
             pets: HashMap::new(),
             pet_yards: HashMap::new(),
             tokens: HashMap::new(),
+            graves: HashMap::new(),
+            breeding_requests: HashMap::new(),
+            transfer_offers: HashMap::new(),
+            adoption_center: HashMap::new(),
+            transfer_log: vec![],
+            ledger: vec![],
+            trade_offers: HashMap::new(),
+            game_sessions: HashMap::new(),
+            contest_challenges: HashMap::new(),
+            battle_logs: vec![],
+            pet_sittings: HashMap::new(),
+            leaderboards: Leaderboards::default(),
+            config: Arc::new(Config::default()),
+            clock: SharedClock::default(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.config = Arc::new(config);
+    }
+
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.clock = clock;
+    }
+
+    /// The time according to the state's clock, in milliseconds since the Unix epoch
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    /// Counts pets saved before births were tracked as born now, by the state's clock
+    pub fn set_missing_births(&mut self) {
+        let now = self.now();
+
+        for pet in self.pets.values_mut().filter(|pet| pet.born == 0) {
+            pet.born = now;
         }
     }
 
     pub fn create_token(&mut self, user: &User) -> String {
-        let token = UserToken::new(user.uuid.clone(), Uuid::new_v4().to_string());
+        let token = UserToken::new(
+            user.uuid.clone(),
+            Uuid::new_v4().to_string(),
+            self.now(),
+            self.config.auth.token_lifetime_ms(),
+        );
         self.tokens.insert(token.token.clone(), token.clone());
 
         token.token
@@ -46,102 +150,1797 @@ impl AppState {
         self.users.values().find(|&user| user.username == username)
     }
 
-    pub fn update_user(&mut self, user: User) {
-        self.users.insert(user.uuid.clone(), user);
-    }
+    pub fn update_user(&mut self, user: User) {
+        self.users.insert(user.uuid.clone(), user);
+    }
+
+    pub fn delete_user(&mut self, user: User) {
+        // First, delete the user's pets
+        for pet_uuid in user.pets.iter() {
+            self.delete_pet(pet_uuid);
+        }
+
+        // Next, delete the user's pet yards
+        for pet_yard_uuid in user.owned_pet_yards.iter() {
+            self.delete_pet_yard(pet_yard_uuid);
+        }
+
+        // Then clear out their graveyard
+        for pet_uuid in user.graveyard.iter() {
+            self.graves.remove(pet_uuid);
+        }
+
+        // And any breeding requests they are part of
+        self.breeding_requests
+            .retain(|_, request| request.from_user != user.uuid && request.to_user != user.uuid);
+
+        // And any transfer offers
+        self.transfer_offers
+            .retain(|_, offer| offer.from_user != user.uuid && offer.to_user != user.uuid);
+
+        // And any trades they are part of
+        self.trade_offers.retain(|_, offer| !offer.involves(&user.uuid));
+
+        // And any games they are playing
+        self.game_sessions.retain(|_, session| session.user != user.uuid);
+
+        // And any contest challenges
+        self.contest_challenges.retain(|_, challenge| !challenge.involves(&user.uuid));
+
+        // And any pet-sittings, as owner or sitter
+        self.pet_sittings.retain(|_, sitting| !sitting.involves(&user.uuid));
+
+        // And their coin history
+        self.ledger.retain(|entry| entry.user != user.uuid);
+
+        // And their place on the leaderboards
+        self.leaderboards.remove(Board::MostAchievements, &user.uuid);
+
+        // Finally, delete the user
+        self.users.remove(&user.uuid);
+    }
+
+    /*
+    
+    Pet functions
+    
+     */
+
+    pub fn get_pet_by_uuid(&self, uuid: &str) -> Option<&Pet> {
+        self.pets.get(uuid)
+    }
+
+    pub fn update_pet(&mut self, pet: Pet) {
+        Self::rank_pet(&mut self.leaderboards, &pet);
+        self.pets.insert(pet.uuid.clone(), pet);
+    }
+
+    /// UUID of the user who owns the pet
+    pub fn get_pet_owner(&self, pet_uuid: &str) -> Option<String> {
+        self.users
+            .values()
+            .find(|user| user.pets.iter().any(|uuid| uuid == pet_uuid))
+            .map(|user| user.get_uuid())
+    }
+
+    pub fn delete_pet(&mut self, uuid: &str) {
+        // Remove the pet from any pet yards it is in
+        for pet_yard in self.pet_yards.values_mut() {
+            pet_yard.remove_pet(uuid.to_string());
+        }
+
+        // Remove the pet from any users who own it
+        for user in self.users.values_mut() {
+            user.remove_pet(uuid.to_string());
+        }
+
+        // Drop any breeding requests and contest challenges it is in
+        self.breeding_requests
+            .retain(|_, request| request.pet != uuid && request.partner != uuid);
+        self.contest_challenges
+            .retain(|_, challenge| challenge.pet != uuid && challenge.opponent != uuid);
+
+        // And stop anyone sitting it
+        self.drop_pet_from_sittings(uuid);
+
+        self.leaderboards.remove_pet(uuid);
+
+        // Finally, delete the pet
+        self.pets.remove(uuid);
+    }
+
+    /// Moves every pet along the health chain, and buries the ones that have died
+    pub fn kill_unloved_pets(&mut self) {
+        let now = self.now();
+        let config = self.config.clone();
+        let mut dead_pets = vec![];
+
+        let on_vacation: HashSet<&String> = self
+            .users
+            .values()
+            .filter(|user| user.vacation.is_some())
+            .flat_map(|user| user.pets.iter())
+            .collect();
+
+        for pet in self.pets.values_mut() {
+            // Pets in the adoption center are looked after until they are adopted
+            if self.adoption_center.contains_key(&pet.uuid) {
+                continue;
+            }
+
+            // And pets whose owner is away are on hold until they're back
+            if on_vacation.contains(&pet.uuid) {
+                continue;
+            }
+
+            pet.update_health(now, &config);
+
+            if pet.is_dead() {
+                dead_pets.push(pet.get_uuid());
+            }
+        }
+
+        for pet_uuid in dead_pets {
+            tracing::info!("Pet {} has died", self.pets[&pet_uuid].get_name());
+            crate::metrics::PETS_KILLED.inc();
+            self.bury_pet(&pet_uuid);
+        }
+    }
+
+    /// Uses an item from the user's inventory on one of their pets
+    pub fn use_item(&mut self, user_uuid: &str, pet_uuid: &str, item_id: &str) -> Result<Pet, ItemError> {
+        let now = self.now();
+        let config = self.config.clone();
+
+        let mut pet = self.get_pet_by_uuid(pet_uuid).cloned().ok_or(ItemError::PetNotFound)?;
+
+        if self.get_pet_owner(pet_uuid).as_deref() != Some(user_uuid) {
+            return Err(ItemError::NotOwner);
+        }
+
+        let item = config.items.get(item_id).ok_or(ItemError::UnknownItem)?;
+
+        let user = self.users.get_mut(user_uuid).ok_or(ItemError::NotOwner)?;
+        if !user.has_item(item_id) {
+            return Err(ItemError::NotInInventory);
+        }
+
+        let taken_off = pet.use_item(item_id, item, now, &config)?;
+
+        // Only used up once it has worked
+        user.take_item(item_id);
+        if let Some(accessory) = taken_off {
+            user.add_items(accessory, 1);
+        }
+
+        self.update_pet(pet.clone());
+
+        // Dressing a pet up isn't caring for it
+        if let Some(action) = item.kind.action() {
+            self.record_care(user_uuid, pet_uuid, action);
+        }
+
+        Ok(pet)
+    }
+
+    /*
+
+    Economy functions
+
+     */
+
+    /// Changes a user's balance and records it in the ledger, refusing to go below zero
+    fn change_coins(&mut self, user_uuid: &str, amount: i64, reason: LedgerReason, now: u64) -> Result<u64, EconomyError> {
+        let user = self.users.get_mut(user_uuid).ok_or(EconomyError::UserNotFound)?;
+
+        let balance = user.coins.checked_add_signed(amount).ok_or(EconomyError::InsufficientFunds {
+            balance: user.coins,
+            price: amount.unsigned_abs(),
+        })?;
+        user.coins = balance;
+
+        self.ledger.push(LedgerEntry::new(user_uuid.to_string(), amount, balance, reason, now));
+
+        Ok(balance)
+    }
+
+    /// Gives coins to a user
+    pub fn credit_coins(&mut self, user_uuid: &str, amount: u64, reason: LedgerReason) {
+        let now = self.now();
+
+        if amount > 0 {
+            let _ = self.change_coins(user_uuid, amount as i64, reason, now);
+        }
+    }
+
+    /// Gives the user their login reward, if they haven't had it today
+    pub fn reward_daily_login(&mut self, user_uuid: &str) {
+        let now = self.now();
+
+        let Some(user) = self.users.get_mut(user_uuid) else {
+            return;
+        };
+
+        if user.rewards.claim_daily_login(now) {
+            self.credit_coins(user_uuid, self.config.economy.daily_login_reward, LedgerReason::DailyLogin);
+        }
+    }
+
+    /// Whether the pet is in a pet yard the user belongs to
+    fn is_yard_mate(&self, user_uuid: &str, pet_uuid: &str) -> bool {
+        self.pet_yards.values().any(|pet_yard| {
+            pet_yard.get_pets().iter().any(|uuid| uuid == pet_uuid)
+                && pet_yard.get_members().iter().any(|uuid| uuid == user_uuid)
+        })
+    }
+
+    /// Rewards the user for caring for one of their own pets, with coins up to a daily
+    /// limit, and towards achievements and quests. Petting a yard-mate's pet only counts
+    /// towards quests and achievements.
+    pub fn record_care(&mut self, user_uuid: &str, pet_uuid: &str, action: PetAction) {
+        let now = self.now();
+
+        if self.get_pet_owner(pet_uuid).as_deref() != Some(user_uuid) {
+            if self.is_yard_mate(user_uuid, pet_uuid) {
+                self.record_yard_activity(pet_uuid);
+
+                if action == PetAction::Pet {
+                    self.record_event(user_uuid, GameEvent::YardMatePetted, 1);
+                }
+            }
+
+            return;
+        }
+
+        self.record_yard_activity(pet_uuid);
+
+        let per_day = self.config.economy.care_rewards_per_day;
+        let Some(user) = self.users.get_mut(user_uuid) else {
+            return;
+        };
+
+        if user.rewards.claim_care(now, per_day) {
+            self.credit_coins(
+                user_uuid,
+                self.config.economy.care_reward,
+                LedgerReason::Care { pet: pet_uuid.to_string() },
+            );
+        }
+
+        match action {
+            PetAction::Feed => self.record_event(user_uuid, GameEvent::PetFed, 1),
+            PetAction::Pet => self.record_event(user_uuid, GameEvent::PetPetted, 1),
+            PetAction::Rest => self.record_event(user_uuid, GameEvent::PetRested, 1),
+            PetAction::Clean => self.record_event(user_uuid, GameEvent::PetCleaned, 1),
+            PetAction::Play => self.record_event(user_uuid, GameEvent::GamePlayed, 1),
+            PetAction::Medicine => {}
+        }
+
+        if let Some(level) = self.get_pet_by_uuid(pet_uuid).map(|pet| pet.get_level()) {
+            self.record_event(user_uuid, GameEvent::LevelReached, level as u64);
+        }
+    }
+
+    /// Buys items from the shop. The balance is checked and taken, and the items given,
+    /// all while the state is locked, so two purchases can't spend the same coins.
+    pub fn buy_item(&mut self, user_uuid: &str, item_id: &str, quantity: u32) -> Result<u64, EconomyError> {
+        let now = self.now();
+
+        let item = self.config.items.get(item_id).ok_or(EconomyError::UnknownItem)?;
+        let unit_price = item.price.ok_or(EconomyError::NotForSale)?;
+
+        if quantity == 0 || quantity > self.config.economy.max_purchase_quantity {
+            return Err(EconomyError::InvalidQuantity);
+        }
+
+        let user = self.users.get(user_uuid).ok_or(EconomyError::UserNotFound)?;
+
+        let price = unit_price.saturating_mul(quantity as u64);
+        if price > user.coins || price > i64::MAX as u64 {
+            return Err(EconomyError::InsufficientFunds { balance: user.coins, price });
+        }
+
+        let balance = self.change_coins(
+            user_uuid,
+            -(price as i64),
+            LedgerReason::Purchase { item: item_id.to_string(), quantity },
+            now,
+        )?;
+
+        if let Some(user) = self.users.get_mut(user_uuid) {
+            user.add_items(item_id.to_string(), quantity);
+        }
+
+        self.record_event(user_uuid, GameEvent::ItemBought, 1);
+
+        Ok(balance)
+    }
+
+    /// Replays the ledger and checks it matches every user's balance
+    pub fn audit_coins(&self) -> Result<(), String> {
+        let balances = economy::replay(&self.ledger)?;
+
+        for user in self.users.values() {
+            let replayed = balances.get(&user.uuid).copied().unwrap_or(0);
+
+            if replayed != user.coins {
+                return Err(format!("{} has {} coins, but the ledger gives {}", user.uuid, user.coins, replayed));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes to the user's balance, most recent first
+    pub fn get_ledger(&self, user_uuid: &str) -> Vec<&LedgerEntry> {
+        self.ledger
+            .iter()
+            .rev()
+            .filter(|entry| entry.user == user_uuid)
+            .collect()
+    }
+
+    /*
+
+    Achievement functions
+
+     */
+
+    /// Counts something the user did towards their achievements and today's quests,
+    /// unlocking and paying out any achievements they have now earned
+    pub fn record_event(&mut self, user_uuid: &str, event: GameEvent, value: u64) {
+        let now = self.now();
+        let config = self.config.clone();
+
+        let Some(user) = self.users.get_mut(user_uuid) else {
+            return;
+        };
+
+        achievements::record(&mut user.achievement_progress, event, value);
+
+        user.quests.refresh(user_uuid, now, &config.quests);
+        user.quests.record(event, value);
+
+        let unlocked: Vec<String> = config
+            .achievements
+            .newly_unlocked(&user.achievement_progress, &user.achievements)
+            .into_iter()
+            .cloned()
+            .collect();
+
+        for id in unlocked {
+            let Some(user) = self.users.get_mut(user_uuid) else {
+                return;
+            };
+            user.achievements.insert(id.clone(), now);
+
+            tracing::info!("User {} unlocked achievement {}", user_uuid, id);
+
+            let reward = config
+                .achievements
+                .get(&id)
+                .and_then(|achievement| achievement.reward)
+                .unwrap_or(config.economy.achievement_reward);
+            self.credit_coins(user_uuid, reward, LedgerReason::Achievement { achievement: id });
+
+            if let Some(user) = self.users.get(user_uuid) {
+                self.leaderboards.set(Board::MostAchievements, user_uuid, user.achievements.len() as u64);
+            }
+        }
+    }
+
+    /// Counts how long each user's pets have been alive towards their achievements
+    pub fn record_pet_ages(&mut self) {
+        let now = self.now();
+
+        let oldest: Vec<(String, u64)> = self
+            .users
+            .values()
+            .filter_map(|user| {
+                let days = user
+                    .pets
+                    .iter()
+                    .filter_map(|pet_uuid| self.pets.get(pet_uuid))
+                    .filter(|pet| !pet.is_dead())
+                    .map(|pet| pet.age_days(now))
+                    .max()?;
+
+                Some((user.get_uuid(), days))
+            })
+            .collect();
+
+        for (user_uuid, days) in oldest {
+            self.record_event(&user_uuid, GameEvent::PetAlive, days);
+        }
+    }
+
+    /*
+
+    Quest functions
+
+     */
+
+    /// Claims a finished quest, giving its reward and any streak bonus.
+    /// Experience goes to the chosen pet, which can be left out if the user only has one.
+    pub fn claim_quest(&mut self, user_uuid: &str, quest_id: &str, pet_uuid: Option<String>) -> Result<QuestLog, QuestError> {
+        let now = self.now();
+        let config = self.config.clone();
+
+        let user = self.users.get(user_uuid).ok_or(QuestError::NotFound)?;
+        let mut quests = user.get_quests(now, &config.quests);
+
+        let quest = quests.quests.iter().find(|quest| quest.id == quest_id).ok_or(QuestError::NotFound)?;
+
+        if quest.claimed {
+            return Err(QuestError::AlreadyClaimed);
+        }
+
+        if !quest.is_complete() {
+            return Err(QuestError::NotComplete);
+        }
+
+        let reward: QuestReward = quest.reward.clone();
+
+        // Check the pet before claiming, so a mistake doesn't use up the quest
+        let mut pet = None;
+        if reward.experience > 0 {
+            let living: Vec<&String> = user
+                .pets
+                .iter()
+                .filter(|uuid| self.pets.get(*uuid).is_some_and(|pet| !pet.is_dead()))
+                .collect();
+
+            let chosen = match pet_uuid {
+                Some(pet_uuid) => pet_uuid,
+                None if living.len() == 1 => living[0].clone(),
+                None => return Err(QuestError::PetRequired),
+            };
+
+            if !user.pets.contains(&chosen) {
+                return Err(QuestError::PetNotFound);
+            }
+
+            let mut chosen = self.pets.get(&chosen).cloned().ok_or(QuestError::PetNotFound)?;
+            chosen.update_health(now, &config);
+
+            if chosen.is_dead() {
+                return Err(QuestError::PetDead);
+            }
+
+            pet = Some(chosen);
+        }
+
+        quests.claim(quest_id)?;
+
+        let Some(user) = self.users.get_mut(user_uuid) else {
+            return Err(QuestError::NotFound);
+        };
+
+        for (item, count) in reward.items.iter() {
+            user.add_items(item.clone(), *count);
+        }
+
+        user.quests = quests.clone();
+
+        self.credit_coins(user_uuid, reward.coins, LedgerReason::Quest { quest: quest_id.to_string() });
+
+        if quests.finished_today() {
+            let bonus = (quests.streak as u64)
+                .saturating_mul(config.quests.streak_bonus_coins)
+                .min(config.quests.max_streak_bonus);
+            self.credit_coins(user_uuid, bonus, LedgerReason::QuestStreak { streak: quests.streak });
+        }
+
+        if let Some(mut pet) = pet {
+            pet.add_experience(reward.experience, now, &config);
+            let level = pet.get_level();
+            self.update_pet(pet);
+            self.record_event(user_uuid, GameEvent::LevelReached, level as u64);
+        }
+
+        Ok(quests)
+    }
+
+    /*
+
+    Graveyard functions
+
+     */
+
+    pub fn get_grave_by_uuid(&self, pet_uuid: &str) -> Option<&Grave> {
+        self.graves.get(pet_uuid)
+    }
+
+    /// Moves a dead pet out of its owner's pets and yards and into their graveyard
+    pub fn bury_pet(&mut self, pet_uuid: &str) {
+        let Some(mut pet) = self.pets.get(pet_uuid).cloned() else {
+            return;
+        };
+
+        let owner = self.get_pet_owner(pet_uuid);
+
+        self.delete_pet(pet_uuid);
+
+        // Pets nobody owns have nowhere to be buried
+        let Some(owner) = owner else {
+            return;
+        };
+
+        pet.remove_pet_yard();
+
+        let died_at = pet.died_at();
+        let cause = health::cause_of_death(&pet.current_stats(died_at, &self.config), &self.config.needs);
+
+        if let Some(user) = self.users.get_mut(&owner) {
+            user.add_grave(pet.get_uuid());
+        }
+
+        self.graves.insert(pet.get_uuid(), Grave::new(pet, owner, cause, died_at));
+    }
+
+    /// Brings a pet back from its owner's graveyard, if it died recently enough
+    pub fn revive_pet(&mut self, user_uuid: &str, pet_uuid: &str) -> Result<Pet, ReviveError> {
+        let now = self.now();
+
+        let grave = self.graves.get(pet_uuid).ok_or(ReviveError::NotFound)?;
+
+        if grave.owner != user_uuid {
+            return Err(ReviveError::NotOwner);
+        }
+
+        grave.check_revivable(now, &self.config.graveyard)?;
+
+        let mut pet = self.graves.remove(pet_uuid).unwrap().pet;
+        pet.revive(now, &self.config);
+
+        if let Some(user) = self.users.get_mut(user_uuid) {
+            user.remove_grave(pet.get_uuid());
+            user.add_pet(pet.get_uuid());
+        }
+
+        self.update_pet(pet.clone());
+
+        Ok(pet)
+    }
+
+
+    /*
+
+    Breeding functions
+
+     */
+
+    /// Checks two pets can breed, returning them caught up to now along with the partner's owner
+    fn check_breeding(&self, user_uuid: &str, pet_uuid: &str, partner_uuid: &str, now: u64) -> Result<(Pet, Pet, String), BreedError> {
+        if pet_uuid == partner_uuid {
+            return Err(BreedError::SamePet);
+        }
+
+        let mut pet = self.get_pet_by_uuid(pet_uuid).cloned().ok_or(BreedError::NotFound)?;
+        let mut partner = self.get_pet_by_uuid(partner_uuid).cloned().ok_or(BreedError::NotFound)?;
+
+        if self.get_pet_owner(pet_uuid).as_deref() != Some(user_uuid) {
+            return Err(BreedError::NotOwner);
+        }
+
+        let partner_owner = self.get_pet_owner(partner_uuid).ok_or(BreedError::NotFound)?;
+
+        // Pets with different owners have to meet in a pet yard both owners belong to
+        if partner_owner != user_uuid {
+            let share_pet_yard = self.pet_yards.values().any(|pet_yard| {
+                let pets = pet_yard.get_pets();
+                let members = pet_yard.get_members();
+
+                pets.iter().any(|uuid| uuid == pet_uuid)
+                    && pets.iter().any(|uuid| uuid == partner_uuid)
+                    && members.iter().any(|uuid| uuid == user_uuid)
+                    && members.contains(&partner_owner)
+            });
+
+            if !share_pet_yard {
+                return Err(BreedError::NotInSameYard);
+            }
+        }
+
+        pet.update_health(now, &self.config);
+        partner.update_health(now, &self.config);
+
+        pet.check_can_breed(now, &self.config)?;
+        partner.check_can_breed(now, &self.config)?;
+
+        if pet.get_species() != partner.get_species() {
+            return Err(BreedError::DifferentSpecies);
+        }
+
+        Ok((pet, partner, partner_owner))
+    }
+
+    /// Breeds the two pets and gives the egg to the user
+    fn lay_egg(&mut self, user_uuid: &str, mut pet: Pet, mut partner: Pet, name: Option<String>, now: u64) -> Pet {
+        let egg = pet.breed_with(&partner, name, now, &self.config);
+
+        pet.set_last_bred(now);
+        partner.set_last_bred(now);
+
+        self.update_pet(pet);
+        self.update_pet(partner);
+        self.update_pet(egg.clone());
+
+        if let Some(user) = self.users.get_mut(user_uuid) {
+            user.add_pet(egg.get_uuid());
+        }
+
+        self.record_event(user_uuid, GameEvent::PetCreated, 1);
+
+        egg
+    }
+
+    /// Breeds two pets straight away if the user owns both,
+    /// otherwise asks the partner's owner with a breeding request
+    pub fn breed_pets(&mut self, user_uuid: &str, pet_uuid: &str, partner_uuid: &str, name: Option<String>) -> Result<BreedOutcome, BreedError> {
+        let now = self.now();
+
+        let (pet, partner, partner_owner) = self.check_breeding(user_uuid, pet_uuid, partner_uuid, now)?;
+
+        if partner_owner == user_uuid {
+            return Ok(BreedOutcome::Egg(Box::new(self.lay_egg(user_uuid, pet, partner, name, now))));
+        }
+
+        let open_requests = self
+            .breeding_requests
+            .values()
+            .filter(|request| request.from_user == user_uuid && !request.is_expired(now))
+            .count();
+        if open_requests >= self.config.breeding.max_open_requests {
+            return Err(BreedError::TooManyRequests);
+        }
+
+        let request = BreedingRequest::new(
+            user_uuid.to_string(),
+            pet.get_uuid(),
+            partner_owner,
+            partner.get_uuid(),
+            name,
+            now,
+            &self.config.breeding,
+        );
+        self.breeding_requests.insert(request.uuid.clone(), request.clone());
+
+        Ok(BreedOutcome::Requested(request))
+    }
+
+    /// Breeding requests the user has made or been asked, leaving out expired ones
+    pub fn get_breeding_requests(&self, user_uuid: &str) -> Vec<&BreedingRequest> {
+        let now = self.now();
+
+        self.breeding_requests
+            .values()
+            .filter(|request| request.from_user == user_uuid || request.to_user == user_uuid)
+            .filter(|request| !request.is_expired(now))
+            .collect()
+    }
+
+    /// Lets the partner's owner agree to a breeding request, giving the egg to the user who asked
+    pub fn accept_breeding_request(&mut self, user_uuid: &str, request_uuid: &str) -> Result<Pet, BreedError> {
+        let now = self.now();
+
+        let request = self.breeding_requests.get(request_uuid).cloned().ok_or(BreedError::NotFound)?;
+
+        if request.to_user != user_uuid {
+            return Err(BreedError::NotFound);
+        }
+
+        if request.is_expired(now) {
+            self.breeding_requests.remove(request_uuid);
+            return Err(BreedError::Expired);
+        }
+
+        // Things may have changed since the request was made
+        let (pet, partner, partner_owner) = self.check_breeding(&request.from_user, &request.pet, &request.partner, now)?;
+
+        if partner_owner != user_uuid {
+            return Err(BreedError::NotOwner);
+        }
+
+        self.breeding_requests.remove(request_uuid);
+
+        Ok(self.lay_egg(&request.from_user, pet, partner, request.name, now))
+    }
+
+    /// Declines a breeding request, or cancels it if the user made it
+    pub fn decline_breeding_request(&mut self, user_uuid: &str, request_uuid: &str) -> Result<(), BreedError> {
+        match self.breeding_requests.get(request_uuid) {
+            Some(request) if request.from_user == user_uuid || request.to_user == user_uuid => {
+                self.breeding_requests.remove(request_uuid);
+                Ok(())
+            }
+            _ => Err(BreedError::NotFound),
+        }
+    }
+
+    /// Drops breeding requests nobody answered in time
+    pub fn expire_breeding_requests(&mut self) {
+        let now = self.now();
+
+        self.breeding_requests.retain(|_, request| !request.is_expired(now));
+    }
+
+    /*
+
+    Transfer functions
+
+     */
+
+    /// Gives a pet to a new owner, or to the adoption center if there isn't one.
+    /// The pet leaves its old owner and every pet yard, and any offers or breeding
+    /// requests for it are dropped, all in one go.
+    fn move_pet(&mut self, pet_uuid: &str, from_user: Option<String>, to_user: Option<String>, event: TransferEvent, now: u64) {
+        for user in self.users.values_mut() {
+            user.remove_pet(pet_uuid.to_string());
+        }
+
+        for pet_yard in self.pet_yards.values_mut() {
+            pet_yard.remove_pet(pet_uuid.to_string());
+        }
+
+        if let Some(pet) = self.pets.get_mut(pet_uuid) {
+            pet.remove_pet_yard();
+        }
+
+        if let Some(user) = to_user.as_ref().and_then(|uuid| self.users.get_mut(uuid)) {
+            user.add_pet(pet_uuid.to_string());
+        }
+
+        self.transfer_offers.retain(|_, offer| offer.pet != pet_uuid);
+        self.breeding_requests
+            .retain(|_, request| request.pet != pet_uuid && request.partner != pet_uuid);
+        self.contest_challenges
+            .retain(|_, challenge| challenge.pet != pet_uuid && challenge.opponent != pet_uuid);
+        self.drop_pet_from_sittings(pet_uuid);
+
+        self.record_transfer(pet_uuid, from_user, to_user, event, now);
+    }
+
+    fn record_transfer(&mut self, pet_uuid: &str, from_user: Option<String>, to_user: Option<String>, event: TransferEvent, now: u64) {
+        self.transfer_log.push(TransferRecord {
+            timestamp: now,
+            pet: pet_uuid.to_string(),
+            from_user,
+            to_user,
+            event,
+        });
+
+        let max_records = self.config.transfers.max_audit_records;
+        if self.transfer_log.len() > max_records {
+            let excess = self.transfer_log.len() - max_records;
+            self.transfer_log.drain(..excess);
+        }
+    }
+
+    /// Checks the user owns the pet and it is alive, returning it caught up to now
+    fn check_giving_away(&self, user_uuid: &str, pet_uuid: &str, now: u64) -> Result<Pet, TransferError> {
+        let mut pet = self.get_pet_by_uuid(pet_uuid).cloned().ok_or(TransferError::NotFound)?;
+
+        if self.get_pet_owner(pet_uuid).as_deref() != Some(user_uuid) {
+            return Err(TransferError::NotOwner);
+        }
+
+        pet.update_health(now, &self.config);
+
+        if pet.is_dead() {
+            return Err(TransferError::Dead);
+        }
+
+        Ok(pet)
+    }
+
+    /// Offers one of the user's pets to another user
+    pub fn offer_pet_transfer(&mut self, user_uuid: &str, pet_uuid: &str, to_user_uuid: &str) -> Result<TransferOffer, TransferError> {
+        let now = self.now();
+
+        if user_uuid == to_user_uuid {
+            return Err(TransferError::SelfTransfer);
+        }
+
+        if self.get_user_by_uuid(to_user_uuid).is_none() {
+            return Err(TransferError::NotFound);
+        }
+
+        self.check_giving_away(user_uuid, pet_uuid, now)?;
+
+        if self.transfer_offers.values().any(|offer| offer.pet == pet_uuid && !offer.is_expired(now)) {
+            return Err(TransferError::AlreadyOffered);
+        }
+
+        let offer = TransferOffer::new(
+            pet_uuid.to_string(),
+            user_uuid.to_string(),
+            to_user_uuid.to_string(),
+            now,
+            &self.config.transfers,
+        );
+        self.transfer_offers.retain(|_, offer| offer.pet != pet_uuid);
+        self.transfer_offers.insert(offer.uuid.clone(), offer.clone());
+
+        self.record_transfer(pet_uuid, Some(user_uuid.to_string()), Some(to_user_uuid.to_string()), TransferEvent::Offered, now);
+
+        Ok(offer)
+    }
+
+    /// Transfer offers the user has made or been sent, leaving out expired ones
+    pub fn get_transfer_offers(&self, user_uuid: &str) -> Vec<&TransferOffer> {
+        let now = self.now();
+
+        self.transfer_offers
+            .values()
+            .filter(|offer| offer.from_user == user_uuid || offer.to_user == user_uuid)
+            .filter(|offer| !offer.is_expired(now))
+            .collect()
+    }
+
+    /// Every change of owner the user was part of, most recent first
+    pub fn get_transfer_history(&self, user_uuid: &str) -> Vec<&TransferRecord> {
+        self.transfer_log
+            .iter()
+            .rev()
+            .filter(|record| record.involves(user_uuid))
+            .collect()
+    }
+
+    /// Lets the user take a pet they were offered
+    pub fn accept_pet_transfer(&mut self, user_uuid: &str, offer_uuid: &str) -> Result<Pet, TransferError> {
+        let now = self.now();
+
+        let offer = self.transfer_offers.get(offer_uuid).cloned().ok_or(TransferError::NotFound)?;
+
+        if offer.to_user != user_uuid {
+            return Err(TransferError::NotFound);
+        }
+
+        if offer.is_expired(now) {
+            self.transfer_offers.remove(offer_uuid);
+            self.record_transfer(&offer.pet, Some(offer.from_user), Some(offer.to_user), TransferEvent::Expired, now);
+            return Err(TransferError::Expired);
+        }
+
+        // The pet may have changed hands or died since the offer was made
+        let pet = match self.check_giving_away(&offer.from_user, &offer.pet, now) {
+            Ok(pet) => pet,
+            Err(e) => {
+                self.transfer_offers.remove(offer_uuid);
+                return Err(e);
+            }
+        };
+
+        self.update_pet(pet);
+        self.move_pet(&offer.pet, Some(offer.from_user), Some(offer.to_user), TransferEvent::Accepted, now);
+
+        Ok(self.pets[&offer.pet].clone())
+    }
+
+    /// Declines a transfer offer, or cancels it if the user made it
+    pub fn decline_pet_transfer(&mut self, user_uuid: &str, offer_uuid: &str) -> Result<(), TransferError> {
+        let now = self.now();
+
+        let event = match self.transfer_offers.get(offer_uuid) {
+            Some(offer) if offer.to_user == user_uuid => TransferEvent::Declined,
+            Some(offer) if offer.from_user == user_uuid => TransferEvent::Cancelled,
+            _ => return Err(TransferError::NotFound),
+        };
+
+        let offer = self.transfer_offers.remove(offer_uuid).unwrap();
+        self.record_transfer(&offer.pet, Some(offer.from_user), Some(offer.to_user), event, now);
+
+        Ok(())
+    }
+
+    /// Drops offers nobody answered in time
+    pub fn expire_transfer_offers(&mut self) {
+        let now = self.now();
+
+        let expired: Vec<TransferOffer> = self
+            .transfer_offers
+            .values()
+            .filter(|offer| offer.is_expired(now))
+            .cloned()
+            .collect();
+
+        for offer in expired {
+            self.transfer_offers.remove(&offer.uuid);
+            self.record_transfer(&offer.pet, Some(offer.from_user), Some(offer.to_user), TransferEvent::Expired, now);
+        }
+    }
+
+    /// Gives up one of the user's pets to the adoption center
+    pub fn surrender_pet(&mut self, user_uuid: &str, pet_uuid: &str) -> Result<AdoptionListing, TransferError> {
+        let now = self.now();
+
+        let mut pet = self.check_giving_away(user_uuid, pet_uuid, now)?;
+
+        // Stats are kept as they are while the pet waits to be adopted
+        pet.set_stats(pet.current_stats(now, &self.config), now);
+        self.update_pet(pet);
+
+        self.move_pet(pet_uuid, Some(user_uuid.to_string()), None, TransferEvent::Surrendered, now);
+
+        let listing = AdoptionListing {
+            pet: pet_uuid.to_string(),
+            surrendered_by: user_uuid.to_string(),
+            listed_at: now,
+        };
+        self.adoption_center.insert(pet_uuid.to_string(), listing.clone());
+
+        Ok(listing)
+    }
+
+    /// Pets in the adoption center, longest waiting first
+    pub fn get_adoption_listings(&self) -> Vec<(&AdoptionListing, &Pet)> {
+        let mut listings: Vec<(&AdoptionListing, &Pet)> = self
+            .adoption_center
+            .values()
+            .filter_map(|listing| Some((listing, self.pets.get(&listing.pet)?)))
+            .collect();
+
+        listings.sort_by_key(|(listing, _)| listing.listed_at);
+
+        listings
+    }
+
+    /// Takes a pet home from the adoption center
+    pub fn adopt_pet(&mut self, user_uuid: &str, pet_uuid: &str) -> Result<Pet, TransferError> {
+        let now = self.now();
+
+        if self.get_user_by_uuid(user_uuid).is_none() || !self.pets.contains_key(pet_uuid) {
+            return Err(TransferError::NotFound);
+        }
+
+        let listing = self.adoption_center.remove(pet_uuid).ok_or(TransferError::NotFound)?;
+
+        // Time in the adoption center doesn't count against the pet
+        if let Some(pet) = self.pets.get_mut(pet_uuid) {
+            pet.shift_clocks(now.saturating_sub(listing.listed_at));
+        }
+
+        self.move_pet(pet_uuid, None, Some(user_uuid.to_string()), TransferEvent::Adopted, now);
+        self.record_event(user_uuid, GameEvent::PetAdopted, 1);
+
+        Ok(self.pets[pet_uuid].clone())
+    }
+
+    /*
+
+    Trading functions
+
+     */
+
+    /// Checks the user has everything in their side of a trade right now
+    fn check_bundle(&self, user_uuid: &str, bundle: &TradeBundle, now: u64) -> Result<(), TradeError> {
+        let missing = || TradeError::Missing { user: user_uuid.to_string() };
+
+        let user = self.get_user_by_uuid(user_uuid).ok_or(TradeError::NotFound)?;
+
+        if user.coins < bundle.coins {
+            return Err(missing());
+        }
+
+        for (item_id, count) in bundle.items.iter() {
+            if self.config.items.get(item_id).is_none() {
+                return Err(TradeError::UnknownItem(item_id.clone()));
+            }
+
+            if user.item_count(item_id) < *count {
+                return Err(missing());
+            }
+        }
+
+        for (index, pet_uuid) in bundle.pets.iter().enumerate() {
+            if bundle.pets[..index].contains(pet_uuid) || !user.pets.contains(pet_uuid) {
+                return Err(missing());
+            }
+
+            let mut pet = self.get_pet_by_uuid(pet_uuid).cloned().ok_or_else(missing)?;
+            pet.update_health(now, &self.config);
+
+            if pet.is_dead() {
+                return Err(TradeError::PetNotTradable(pet_uuid.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a new offer or counter offer makes sense and both users could go through with it
+    fn check_trade(&self, from_user: &str, to_user: &str, offered: &TradeBundle, requested: &TradeBundle, now: u64) -> Result<(), TradeError> {
+        if from_user == to_user {
+            return Err(TradeError::SelfTrade);
+        }
+
+        if offered.is_empty() && requested.is_empty() {
+            return Err(TradeError::EmptyTrade);
+        }
+
+        let open_offers = self
+            .trade_offers
+            .values()
+            .filter(|offer| offer.from_user == from_user && !offer.is_expired(now))
+            .count();
+        if open_offers >= self.config.trading.max_open_offers {
+            return Err(TradeError::TooManyOffers);
+        }
+
+        self.check_bundle(from_user, offered, now)?;
+        self.check_bundle(to_user, requested, now)
+    }
+
+    /// Proposes a trade to another user
+    pub fn propose_trade(&mut self, user_uuid: &str, to_user_uuid: &str, offered: TradeBundle, requested: TradeBundle) -> Result<TradeOffer, TradeError> {
+        let now = self.now();
+
+        self.check_trade(user_uuid, to_user_uuid, &offered, &requested, now)?;
+
+        let offer = TradeOffer::new(
+            user_uuid.to_string(),
+            to_user_uuid.to_string(),
+            offered,
+            requested,
+            None,
+            now,
+            &self.config.trading,
+        );
+        self.trade_offers.insert(offer.uuid.clone(), offer.clone());
+
+        Ok(offer)
+    }
+
+    /// Gets an open offer sent to the user, dropping it if it has expired
+    fn take_received_trade(&mut self, user_uuid: &str, offer_uuid: &str, now: u64) -> Result<TradeOffer, TradeError> {
+        let offer = match self.trade_offers.get(offer_uuid) {
+            Some(offer) if offer.to_user == user_uuid => offer.clone(),
+            _ => return Err(TradeError::NotFound),
+        };
+
+        if offer.is_expired(now) {
+            self.trade_offers.remove(offer_uuid);
+            return Err(TradeError::Expired);
+        }
+
+        Ok(offer)
+    }
+
+    /// Answers a trade offer with a different one, which replaces it
+    pub fn counter_trade(&mut self, user_uuid: &str, offer_uuid: &str, offered: TradeBundle, requested: TradeBundle) -> Result<TradeOffer, TradeError> {
+        let now = self.now();
+
+        let original = self.take_received_trade(user_uuid, offer_uuid, now)?;
+
+        self.check_trade(user_uuid, &original.from_user, &offered, &requested, now)?;
+
+        let counter = TradeOffer::new(
+            user_uuid.to_string(),
+            original.from_user,
+            offered,
+            requested,
+            Some(original.uuid),
+            now,
+            &self.config.trading,
+        );
+        self.trade_offers.remove(offer_uuid);
+        self.trade_offers.insert(counter.uuid.clone(), counter.clone());
+
+        Ok(counter)
+    }
+
+    /// Accepts a trade offer, swapping everything in it between the two users.
+    /// Both sides are checked before anything changes hands, so either all of it moves or none of it does.
+    pub fn accept_trade(&mut self, user_uuid: &str, offer_uuid: &str) -> Result<TradeOffer, TradeError> {
+        let now = self.now();
+
+        let offer = self.take_received_trade(user_uuid, offer_uuid, now)?;
+
+        if let Err(e) = self
+            .check_bundle(&offer.from_user, &offer.offered, now)
+            .and_then(|_| self.check_bundle(&offer.to_user, &offer.requested, now))
+        {
+            self.trade_offers.remove(offer_uuid);
+            return Err(e);
+        }
+
+        self.trade_offers.remove(offer_uuid);
+
+        self.give_bundle(&offer.from_user, &offer.to_user, &offer.offered, &offer.uuid, now);
+        self.give_bundle(&offer.to_user, &offer.from_user, &offer.requested, &offer.uuid, now);
+
+        self.record_event(&offer.from_user, GameEvent::TradeCompleted, 1);
+        self.record_event(&offer.to_user, GameEvent::TradeCompleted, 1);
+
+        Ok(offer)
+    }
+
+    /// Moves one side of a trade, which must already have been checked
+    fn give_bundle(&mut self, from_user: &str, to_user: &str, bundle: &TradeBundle, trade_uuid: &str, now: u64) {
+        if bundle.coins > 0 {
+            let reason = || LedgerReason::Trade { trade: trade_uuid.to_string() };
+            let coins = bundle.coins as i64;
+
+            let _ = self.change_coins(from_user, -coins, reason(), now);
+            let _ = self.change_coins(to_user, coins, reason(), now);
+        }
+
+        for (item_id, count) in bundle.items.iter() {
+            if let Some(user) = self.users.get_mut(from_user) {
+                user.take_items(item_id, *count);
+            }
+
+            if let Some(user) = self.users.get_mut(to_user) {
+                user.add_items(item_id.clone(), *count);
+            }
+        }
+
+        for pet_uuid in bundle.pets.iter() {
+            self.move_pet(pet_uuid, Some(from_user.to_string()), Some(to_user.to_string()), TransferEvent::Traded, now);
+        }
+    }
+
+    /// Declines a trade offer, or cancels it if the user made it
+    pub fn decline_trade(&mut self, user_uuid: &str, offer_uuid: &str) -> Result<(), TradeError> {
+        match self.trade_offers.get(offer_uuid) {
+            Some(offer) if offer.involves(user_uuid) => {
+                self.trade_offers.remove(offer_uuid);
+                Ok(())
+            }
+            _ => Err(TradeError::NotFound),
+        }
+    }
+
+    /// Trade offers the user has made or been sent, leaving out expired ones
+    pub fn get_trade_offers(&self, user_uuid: &str) -> Vec<&TradeOffer> {
+        let now = self.now();
+
+        self.trade_offers
+            .values()
+            .filter(|offer| offer.involves(user_uuid) && !offer.is_expired(now))
+            .collect()
+    }
+
+    /// Drops trade offers nobody answered in time
+    pub fn expire_trade_offers(&mut self) {
+        let now = self.now();
+
+        self.trade_offers.retain(|_, offer| !offer.is_expired(now));
+    }
+
+    /*
+
+    Mini-game functions
+
+     */
+
+    /// Starts a mini-game with one of the user's pets, which counts as playing for cooldowns
+    pub fn start_game(&mut self, user_uuid: &str, pet_uuid: &str, kind: GameKind) -> Result<GameSession, GameError> {
+        let now = self.now();
+
+        let mut pet = self.get_pet_by_uuid(pet_uuid).cloned().ok_or(GameError::PetNotFound)?;
+
+        if self.get_pet_owner(pet_uuid).as_deref() != Some(user_uuid) {
+            return Err(GameError::NotOwner);
+        }
+
+        self.game_sessions.retain(|_, session| !session.is_expired(now));
+
+        if self.game_sessions.values().any(|session| session.pet == pet_uuid) {
+            return Err(GameError::AlreadyPlaying);
+        }
+
+        pet.start_game(now, &self.config)?;
+
+        let session = GameSession::new(
+            user_uuid.to_string(),
+            pet_uuid.to_string(),
+            kind,
+            now,
+            &self.config.minigames,
+            &mut rand::thread_rng(),
+        );
+
+        self.update_pet(pet);
+        self.game_sessions.insert(session.uuid.clone(), session.clone());
+
+        Ok(session)
+    }
+
+    /// Plays a move in one of the user's games. Once the game is over its score is
+    /// turned into experience and happiness for the pet.
+    pub fn play_game(&mut self, user_uuid: &str, session_uuid: &str, game_move: GameMove) -> Result<MoveOutcome, GameError> {
+        let now = self.now();
+        let config = self.config.clone();
+
+        let mut session = match self.game_sessions.get(session_uuid) {
+            Some(session) if session.user == user_uuid => session.clone(),
+            _ => return Err(GameError::SessionNotFound),
+        };
+
+        if session.is_expired(now) {
+            self.game_sessions.remove(session_uuid);
+            return Err(GameError::Expired);
+        }
+
+        // The pet may have been given away since the game started
+        if self.get_pet_owner(&session.pet).as_deref() != Some(user_uuid) {
+            self.game_sessions.remove(session_uuid);
+            return Err(GameError::NotOwner);
+        }
+
+        let (result, score) = session.play(game_move, now, &config.minigames, &mut rand::thread_rng())?;
+
+        let Some(score) = score else {
+            self.game_sessions.insert(session.uuid.clone(), session.clone());
+
+            return Ok(MoveOutcome { result, score: None, experience: 0, session });
+        };
+
+        self.game_sessions.remove(session_uuid);
+
+        let mut pet = self.get_pet_by_uuid(&session.pet).cloned().ok_or(GameError::PetNotFound)?;
+        let experience = pet.finish_game(score, now, &config);
+        self.update_pet(pet);
+        self.record_care(user_uuid, &session.pet, PetAction::Play);
+
+        Ok(MoveOutcome { result, score: Some(score), experience, session })
+    }
+
+    /// The user's games that are still going, with nothing that would give them away
+    pub fn get_game_sessions(&self, user_uuid: &str) -> Vec<serde_json::Value> {
+        let now = self.now();
+
+        self.game_sessions
+            .values()
+            .filter(|session| session.user == user_uuid && !session.is_expired(now))
+            .map(|session| session.for_user(&self.config.minigames))
+            .collect()
+    }
+
+    /// Drops games nobody finished in time
+    pub fn expire_game_sessions(&mut self) {
+        let now = self.now();
+
+        self.game_sessions.retain(|_, session| !session.is_expired(now));
+    }
+
+    /*
+
+    Contest functions
+
+     */
+
+    /// Checks the user's pet can take on the opponent: they have different owners, share a
+    /// pet yard both owners belong to, and are both alive. Returns both pets and the opponent's owner.
+    fn check_contest(&self, user_uuid: &str, pet_uuid: &str, opponent_uuid: &str, now: u64) -> Result<(Pet, Pet, String), ContestError> {
+        let mut pet = self.get_pet_by_uuid(pet_uuid).cloned().ok_or(ContestError::NotFound)?;
+        let mut opponent = self.get_pet_by_uuid(opponent_uuid).cloned().ok_or(ContestError::NotFound)?;
+
+        if self.get_pet_owner(pet_uuid).as_deref() != Some(user_uuid) {
+            return Err(ContestError::NotOwner);
+        }
+
+        let opponent_owner = self.get_pet_owner(opponent_uuid).ok_or(ContestError::NotFound)?;
+
+        if opponent_owner == user_uuid {
+            return Err(ContestError::SameOwner);
+        }
+
+        let share_pet_yard = self.pet_yards.values().any(|pet_yard| {
+            let pets = pet_yard.get_pets();
+            let members = pet_yard.get_members();
+
+            pets.iter().any(|uuid| uuid == pet_uuid)
+                && pets.iter().any(|uuid| uuid == opponent_uuid)
+                && members.iter().any(|uuid| uuid == user_uuid)
+                && members.contains(&opponent_owner)
+        });
+
+        if !share_pet_yard {
+            return Err(ContestError::NotInSameYard);
+        }
+
+        pet.update_health(now, &self.config);
+        opponent.update_health(now, &self.config);
+
+        if pet.is_dead() || opponent.is_dead() {
+            return Err(ContestError::Dead);
+        }
+
+        Ok((pet, opponent, opponent_owner))
+    }
+
+    /// Challenges another user's pet to a contest, held once its owner accepts
+    pub fn challenge_pet(&mut self, user_uuid: &str, pet_uuid: &str, opponent_uuid: &str) -> Result<ContestChallenge, ContestError> {
+        let now = self.now();
+
+        let (_, _, opponent_owner) = self.check_contest(user_uuid, pet_uuid, opponent_uuid, now)?;
+
+        let already_challenged = self.contest_challenges.values().any(|challenge| {
+            !challenge.is_expired(now)
+                && ((challenge.pet == pet_uuid && challenge.opponent == opponent_uuid)
+                    || (challenge.pet == opponent_uuid && challenge.opponent == pet_uuid))
+        });
+
+        if already_challenged {
+            return Err(ContestError::AlreadyChallenged);
+        }
+
+        let challenge = ContestChallenge::new(
+            user_uuid.to_string(),
+            pet_uuid.to_string(),
+            opponent_owner,
+            opponent_uuid.to_string(),
+            now,
+            &self.config.contests,
+        );
+        self.contest_challenges.insert(challenge.uuid.clone(), challenge.clone());
+
+        Ok(challenge)
+    }
+
+    /// Challenges the user has made or been sent, leaving out expired ones
+    pub fn get_contest_challenges(&self, user_uuid: &str) -> Vec<&ContestChallenge> {
+        let now = self.now();
+
+        self.contest_challenges
+            .values()
+            .filter(|challenge| challenge.involves(user_uuid) && !challenge.is_expired(now))
+            .collect()
+    }
+
+    /// Lets the opponent's owner accept a challenge, holding the contest straight away
+    pub fn accept_contest_challenge(&mut self, user_uuid: &str, challenge_uuid: &str) -> Result<BattleLog, ContestError> {
+        let now = self.now();
+
+        let challenge = match self.contest_challenges.get(challenge_uuid) {
+            Some(challenge) if challenge.to_user == user_uuid => challenge.clone(),
+            _ => return Err(ContestError::NotFound),
+        };
+
+        if challenge.is_expired(now) {
+            self.contest_challenges.remove(challenge_uuid);
+            return Err(ContestError::Expired);
+        }
+
+        // Things may have changed since the challenge was made
+        let (pet, opponent, opponent_owner) = self.check_contest(&challenge.from_user, &challenge.pet, &challenge.opponent, now)?;
+
+        if opponent_owner != user_uuid {
+            return Err(ContestError::NotOwner);
+        }
+
+        self.contest_challenges.remove(challenge_uuid);
+
+        Ok(self.hold_contest(&challenge, pet, opponent, now))
+    }
+
+    /// Declines a challenge, or cancels it if the user made it
+    pub fn decline_contest_challenge(&mut self, user_uuid: &str, challenge_uuid: &str) -> Result<(), ContestError> {
+        match self.contest_challenges.get(challenge_uuid) {
+            Some(challenge) if challenge.involves(user_uuid) => {
+                self.contest_challenges.remove(challenge_uuid);
+                Ok(())
+            }
+            _ => Err(ContestError::NotFound),
+        }
+    }
+
+    /// Drops challenges nobody answered in time
+    pub fn expire_contest_challenges(&mut self) {
+        let now = self.now();
+
+        self.contest_challenges.retain(|_, challenge| !challenge.is_expired(now));
+    }
+
+    /// Plays out a contest, updates both pets' records and keeps the battle log
+    fn hold_contest(&mut self, challenge: &ContestChallenge, mut pet: Pet, mut opponent: Pet, now: u64) -> BattleLog {
+        let config = self.config.clone();
+
+        let contestant = |pet: &Pet, owner: &str| {
+            Contestant::new(
+                pet.get_uuid(),
+                owner.to_string(),
+                pet.get_name(),
+                pet.get_level(),
+                &config.species.traits(&pet.get_species()).contest_traits,
+                &config.contests,
+            )
+        };
+        let challenger = contestant(&pet, &challenge.from_user);
+        let defender = contestant(&opponent, &challenge.to_user);
+
+        // Kept to 53 bits so JavaScript clients can read it back exactly
+        let seed = rand::random::<u64>() >> 11;
+        let (turns, result) = contests::resolve(seed, &challenger, &defender, &config.contests);
+
+        let experience = |won: bool| {
+            if won { config.contests.winner_experience } else { config.contests.loser_experience }
+        };
+        pet.record_contest(result, true, experience(result == ContestResult::ChallengerWon), now, &config);
+        opponent.record_contest(result, false, experience(result == ContestResult::OpponentWon), now, &config);
+
+        let levels = [
+            (challenge.from_user.clone(), pet.get_level()),
+            (challenge.to_user.clone(), opponent.get_level()),
+        ];
+        self.update_pet(pet);
+        self.update_pet(opponent);
+        self.record_yard_activity(&challenge.pet);
+
+        let log = BattleLog {
+            uuid: Uuid::new_v4().to_string(),
+            timestamp: now,
+            seed,
+            challenger,
+            opponent: defender,
+            turns,
+            result,
+        };
+
+        self.battle_logs.push(log.clone());
+
+        let max_logs = config.contests.max_battle_logs;
+        if self.battle_logs.len() > max_logs {
+            let excess = self.battle_logs.len() - max_logs;
+            self.battle_logs.drain(..excess);
+        }
+
+        if let Some(winner) = log.winner() {
+            self.record_event(winner, GameEvent::ContestWon, 1);
+        }
+
+        for (user_uuid, level) in levels {
+            self.record_event(&user_uuid, GameEvent::LevelReached, level as u64);
+        }
+
+        log
+    }
+
+    /// Contests the user's pets have been in, most recent first
+    pub fn get_battle_logs(&self, user_uuid: &str) -> Vec<&BattleLog> {
+        self.battle_logs.iter().rev().filter(|log| log.involves(user_uuid)).collect()
+    }
+
+    pub fn get_battle_log(&self, battle_uuid: &str) -> Option<&BattleLog> {
+        self.battle_logs.iter().find(|log| log.uuid == battle_uuid)
+    }
+
+    /*
+    
+    Pet yard functions
+    
+     */
+
+    pub fn get_pet_yard_by_uuid(&self, uuid: &str) -> Option<&PetYard> {
+        self.pet_yards.get(uuid)
+    }
+
+    pub fn update_pet_yard(&mut self, pet_yard: PetYard) {
+        self.pet_yards.insert(pet_yard.uuid.clone(), pet_yard);
+    }
+
+    pub fn delete_pet_yard(&mut self, uuid: &str) {
+        // First, remove the pet yard from all users
+        for user in self.users.values_mut() {
+            user.remove_owned_pet_yard(uuid.to_string());
+            user.remove_joined_pet_yard(uuid.to_string());
+        }
+
+        // Next, remove all pets from the pet yard
+        if let Some(pet_yard) = self.pet_yards.get(uuid) {
+            let pet_uuids: Vec<String> = pet_yard.pets.to_vec();
+
+            // For each pet in the yard, just remove the pet yard
+            for pet_uuid in pet_uuids {
+                let mut pet = self.get_pet_by_uuid(&pet_uuid).unwrap().to_owned();
+
+                pet.remove_pet_yard();
+
+                // Update the pet
+                self.update_pet(pet.clone());
+            }
+        }
+
+        self.leaderboards.remove(Board::MostActiveYard, uuid);
+
+        // Finally, delete the pet yard
+        self.pet_yards.remove(uuid);
+    }
+
+    /// Counts something done with a pet towards the activity of the pet yard it is in
+    fn record_yard_activity(&mut self, pet_uuid: &str) {
+        for pet_yard in self.pet_yards.values_mut() {
+            if pet_yard.pets.iter().any(|uuid| uuid == pet_uuid) {
+                pet_yard.activity += 1;
+                self.leaderboards.set(Board::MostActiveYard, &pet_yard.uuid, pet_yard.activity);
+            }
+        }
+    }
+
+    /*
+
+    Vacation functions
+
+     */
+
+    /// Puts the user's pets on hold for the given number of days
+    pub fn start_vacation(&mut self, user_uuid: &str, days: u64) -> Result<Vacation, VacationError> {
+        let now = self.now();
+        let config = self.config.clone();
+
+        let user = self.users.get_mut(user_uuid).ok_or(VacationError::UserNotFound)?;
+
+        if days == 0 || days > config.vacations.max_days {
+            return Err(VacationError::InvalidLength);
+        }
+
+        if user.vacation.is_some() {
+            return Err(VacationError::AlreadyAway);
+        }
+
+        let available_at = vacations::next_vacation_at(user.vacation_ended, &config.vacations);
+        if now < available_at {
+            return Err(VacationError::TooSoon { available_at });
+        }
+
+        let vacation = Vacation::new(now, days);
+        user.vacation = Some(vacation.clone());
+
+        tracing::info!("User {} went on vacation for {} days", user_uuid, days);
+
+        Ok(vacation)
+    }
+
+    /// Brings the user back from vacation early
+    pub fn end_vacation(&mut self, user_uuid: &str) -> Result<(), VacationError> {
+        let now = self.now();
+
+        let user = self.users.get(user_uuid).ok_or(VacationError::UserNotFound)?;
+        if user.vacation.is_none() {
+            return Err(VacationError::NotAway);
+        }
+
+        self.pause_vacationing_pets();
+
+        // The vacation may have just run out by itself
+        if let Some(user) = self.users.get_mut(user_uuid) {
+            if let Some(vacation) = user.vacation.take() {
+                user.vacation_ended = Some(now.min(vacation.ends));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Holds the pets of users on vacation where they were, and ends vacations that have run out
+    pub fn pause_vacationing_pets(&mut self) {
+        let now = self.now();
+
+        for user in self.users.values_mut() {
+            let Some(vacation) = user.vacation.as_mut() else {
+                continue;
+            };
+
+            if let Some((from, to)) = vacation.pause_until(now) {
+                for pet_uuid in user.pets.iter() {
+                    if let Some(pet) = self.pets.get_mut(pet_uuid) {
+                        pet.pause_clocks(from, to);
+                    }
+                }
+            }
+
+            if vacation.is_over(now) {
+                tracing::info!("User {}'s vacation is over", user.uuid);
+
+                user.vacation_ended = Some(vacation.ends);
+                user.vacation = None;
+            }
+        }
+    }
+
+    /// Lets another user feed and pet some of the user's pets for the given number of days
+    pub fn start_pet_sitting(&mut self, user_uuid: &str, sitter: &str, pets: Vec<String>, days: u64) -> Result<PetSitting, VacationError> {
+        let now = self.now();
+        let config = self.config.clone();
+
+        if self.get_user_by_uuid(user_uuid).is_none() || self.get_user_by_uuid(sitter).is_none() {
+            return Err(VacationError::UserNotFound);
+        }
+
+        if user_uuid == sitter {
+            return Err(VacationError::SameUser);
+        }
+
+        if days == 0 || days > config.vacations.max_sitting_days {
+            return Err(VacationError::InvalidLength);
+        }
+
+        let mut pets = pets;
+        pets.sort();
+        pets.dedup();
 
-    pub fn delete_user(&mut self, user: User) {
-        // First, delete the user's pets
-        for pet_uuid in user.pets.iter() {
-            self.delete_pet(pet_uuid);
+        if pets.is_empty() {
+            return Err(VacationError::NoPets);
         }
 
-        // Next, delete the user's pet yards
-        for pet_yard_uuid in user.owned_pet_yards.iter() {
-            self.delete_pet_yard(pet_yard_uuid);
+        for pet_uuid in pets.iter() {
+            if self.get_pet_by_uuid(pet_uuid).is_none() {
+                return Err(VacationError::PetNotFound);
+            }
+
+            if self.get_pet_owner(pet_uuid).as_deref() != Some(user_uuid) {
+                return Err(VacationError::NotOwner);
+            }
         }
 
-        // Finally, delete the user
-        self.users.remove(&user.uuid);
-    }
+        let sittings = self
+            .pet_sittings
+            .values()
+            .filter(|sitting| sitting.owner == user_uuid && !sitting.is_expired(now))
+            .count();
+        if sittings >= config.vacations.max_sittings {
+            return Err(VacationError::TooManySittings);
+        }
 
-    /*
-    
-    Pet functions
-    
-     */
+        let sitting = PetSitting::new(user_uuid.to_string(), sitter.to_string(), pets, now, days);
+        self.pet_sittings.insert(sitting.uuid.clone(), sitting.clone());
 
-    pub fn get_pet_by_uuid(&self, uuid: &str) -> Option<&Pet> {
-        self.pets.get(uuid)
+        Ok(sitting)
     }
 
-    pub fn update_pet(&mut self, pet: Pet) {
-        self.pets.insert(pet.uuid.clone(), pet);
+    /// Pet-sittings the user has set up or is doing
+    pub fn get_pet_sittings(&self, user_uuid: &str) -> Vec<&PetSitting> {
+        self.pet_sittings.values().filter(|sitting| sitting.involves(user_uuid)).collect()
     }
 
-    pub fn delete_pet(&mut self, uuid: &str) {
-        // Remove the pet from any pet yards it is in
-        for pet_yard in self.pet_yards.values_mut() {
-            pet_yard.remove_pet(uuid.to_string());
+    /// Ends a pet-sitting, which either the owner or the sitter can do
+    pub fn end_pet_sitting(&mut self, user_uuid: &str, sitting_uuid: &str) -> Result<(), VacationError> {
+        match self.pet_sittings.get(sitting_uuid) {
+            Some(sitting) if sitting.involves(user_uuid) => {
+                self.pet_sittings.remove(sitting_uuid);
+                Ok(())
+            }
+            _ => Err(VacationError::SittingNotFound),
         }
+    }
 
-        // Remove the pet from any users who own it
-        for user in self.users.values_mut() {
-            user.remove_pet(uuid.to_string());
-        }
+    /// Drops pet-sittings that have run out
+    pub fn expire_pet_sittings(&mut self) {
+        let now = self.now();
 
-        // Finally, delete the pet
-        self.pets.remove(uuid);
+        self.pet_sittings.retain(|_, sitting| !sitting.is_expired(now));
     }
 
-    pub fn kill_unloved_pets(&mut self) {
-        for pet in self.pets.clone().values() {
-            if pet.should_die() {
-                tracing::info!("Pet {} has died", pet.get_name());
-                self.delete_pet(&pet.uuid);
-            }
+    /// Takes a pet out of any pet-sittings, dropping the ones left with no pets
+    fn drop_pet_from_sittings(&mut self, pet_uuid: &str) {
+        for sitting in self.pet_sittings.values_mut() {
+            sitting.pets.retain(|pet| pet != pet_uuid);
         }
+
+        self.pet_sittings.retain(|_, sitting| !sitting.pets.is_empty());
     }
 
+    /// Whether the user can feed and pet the pet: its owner, a yard-mate or a sitter
+    pub fn can_care_for(&self, user_uuid: &str, pet_uuid: &str) -> bool {
+        let now = self.now();
+
+        self.get_pet_owner(pet_uuid).as_deref() == Some(user_uuid)
+            || self.is_yard_mate(user_uuid, pet_uuid)
+            || self.pet_sittings.values().any(|sitting| sitting.covers(user_uuid, pet_uuid, now))
+    }
 
     /*
-    
-    Pet yard functions
-    
+
+    Leaderboard functions
+
      */
 
-    pub fn get_pet_yard_by_uuid(&self, uuid: &str) -> Option<&PetYard> {
-        self.pet_yards.get(uuid)
+    /// Puts a living pet on the pet leaderboards, or takes a dead one off them
+    fn rank_pet(leaderboards: &mut Leaderboards, pet: &Pet) {
+        if pet.is_dead() {
+            leaderboards.remove_pet(&pet.uuid);
+        } else {
+            leaderboards.set_pet(
+                &pet.uuid,
+                &pet.species,
+                leaderboards::level_score(pet.level, pet.experience),
+                leaderboards::age_score(pet.born),
+            );
+        }
     }
 
-    pub fn update_pet_yard(&mut self, pet_yard: PetYard) {
-        self.pet_yards.insert(pet_yard.uuid.clone(), pet_yard);
-    }
+    /// Ranks every pet, user and pet yard from scratch. Only needed when the state is loaded,
+    /// as the rankings are kept up to date after that.
+    pub fn rebuild_leaderboards(&mut self) {
+        let mut rankings = Leaderboards::default();
 
-    pub fn delete_pet_yard(&mut self, uuid: &str) {
-        // First, remove the pet yard from all users
-        for user in self.users.values_mut() {
-            user.remove_owned_pet_yard(uuid.to_string());
-            user.remove_joined_pet_yard(uuid.to_string());
+        for pet in self.pets.values() {
+            Self::rank_pet(&mut rankings, pet);
         }
 
-        // Next, remove all pets from the pet yard
-        if let Some(pet_yard) = self.pet_yards.get(uuid) {
-            let pet_uuids: Vec<String> = pet_yard.pets.iter().cloned().collect();
+        for user in self.users.values().filter(|user| !user.achievements.is_empty()) {
+            rankings.set(Board::MostAchievements, &user.uuid, user.achievements.len() as u64);
+        }
 
-            // For each pet in the yard, just remove the pet yard
-            for pet_uuid in pet_uuids {
-                let mut pet = self.get_pet_by_uuid(&pet_uuid).unwrap().to_owned();
+        for pet_yard in self.pet_yards.values().filter(|pet_yard| pet_yard.activity > 0) {
+            rankings.set(Board::MostActiveYard, &pet_yard.uuid, pet_yard.activity);
+        }
 
-                pet.remove_pet_yard();
+        self.leaderboards = rankings;
+    }
 
-                // Update the pet
-                self.update_pet(pet.clone());
+    /// A page of a leaderboard, overall or for one species of pet
+    pub fn get_leaderboard(
+        &self,
+        board: Board,
+        species: Option<&str>,
+        offset: usize,
+        limit: usize,
+    ) -> Result<serde_json::Value, LeaderboardError> {
+        let now = self.now();
+
+        if let Some(species) = species {
+            if !board.is_for_pets() {
+                return Err(LeaderboardError::NotBySpecies);
+            }
+
+            if self.config.species.get(species).is_none() {
+                return Err(LeaderboardError::UnknownSpecies);
             }
         }
 
-        // Finally, delete the pet yard
-        self.pet_yards.remove(uuid);
+        // Boards nothing has been ranked on yet are just empty
+        let empty = Ranking::default();
+        let ranking = self.leaderboards.get(board, species).unwrap_or(&empty);
+
+        let entries: Vec<serde_json::Value> = ranking
+            .page(offset, limit)
+            .into_iter()
+            .filter_map(|(rank, uuid, _)| match board {
+                Board::HighestLevel | Board::LongestLiving => self.pets.get(uuid).map(|pet| {
+                    serde_json::json!({
+                        "rank": rank,
+                        "uuid": pet.uuid,
+                        "name": pet.name,
+                        "image": pet.image,
+                        "species": pet.species,
+                        "level": pet.level,
+                        "experience": pet.experience,
+                        "age_days": pet.age_days(now),
+                    })
+                }),
+                Board::MostAchievements => self.users.get(uuid).map(|user| {
+                    serde_json::json!({
+                        "rank": rank,
+                        "uuid": user.uuid,
+                        "username": user.username,
+                        "achievements": user.achievements.len(),
+                    })
+                }),
+                Board::MostActiveYard => self.pet_yards.get(uuid).map(|pet_yard| {
+                    serde_json::json!({
+                        "rank": rank,
+                        "uuid": pet_yard.uuid,
+                        "name": pet_yard.name,
+                        "image": pet_yard.image,
+                        "activity": pet_yard.activity,
+                        "num_members": pet_yard.members.len(),
+                        "num_pets": pet_yard.pets.len(),
+                    })
+                }),
+            })
+            .collect();
+
+        Ok(serde_json::json!({
+            "board": board,
+            "species": species,
+            "total": ranking.len(),
+            "offset": offset,
+            "entries": entries,
+        }))
     }
 }
 
@@ -164,17 +1963,43 @@ pub struct User {
     joined_pet_yards: Vec<String>,
     // Chat logs with other users
     chat_logs: HashMap<String, Vec<DirectMessage>>,
+    // UUIDs of the user's dead pets
+    #[serde(default)]
+    graveyard: Vec<String>,
+    // How many of each item the user has, by item id
+    #[serde(default)]
+    inventory: BTreeMap<String, u32>,
+    #[serde(default)]
+    coins: u64,
+    // Rewards already claimed, so they aren't given twice
+    #[serde(default)]
+    rewards: RewardTracker,
+    // Running totals of what the user has done, for achievements
+    #[serde(default)]
+    achievement_progress: EventProgress,
+    // When each achievement was unlocked, by achievement id
+    #[serde(default)]
+    achievements: BTreeMap<String, u64>,
+    // Today's quests and the user's streak
+    #[serde(default)]
+    quests: QuestLog,
+    // The vacation the user is on, if any
+    #[serde(default)]
+    vacation: Option<Vacation>,
+    // When the user's last vacation ended
+    #[serde(default)]
+    vacation_ended: Option<u64>,
 }
 
 impl User {
-    pub fn new(username: String, email: String, password: String) -> Self {
+    pub fn new(username: String, email: String, password: String, now: u64) -> Self {
         let salt = Uuid::new_v4().to_string();
         let salted_password = format!("{}{}", password, salt);
         let hashed_password = hash(&salted_password);
 
         Self {
             uuid: Uuid::new_v4().to_string(),
-            join_timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            join_timestamp: now,
             username,
             email,
             h_s_password: hashed_password,
@@ -183,6 +2008,15 @@ impl User {
             owned_pet_yards: vec![],
             joined_pet_yards: vec![],
             chat_logs: HashMap::new(),
+            graveyard: vec![],
+            inventory: BTreeMap::new(),
+            coins: 0,
+            rewards: RewardTracker::default(),
+            achievement_progress: EventProgress::new(),
+            achievements: BTreeMap::new(),
+            quests: QuestLog::default(),
+            vacation: None,
+            vacation_ended: None,
         }
     }
 
@@ -224,6 +2058,10 @@ impl User {
             "owned_pet_yards": self.owned_pet_yards,
             "joined_pet_yards": self.joined_pet_yards,
             "chat_logs": self.chat_logs,
+            "graveyard": self.graveyard,
+            "inventory": self.inventory,
+            "coins": self.coins,
+            "vacation": self.vacation,
         }).to_string()
     }
 
@@ -237,6 +2075,10 @@ impl User {
             "owned_pet_yards": self.owned_pet_yards,
             "joined_pet_yards": self.joined_pet_yards,
             "chat_logs": self.chat_logs,
+            "graveyard": self.graveyard,
+            "inventory": self.inventory,
+            "coins": self.coins,
+            "vacation": self.vacation,
             "token": token,
         }).to_string()
     }
@@ -247,6 +2089,8 @@ impl User {
             "username": self.username,
             "pets": self.pets,
             "owned_pet_yards": self.owned_pet_yards,
+            "graveyard": self.graveyard,
+            "badges": self.achievements,
         }).to_string()
     }
 
@@ -280,6 +2124,76 @@ impl User {
         self.joined_pet_yards.retain(|uuid| uuid != &pet_yard_uuid);
     }
 
+    pub fn add_grave(&mut self, pet_uuid: String) {
+        if !self.graveyard.contains(&pet_uuid) {
+            self.graveyard.push(pet_uuid);
+        }
+    }
+
+    pub fn remove_grave(&mut self, pet_uuid: String) {
+        self.graveyard.retain(|uuid| uuid != &pet_uuid);
+    }
+
+    pub fn add_items(&mut self, item_id: String, count: u32) {
+        *self.inventory.entry(item_id).or_insert(0) += count;
+    }
+
+    /// Takes one of the item out of the inventory, returning false if the user has none
+    pub fn take_item(&mut self, item_id: &str) -> bool {
+        self.take_items(item_id, 1)
+    }
+
+    /// Takes some of the item out of the inventory, returning false if the user doesn't have enough
+    pub fn take_items(&mut self, item_id: &str, count: u32) -> bool {
+        match self.inventory.get_mut(item_id) {
+            Some(held) if *held >= count => {
+                *held -= count;
+
+                if *held == 0 {
+                    self.inventory.remove(item_id);
+                }
+
+                true
+            }
+            _ => count == 0,
+        }
+    }
+
+    pub fn item_count(&self, item_id: &str) -> u32 {
+        self.inventory.get(item_id).copied().unwrap_or(0)
+    }
+
+    pub fn has_item(&self, item_id: &str) -> bool {
+        self.item_count(item_id) > 0
+    }
+
+    pub fn get_achievement_progress(&self) -> &EventProgress {
+        &self.achievement_progress
+    }
+
+    pub fn get_achievements(&self) -> &BTreeMap<String, u64> {
+        &self.achievements
+    }
+
+    /// The user's quests, moved on to today's if they are from an earlier day
+    pub fn get_quests(&self, now: u64, config: &QuestConfig) -> QuestLog {
+        let mut quests = self.quests.clone();
+        quests.refresh(&self.uuid, now, config);
+        quests
+    }
+
+    pub fn get_coins(&self) -> u64 {
+        self.coins
+    }
+
+    pub fn get_inventory(&self) -> BTreeMap<String, u32> {
+        self.inventory.clone()
+    }
+
+    pub fn get_graveyard(&self) -> Vec<String> {
+        self.graveyard.clone()
+    }
+
     pub fn get_dms(&self, recipient_uuid: &str) -> String {
         serde_json::json!(self.chat_logs.get(recipient_uuid).unwrap_or(&vec![])).to_string()
     }
@@ -296,12 +2210,12 @@ pub struct UserToken {
 }
 
 impl UserToken {
-    pub fn new(uuid: String, token: String) -> Self {
+    pub fn new(uuid: String, token: String, now: u64, lifetime_ms: u64) -> Self {
         Self {
             uuid,
             token,
-            creation_timestamp: chrono::Utc::now().timestamp_millis() as u64,
-            expiration_timestamp: chrono::Utc::now().timestamp_millis() as u64 + 1000 * 60 * 60 * 24, // 24 hours
+            creation_timestamp: now,
+            expiration_timestamp: now + lifetime_ms,
         }
     }
 
@@ -313,12 +2227,12 @@ impl UserToken {
         self.token.clone()
     }
 
-    pub fn is_valid(&self) -> bool {
-        self.expiration_timestamp > chrono::Utc::now().timestamp_millis() as u64
+    pub fn is_valid(&self, now: u64) -> bool {
+        self.expiration_timestamp > now
     }
 
-    pub fn refresh(&mut self) {
-        self.expiration_timestamp = chrono::Utc::now().timestamp_millis() as u64 + 1000 * 60 * 60 * 24; // 24 hours
+    pub fn refresh(&mut self, now: u64, lifetime_ms: u64) {
+        self.expiration_timestamp = now + lifetime_ms;
     }
 }
 
@@ -331,13 +2245,14 @@ pub struct DirectMessage {
     timestamp: u64,
 }
 
+#[allow(dead_code)]
 impl DirectMessage {
-    pub fn new(sender: String, receiver: String, encrypted_msg: String) -> Self {
+    pub fn new(sender: String, receiver: String, encrypted_msg: String, now: u64) -> Self {
         Self {
             sender,
             receiver,
             encrypted_msg,
-            timestamp: chrono::Utc::now().timestamp_millis() as u64,
+            timestamp: now,
         }
     }
 
@@ -347,22 +2262,103 @@ impl DirectMessage {
     }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq, Clone)]
+#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Clone)]
 pub struct Pet {
     uuid: String,
     name: String,
     image: u64,
     species: String,
-    level: u128,
-    experience: u8,
+    level: u32,
+    // Experience towards the next level
+    experience: u64,
     last_fed: u64,
     last_pet: u64,
     // UUID of the pet yard the pet is in, or None if the pet is not in a pet yard
     pet_yard: Option<String>,
+    // Stats as of stats_updated, they decay from there
+    #[serde(default)]
+    stats: PetStats,
+    #[serde(default)]
+    stats_updated: u64,
+    #[serde(default)]
+    health: HealthState,
+    // When the pet entered its current health state
+    #[serde(default)]
+    health_since: u64,
+    // Most recent first
+    #[serde(default)]
+    events: Vec<PetEvent>,
+    // When each action was last done, for cooldowns
+    #[serde(default)]
+    actions: ActionLog,
+    #[serde(default = "growth::legacy_stage")]
+    stage: GrowthStage,
+    // The species form the pet has grown into, if any
+    #[serde(default)]
+    form: Option<String>,
+    // How well the pet has been looked after, from 0 to 100
+    #[serde(default = "default_care")]
+    care: f64,
+    // Oldest first
+    #[serde(default)]
+    evolutions: Vec<EvolutionRecord>,
+    #[serde(default)]
+    genes: Genes,
+    // UUIDs of the pet's parents, empty if it wasn't bred
+    #[serde(default)]
+    parents: Vec<String>,
+    #[serde(default)]
+    last_bred: u64,
+    // Item id of the cosmetic the pet is wearing
+    #[serde(default)]
+    accessory: Option<String>,
+    // Pets saved before this was tracked load as 0, and count as born when they were loaded
+    #[serde(default)]
+    born: u64,
+    // Wins, losses and draws in contests
+    #[serde(default)]
+    contests: ContestRecord,
+}
+
+fn default_care() -> f64 {
+    crate::needs::MAX_STAT
+}
+
+/// What happened when a user asked to breed two pets
+#[derive(Debug, Clone)]
+pub enum BreedOutcome {
+    // Both pets were theirs, so the egg was laid straight away
+    Egg(Box<Pet>),
+    // The partner's owner has to agree first
+    Requested(BreedingRequest),
+}
+
+/// Why an action on a pet could not be done
+#[derive(Debug, Clone, PartialEq)]
+pub enum PetActionError {
+    Dead,
+    NotSick,
+    // The action can't be done again until the given time
+    Cooldown { available_at: u64 },
+    // The pet's species won't eat the food
+    Refused,
+}
+
+impl std::fmt::Display for PetActionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PetActionError::Dead => write!(f, "Pet has died"),
+            PetActionError::NotSick => write!(f, "Pet is not sick"),
+            PetActionError::Cooldown { available_at } => {
+                write!(f, "Action is on cooldown until {}", available_at)
+            }
+            PetActionError::Refused => write!(f, "Pet won't eat that"),
+        }
+    }
 }
 
 impl Pet {
-    pub fn new(name: String, species: String, image: u64, pet_yard: Option<String>) -> Self {
+    pub fn new(name: String, species: String, image: u64, pet_yard: Option<String>, now: u64) -> Self {
         Self {
             uuid: Uuid::new_v4().to_string(),
             name,
@@ -371,8 +2367,24 @@ impl Pet {
             level: 1,
             experience: 0,
             pet_yard,
-            last_fed: chrono::Utc::now().timestamp_millis() as u64,
-            last_pet: chrono::Utc::now().timestamp_millis() as u64,
+            last_fed: now,
+            last_pet: now,
+            stats: PetStats::default(),
+            stats_updated: now,
+            health: HealthState::Healthy,
+            health_since: now,
+            events: vec![],
+            actions: ActionLog::new(),
+            stage: GrowthStage::Egg,
+            form: None,
+            care: default_care(),
+            evolutions: vec![],
+            genes: Genes::default(),
+            parents: vec![],
+            last_bred: 0,
+            accessory: None,
+            contests: ContestRecord::default(),
+            born: now,
         }
     }
 
@@ -404,63 +2416,476 @@ impl Pet {
         self.species.clone()
     }
 
-    pub fn set_species(&mut self, species: String) {
+    /// Changes the pet's species, which its forms and evolutions only make sense for
+    /// until it has grown into one
+    pub fn set_species(&mut self, species: String) -> Result<(), SpeciesError> {
+        if species != self.species && self.form.is_some() {
+            return Err(SpeciesError::AlreadyGrown);
+        }
+
         self.species = species;
+        Ok(())
     }
 
-    pub fn get_level(&self) -> u128 {
+    pub fn get_level(&self) -> u32 {
         self.level
     }
 
-    pub fn get_experience(&self) -> u8 {
+    pub fn get_experience(&self) -> u64 {
         self.experience
     }
 
-    pub fn feed(&mut self) {
-        self.add_experience(1);
-        // Update last fed time to plus 1 day, or now if it's been more than a day
-        let now = chrono::Utc::now().timestamp_millis() as u64;
-        if self.last_fed + 1000 * 60 * 60 * 24 > now {
-            self.last_fed = now;
+    /// Works out the pet's stats at the given time from the last saved snapshot.
+    /// A dead pet's stats stay as they were when it died.
+    pub fn current_stats(&self, now: u64, config: &Config) -> PetStats {
+        let now = if self.is_dead() { now.min(self.health_since) } else { now };
+
+        self.stats_snapshot(config).at(now, &config.needs)
+    }
+
+    /// The stats as last saved, and how quickly this pet's stats decay from there
+    fn stats_snapshot(&self, config: &Config) -> StatsSnapshot {
+        let rates = config
+            .species
+            .traits(&self.species)
+            .decay_rates_for(self.form.as_deref())
+            .scaled(&self.genes.decay_rates);
+
+        StatsSnapshot {
+            stats: self.stats,
+            taken: self.stats_updated_at(),
+            rates,
+        }
+    }
+
+    fn stats_updated_at(&self) -> u64 {
+        // Pets saved before stats existed start decaying from when they were last cared for
+        if self.stats_updated == 0 {
+            self.last_fed.min(self.last_pet)
         } else {
-            self.last_fed += 1000 * 60 * 60 * 24;
+            self.stats_updated
         }
     }
 
-    pub fn pet(&mut self) {
-        self.add_experience(5);
+    /// Saves the current stats as the new snapshot, so changes can be made on top of them
+    fn settle_stats(&mut self, now: u64, config: &Config) {
+        self.stats = self.current_stats(now, config);
+        self.stats_updated = now;
+    }
 
-        let now = chrono::Utc::now().timestamp_millis() as u64;
-        if self.last_pet + 1000 * 60 * 60 * 24 > now {
-            self.last_pet = now;
-        } else {
-            self.last_pet += 1000 * 60 * 60 * 24;
-        }        
+    /// Catches the pet's stats and health up to now. Dead pets can't be cared for,
+    /// and actions on cooldown can't be done yet.
+    fn prepare_for_action(&mut self, action: PetAction, now: u64, config: &Config) -> Result<(), PetActionError> {
+        self.update_health(now, config);
+
+        if self.is_dead() {
+            return Err(PetActionError::Dead);
+        }
+
+        if let Some(available_at) = cooldowns::available_at(self.actions.get(&action), config.cooldowns.limit(action), now) {
+            return Err(PetActionError::Cooldown { available_at });
+        }
+
+        self.settle_stats(now, config);
+        self.care = growth::update_care(self.care, &self.stats);
+
+        Ok(())
+    }
+
+    pub fn feed(&mut self, now: u64, config: &Config) -> Result<(), PetActionError> {
+        self.prepare_for_action(PetAction::Feed, now, config)?;
+        restore(&mut self.stats.hunger, config.needs.feed_restores);
+        self.last_fed = now;
+
+        self.add_experience(config.leveling.action_experience.feed, now, config);
+        cooldowns::record_use(&mut self.actions, PetAction::Feed, now);
+        self.update_health(now, config);
+
+        Ok(())
+    }
+
+    pub fn pet(&mut self, now: u64, config: &Config) -> Result<(), PetActionError> {
+        self.prepare_for_action(PetAction::Pet, now, config)?;
+        restore(&mut self.stats.happiness, config.needs.pet_restores);
+        self.last_pet = now;
+
+        self.add_experience(config.leveling.action_experience.pet, now, config);
+        cooldowns::record_use(&mut self.actions, PetAction::Pet, now);
+        self.update_health(now, config);
+
+        Ok(())
+    }
+
+    /// Cures a sick pet, or stabilises a critical one
+    pub fn give_medicine(&mut self, now: u64, config: &Config) -> Result<(), PetActionError> {
+        self.prepare_for_action(PetAction::Medicine, now, config)?;
+
+        let next = health::after_medicine(self.health, &self.stats, &config.health)
+            .ok_or(PetActionError::NotSick)?;
+        self.set_health(next, now, config);
+        self.recover(config);
+
+        self.add_experience(config.leveling.action_experience.medicine, now, config);
+        cooldowns::record_use(&mut self.actions, PetAction::Medicine, now);
+
+        Ok(())
+    }
+
+    /// Gives back some of the energy and hygiene that made the pet sick
+    fn recover(&mut self, config: &Config) {
+        restore(&mut self.stats.energy, config.health.medicine_restores);
+        restore(&mut self.stats.hygiene, config.health.medicine_restores);
+    }
+
+    /// Uses an item on the pet. Food, toys and medicine count as feeding, petting and
+    /// giving medicine, and cosmetics are worn, returning the one taken off.
+    pub fn use_item(&mut self, item_id: &str, item: &Item, now: u64, config: &Config) -> Result<Option<String>, PetActionError> {
+        let Some(action) = item.kind.action() else {
+            return Ok(self.accessory.replace(item_id.to_string()));
+        };
+
+        let mut multiplier = 1.0;
+        if item.kind == ItemKind::Food {
+            match config.species.traits(&self.species).food_preference(item_id) {
+                Preference::Refused => return Err(PetActionError::Refused),
+                Preference::Favorite => multiplier = config.inventory.favorite_multiplier,
+                Preference::Neutral => {}
+            }
+        }
+
+        self.prepare_for_action(action, now, config)?;
+
+        if item.kind == ItemKind::Medicine {
+            let next = health::after_medicine(self.health, &self.stats, &config.health)
+                .ok_or(PetActionError::NotSick)?;
+            self.set_health(next, now, config);
+            self.recover(config);
+        }
+
+        item.restores.apply(&mut self.stats, multiplier);
+
+        match action {
+            PetAction::Feed => self.last_fed = now,
+            PetAction::Pet => self.last_pet = now,
+            PetAction::Medicine | PetAction::Rest | PetAction::Clean | PetAction::Play => {}
+        }
+
+        let experience = config.leveling.action_experience.for_action(action) + item.experience;
+        self.add_experience(experience, now, config);
+        cooldowns::record_use(&mut self.actions, action, now);
+        self.update_health(now, config);
+
+        Ok(None)
+    }
+
+    /// Restores energy, and stabilises a critical pet
+    pub fn rest(&mut self, now: u64, config: &Config) -> Result<(), PetActionError> {
+        self.prepare_for_action(PetAction::Rest, now, config)?;
+        restore(&mut self.stats.energy, config.health.rest_restores);
+
+        let next = health::after_rest(self.health);
+        if next != self.health {
+            self.set_health(next, now, config);
+        }
+
+        self.add_experience(config.leveling.action_experience.rest, now, config);
+        cooldowns::record_use(&mut self.actions, PetAction::Rest, now);
+
+        Ok(())
+    }
+
+    /// Restores hygiene
+    pub fn clean(&mut self, now: u64, config: &Config) -> Result<(), PetActionError> {
+        self.prepare_for_action(PetAction::Clean, now, config)?;
+        restore(&mut self.stats.hygiene, config.needs.clean_restores);
+
+        self.add_experience(config.leveling.action_experience.clean, now, config);
+        cooldowns::record_use(&mut self.actions, PetAction::Clean, now);
+        self.update_health(now, config);
+
+        Ok(())
+    }
+
+    /// Starts a mini-game, which uses up the play action and some energy whatever the score
+    pub fn start_game(&mut self, now: u64, config: &Config) -> Result<(), PetActionError> {
+        self.prepare_for_action(PetAction::Play, now, config)?;
+        restore(&mut self.stats.energy, -config.minigames.energy_cost);
+
+        cooldowns::record_use(&mut self.actions, PetAction::Play, now);
+        self.update_health(now, config);
+
+        Ok(())
+    }
+
+    /// Rewards the pet for a finished mini-game, scaled by the score, returning the experience given
+    pub fn finish_game(&mut self, score: u32, now: u64, config: &Config) -> u64 {
+        self.update_health(now, config);
+
+        if self.is_dead() {
+            return 0;
+        }
+
+        let share = score.min(MAX_SCORE) as f64 / MAX_SCORE as f64;
+
+        self.settle_stats(now, config);
+        restore(&mut self.stats.happiness, config.minigames.happiness_reward * share);
+
+        let experience = (config.leveling.action_experience.play as f64 * share).round() as u64;
+        self.add_experience(experience, now, config);
+        self.update_health(now, config);
+
+        experience
+    }
+
+    /// Adds a contest to the pet's record, along with the experience it earned
+    pub fn record_contest(&mut self, result: ContestResult, challenger: bool, experience: u64, now: u64, config: &Config) {
+        self.contests.add(result, challenger);
+        self.add_experience(experience, now, config);
+    }
+
+    pub fn get_contest_record(&self) -> ContestRecord {
+        self.contests
+    }
+
+    /// Whole days since the pet was born
+    pub fn age_days(&self, now: u64) -> u64 {
+        now.saturating_sub(self.born) / (1000 * 60 * 60 * 24)
     }
 
-    pub fn add_experience(&mut self, experience: u8) {
-        self.experience += experience;
-        if self.experience >= EXP_PER_LEVEL {
-            self.level += 1;
-            self.experience = 0;
+    pub fn get_health(&self) -> HealthState {
+        self.health
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == HealthState::Dead
+    }
+
+    /// When the pet died, only meaningful for dead pets
+    pub fn died_at(&self) -> u64 {
+        self.health_since
+    }
+
+    /// Brings a dead pet back to life, sick and with its stats partly restored
+    pub fn revive(&mut self, now: u64, config: &Config) {
+        let revival_stats = config.graveyard.revival_stats;
+
+        self.stats = PetStats {
+            hunger: revival_stats,
+            happiness: revival_stats,
+            energy: revival_stats,
+            hygiene: revival_stats,
+        };
+        self.stats_updated = now;
+
+        self.set_health(HealthState::Sick, now, config);
+    }
+
+    /// Moves the pet's stat and health clocks forward, so the given time doesn't count
+    /// towards its stats decaying or its health changing
+    pub fn shift_clocks(&mut self, by_ms: u64) {
+        self.stats_updated += by_ms;
+        self.health_since += by_ms;
+    }
+
+    /// Stops the time between from and to counting towards the pet's stats decaying or its
+    /// health changing. Clocks already moved on by care in that time are only moved up to `to`.
+    pub fn pause_clocks(&mut self, from: u64, to: u64) {
+        let pause = |clock: u64| if clock <= from { clock + (to - from) } else { clock.max(to) };
+
+        self.stats_updated = pause(self.stats_updated_at());
+        self.health_since = pause(self.health_since);
+    }
+
+    /// Moves the pet along the health chain until it settles, based on its stats and
+    /// how long it has been in each state
+    pub fn update_health(&mut self, now: u64, config: &Config) {
+        let snapshot = self.stats_snapshot(config);
+        let lifespan_modifier = config.species.traits(&self.species).lifespan_modifier;
+
+        while let Some((next, at)) = health::next_state(
+            self.health,
+            self.health_since,
+            now,
+            &snapshot,
+            lifespan_modifier,
+            &config.health,
+            &config.needs,
+        ) {
+            self.set_health(next, at, config);
+        }
+    }
+
+    fn set_health(&mut self, health: HealthState, at: u64, config: &Config) {
+        self.record_event(
+            at,
+            PetEventKind::HealthChanged {
+                from: self.health,
+                to: health,
+            },
+            config,
+        );
+
+        self.health = health;
+        self.health_since = at;
+    }
+
+    pub fn record_event(&mut self, timestamp: u64, kind: PetEventKind, config: &Config) {
+        self.events.insert(0, PetEvent { timestamp, kind });
+        self.events.truncate(config.health.max_events);
+    }
+
+    /// Adds experience, carrying any left over into the next level, and records each level up
+    pub fn add_experience(&mut self, experience: u64, now: u64, config: &Config) {
+        let (level, experience) =
+            leveling::gain_experience(self.level, self.experience, experience, &config.leveling);
+
+        for reached in (self.level + 1)..=level {
+            self.record_event(now, PetEventKind::LevelUp { level: reached }, config);
+        }
+
+        self.level = level;
+        self.experience = experience;
+
+        self.evolve(now, config);
+    }
+
+    /// Grows the pet into the next forms of its species that it is ready for
+    fn evolve(&mut self, now: u64, config: &Config) {
+        let species = config.species.traits(&self.species);
+
+        // The new form can decay differently, so keep what has happened so far
+        self.settle_stats(now, config);
+
+        while let Some(evolution) = growth::next_evolution(&species.evolutions, self.stage, self.level, self.care) {
+            self.evolutions.push(EvolutionRecord {
+                timestamp: now,
+                from: self.stage,
+                to: evolution.stage,
+                form: evolution.form.clone(),
+                level: self.level,
+            });
+            self.record_event(
+                now,
+                PetEventKind::Evolved {
+                    stage: evolution.stage,
+                    form: evolution.form.clone(),
+                },
+                config,
+            );
+
+            self.stage = evolution.stage;
+            self.form = Some(evolution.form.clone());
+
+            // Switch to a picture that suits the new form
+            if !evolution.allows_image(self.image) {
+                self.image = evolution.images[0];
+            }
+        }
+    }
+
+    pub fn set_genes(&mut self, genes: Genes) {
+        self.genes = genes;
+    }
+
+    pub fn set_last_bred(&mut self, now: u64) {
+        self.last_bred = now;
+    }
+
+    pub fn get_parents(&self) -> Vec<String> {
+        self.parents.clone()
+    }
+
+    /// Checks the pet is old enough, alive, and rested enough to breed
+    pub fn check_can_breed(&self, now: u64, config: &Config) -> Result<(), BreedError> {
+        if self.is_dead() {
+            return Err(BreedError::Dead);
+        }
+
+        if self.stage != GrowthStage::Adult {
+            return Err(BreedError::NotAdult);
+        }
+
+        if self.last_bred > 0 && self.last_bred + config.breeding.cooldown_ms() > now {
+            return Err(BreedError::Cooldown {
+                available_at: self.last_bred + config.breeding.cooldown_ms(),
+            });
         }
+
+        Ok(())
+    }
+
+    /// Makes an egg with genes from both parents
+    pub fn breed_with(&self, partner: &Pet, name: Option<String>, now: u64, config: &Config) -> Pet {
+        let mut rng = rand::thread_rng();
+
+        let name = name.unwrap_or_else(|| format!("{} and {}'s egg", self.name, partner.name));
+        let image = if rng.gen_bool(0.5) { self.image } else { partner.image };
+
+        let mut egg = Pet::new(name, self.species.clone(), image, None, now);
+        egg.genes = Genes::inherit(&self.genes, &partner.genes, &config.breeding, &mut rng);
+        egg.parents = vec![self.get_uuid(), partner.get_uuid()];
+        egg.set_stats(config.species.traits(&self.species).base_stats, now);
+
+        egg
+    }
+
+    pub fn get_stage(&self) -> GrowthStage {
+        self.stage
+    }
+
+    pub fn get_form(&self) -> Option<String> {
+        self.form.clone()
+    }
+
+    /// Replaces the pet's stats, such as with its species' starting stats
+    pub fn set_stats(&mut self, stats: PetStats, now: u64) {
+        self.stats = stats;
+        self.stats_updated = now;
     }
 
     pub fn get_image(&self) -> u64 {
         self.image
     }
 
+    pub fn mood(&self, now: u64, config: &Config) -> Mood {
+        Mood::of(self.health, &self.current_stats(now, config))
+    }
+
+    /// Draws the pet's picture in its current mood, if there is art for it
+    pub fn render_art(&self, now: u64, config: &Config) -> Option<String> {
+        let mood = self.mood(now, config);
+
+        if self.stage == GrowthStage::Egg {
+            return Some(art::render_egg(mood));
+        }
+
+        config.art.render(&self.species, self.image, mood)
+    }
+
     pub fn set_image(&mut self, image: u64) {
         self.image = image;
     }
 
-    pub fn should_die(&self) -> bool {
-        let now = chrono::Utc::now().timestamp_millis() as u64;
-        // If the pet has not been fed or petted in 3 days, it dies
-        self.last_fed + 1000 * 60 * 60 * 24 * 3 < now || self.last_pet + 1000 * 60 * 60 * 24 * 7 < now
+    pub fn for_user(&self, now: u64, config: &Config) -> String {
+        // Show the pet as it is right now, rather than as of the last action
+        let mut current = self.clone();
+        current.update_health(now, config);
+
+        let mut pet = serde_json::to_value(&current).unwrap();
+        pet["stats"] = serde_json::json!(current.current_stats(now, config));
+        pet["experience_to_next_level"] = serde_json::json!(config.leveling.experience_to_next(self.level));
+        pet["cooldowns"] = cooldowns::summary(&current.actions, &config.cooldowns, now);
+        pet["mood"] = serde_json::json!(current.mood(now, config));
+        pet["art"] = serde_json::json!(current.render_art(now, config));
+        pet.as_object_mut().unwrap().remove("actions");
+
+        pet.to_string()
     }
 
-    pub fn for_public(&self) -> String {
+    pub fn for_public(&self, now: u64, config: &Config) -> String {
+        let mut current = self.clone();
+        current.update_health(now, config);
+
         serde_json::json!({
             "uuid": self.uuid,
             "name": self.name,
@@ -468,7 +2893,19 @@ impl Pet {
             "species": self.species,
             "level": self.level,
             "experience": self.experience,
+            "stage": self.stage,
+            "form": self.form,
+            "color": self.genes.color,
+            "parents": self.parents,
+            "born": self.born,
+            "accessory": self.accessory,
+            "contests": self.contests,
+            "experience_to_next_level": config.leveling.experience_to_next(self.level),
             "in_pet_yard": self.pet_yard.is_some(),
+            "stats": current.current_stats(now, config),
+            "health": current.health,
+            "mood": current.mood(now, config),
+            "art": current.render_art(now, config),
         }).to_string()
     }
 }
@@ -485,6 +2922,9 @@ pub struct PetYard {
     members: Vec<String>,
     // UUIDs of the pets in the pet yard
     pets: Vec<String>,
+    // How many times pets in the pet yard have been cared for, played with or contested
+    #[serde(default)]
+    activity: u64,
 }
 
 impl PetYard {
@@ -496,6 +2936,7 @@ impl PetYard {
             owner: owner_uuid,
             members: vec![],
             pets: vec![],
+            activity: 0,
         }
     }
 
@@ -0,0 +1,138 @@
+/*
+
+This file handles exporting tracing spans to an OpenTelemetry collector over OTLP,
+and picking up W3C trace context from incoming requests.
+
+*/
+
+use axum::http::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracerProvider, SpanExporter};
+use opentelemetry_sdk::Resource;
+use std::error::Error;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::Layer;
+
+use crate::config::TelemetryConfig;
+use crate::logging::BoxedLayer;
+
+/// Builds the OTLP exporter if it is enabled. The returned provider must be shut down on exit
+/// so spans still waiting in the batch are sent.
+pub fn init(
+    config: &TelemetryConfig,
+) -> Result<Option<(SdkTracerProvider, BoxedLayer)>, Box<dyn Error>> {
+    if !config.otlp_enabled {
+        return Ok(None);
+    }
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(config.otlp_endpoint.clone())
+        .build()?;
+
+    let provider = provider_with_exporter(exporter, &config.service_name, true);
+    let layer = layer(&provider);
+
+    Ok(Some((provider, layer)))
+}
+
+/// Creates a tracer provider sending spans to the given exporter.
+/// Batching is turned off in tests so spans are exported as soon as they end.
+pub fn provider_with_exporter<E: SpanExporter + 'static>(
+    exporter: E,
+    service_name: &str,
+    batch: bool,
+) -> SdkTracerProvider {
+    // Read the W3C traceparent header from incoming requests
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let resource = Resource::builder()
+        .with_service_name(service_name.to_string())
+        .build();
+
+    let builder = SdkTracerProvider::builder().with_resource(resource);
+
+    if batch {
+        builder.with_batch_exporter(exporter).build()
+    } else {
+        builder.with_simple_exporter(exporter).build()
+    }
+}
+
+/// Creates a tracing layer that turns spans into OpenTelemetry spans
+pub fn layer(provider: &SdkTracerProvider) -> BoxedLayer {
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("svp-backend"))
+        .boxed()
+}
+
+/// Makes the span a child of the trace in the request's `traceparent` header, if there is one
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+
+    // Fails only when no OpenTelemetry layer is installed, which is fine
+    let _ = span.set_parent(parent);
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry_sdk::trace::InMemorySpanExporter;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_spans_are_exported_with_remote_parent() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = provider_with_exporter(exporter.clone(), "svp-test", false);
+        let subscriber = tracing_subscriber::registry().with(vec![layer(&provider)]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".parse().unwrap(),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = tracing::info_span!("request");
+            set_parent_from_headers(&request, &headers);
+
+            let _entered = request.enter();
+            tracing::info_span!("state.lock").in_scope(|| {});
+        });
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+
+        assert_eq!(spans.len(), 2);
+
+        let request = spans.iter().find(|span| span.name == "request").unwrap();
+        let lock = spans.iter().find(|span| span.name == "state.lock").unwrap();
+
+        // The request continues the remote trace, and the lock span is its child
+        assert_eq!(
+            request.span_context.trace_id().to_string(),
+            "4bf92f3577b34da6a3ce929d0e0e4736"
+        );
+        assert_eq!(request.parent_span_id.to_string(), "00f067aa0ba902b7");
+        assert_eq!(lock.parent_span_id, request.span_context.span_id());
+        assert_eq!(lock.span_context.trace_id(), request.span_context.trace_id());
+    }
+}
@@ -0,0 +1,294 @@
+/*
+
+This file handles trades between users.
+
+A user offers some coins, items and pets in exchange for some of another user's.
+The other user can accept, decline or make a counter offer, which replaces the original.
+Trades only go through if both users still have everything when it is accepted.
+
+*/
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const MS_PER_HOUR: u64 = 1000 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TradeConfig {
+    // How long a trade offer waits for an answer
+    pub offer_expiry_hours: u64,
+    // Most trade offers a user can have waiting on others at once
+    pub max_open_offers: usize,
+}
+
+impl Default for TradeConfig {
+    fn default() -> Self {
+        Self {
+            offer_expiry_hours: 48,
+            max_open_offers: 20,
+        }
+    }
+}
+
+impl TradeConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.offer_expiry_hours == 0 {
+            return Err("trading.offer_expiry_hours must be greater than 0".to_string());
+        }
+
+        if self.max_open_offers == 0 {
+            return Err("trading.max_open_offers must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// One side of a trade
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct TradeBundle {
+    pub coins: u64,
+    // Item counts, by item id
+    pub items: BTreeMap<String, u32>,
+    // Pet UUIDs
+    pub pets: Vec<String>,
+}
+
+impl TradeBundle {
+    pub fn is_empty(&self) -> bool {
+        self.coins == 0 && self.items.values().all(|count| *count == 0) && self.pets.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeOffer {
+    pub uuid: String,
+    pub from_user: String,
+    pub to_user: String,
+    // What from_user gives
+    pub offered: TradeBundle,
+    // What from_user wants from to_user
+    pub requested: TradeBundle,
+    // The offer this one counters, if any
+    pub counter_of: Option<String>,
+    pub created: u64,
+    pub expires: u64,
+}
+
+impl TradeOffer {
+    pub fn new(
+        from_user: String,
+        to_user: String,
+        offered: TradeBundle,
+        requested: TradeBundle,
+        counter_of: Option<String>,
+        now: u64,
+        config: &TradeConfig,
+    ) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            from_user,
+            to_user,
+            offered,
+            requested,
+            counter_of,
+            created: now,
+            expires: now + config.offer_expiry_hours * MS_PER_HOUR,
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.expires
+    }
+
+    pub fn involves(&self, user_uuid: &str) -> bool {
+        self.from_user == user_uuid || self.to_user == user_uuid
+    }
+
+    pub fn for_user(&self) -> serde_json::Value {
+        serde_json::json!(self)
+    }
+}
+
+/// Why a trade couldn't be made or carried out
+#[derive(Debug, Clone, PartialEq)]
+pub enum TradeError {
+    NotFound,
+    SelfTrade,
+    EmptyTrade,
+    TooManyOffers,
+    UnknownItem(String),
+    // A user no longer has what their side of the trade needs
+    Missing { user: String },
+    PetNotTradable(String),
+    Expired,
+}
+
+impl std::fmt::Display for TradeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TradeError::NotFound => write!(f, "User or trade offer not found"),
+            TradeError::SelfTrade => write!(f, "Can't trade with yourself"),
+            TradeError::EmptyTrade => write!(f, "A trade has to include something"),
+            TradeError::TooManyOffers => write!(f, "Too many open trade offers"),
+            TradeError::UnknownItem(item) => write!(f, "Unknown item {}", item),
+            TradeError::Missing { user } => write!(f, "User {} doesn't have everything in the trade", user),
+            TradeError::PetNotTradable(pet) => write!(f, "Pet {} can't be traded", pet),
+            TradeError::Expired => write!(f, "Trade offer has expired"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::config::Config;
+    use crate::economy::LedgerReason;
+    use crate::simulation::{add_user_with_pet, mock_state, MS_PER_HOUR};
+    use crate::structs::AppState;
+    use std::sync::Arc;
+
+    // A user's UUID and their cat's
+    type UserAndPet = (String, String);
+
+    /// Two users with a cat each, some coins and some kibble
+    fn setup() -> (AppState, Arc<MockClock>, UserAndPet, UserAndPet) {
+        let (mut state, clock) = mock_state(Config::default());
+
+        let alice = add_user_with_pet(&mut state, "alice");
+        let bob = add_user_with_pet(&mut state, "bob");
+
+        for user_uuid in [&alice.0, &bob.0] {
+            state.credit_coins(user_uuid, 100, LedgerReason::StartingCoins);
+
+            let mut user = state.get_user_by_uuid(user_uuid).unwrap().clone();
+            user.add_items("kibble".to_string(), 5);
+            state.update_user(user);
+        }
+
+        (state, clock, alice, bob)
+    }
+
+    fn bundle(coins: u64, kibble: u32, pets: &[&String]) -> TradeBundle {
+        TradeBundle {
+            coins,
+            items: [("kibble".to_string(), kibble)].into_iter().filter(|(_, count)| *count > 0).collect(),
+            pets: pets.iter().map(|pet| pet.to_string()).collect(),
+        }
+    }
+
+    /// Coins and kibble, in that order. Achievements for trading pay out too, so those
+    /// rewards are left out of the coins.
+    fn holdings(state: &AppState, user_uuid: &str) -> (u64, u32) {
+        let user = state.get_user_by_uuid(user_uuid).unwrap();
+        let rewards: i64 = state
+            .get_ledger(user_uuid)
+            .iter()
+            .filter(|entry| matches!(entry.reason, LedgerReason::Achievement { .. } | LedgerReason::Quest { .. }))
+            .map(|entry| entry.amount)
+            .sum();
+
+        (user.get_coins() - rewards as u64, user.item_count("kibble"))
+    }
+
+    fn owner(state: &AppState, pet_uuid: &str) -> String {
+        state.get_pet_owner(pet_uuid).unwrap()
+    }
+
+    #[test]
+    fn test_empty_bundles() {
+        assert!(TradeBundle::default().is_empty());
+
+        let zero_items = TradeBundle {
+            items: [("kibble".to_string(), 0)].into_iter().collect(),
+            ..TradeBundle::default()
+        };
+        assert!(zero_items.is_empty());
+
+        let coins = TradeBundle { coins: 5, ..TradeBundle::default() };
+        assert!(!coins.is_empty());
+    }
+
+    #[test]
+    fn test_accepted_trades_swap_both_sides() {
+        let (mut state, _, (alice, alice_cat), (bob, bob_cat)) = setup();
+
+        let offer = state.propose_trade(&alice, &bob, bundle(30, 2, &[&alice_cat]), bundle(10, 1, &[&bob_cat])).unwrap();
+        state.accept_trade(&bob, &offer.uuid).unwrap();
+
+        assert_eq!(holdings(&state, &alice), (80, 4));
+        assert_eq!(holdings(&state, &bob), (120, 6));
+        assert_eq!(owner(&state, &alice_cat), bob);
+        assert_eq!(owner(&state, &bob_cat), alice);
+        assert!(state.trade_offers.is_empty());
+        assert!(state.audit_coins().is_ok());
+    }
+
+    #[test]
+    fn test_counter_offers_replace_the_original() {
+        let (mut state, _, (alice, alice_cat), (bob, _)) = setup();
+
+        let offer = state.propose_trade(&alice, &bob, bundle(0, 0, &[&alice_cat]), bundle(50, 0, &[])).unwrap();
+        let counter = state.counter_trade(&bob, &offer.uuid, bundle(20, 0, &[]), bundle(0, 0, &[&alice_cat])).unwrap();
+
+        assert_eq!(counter.counter_of, Some(offer.uuid.clone()));
+        assert_eq!(state.trade_offers.keys().collect::<Vec<_>>(), vec![&counter.uuid]);
+        assert_eq!(state.accept_trade(&bob, &offer.uuid).unwrap_err(), TradeError::NotFound);
+
+        // Only the user it was sent to can answer the counter offer
+        assert_eq!(state.accept_trade(&bob, &counter.uuid).unwrap_err(), TradeError::NotFound);
+        state.accept_trade(&alice, &counter.uuid).unwrap();
+        assert_eq!(holdings(&state, &alice), (120, 5));
+        assert_eq!(holdings(&state, &bob), (80, 5));
+        assert_eq!(owner(&state, &alice_cat), bob);
+    }
+
+    #[test]
+    fn test_trades_fail_as_a_whole_when_a_side_is_missing() {
+        let (mut state, _, (alice, alice_cat), (bob, bob_cat)) = setup();
+
+        let offer = state.propose_trade(&alice, &bob, bundle(30, 2, &[&alice_cat]), bundle(10, 1, &[&bob_cat])).unwrap();
+
+        // Bob spends his kibble before accepting
+        let mut user = state.get_user_by_uuid(&bob).unwrap().clone();
+        user.take_items("kibble", 5);
+        state.update_user(user);
+
+        let (alice_before, bob_before) = (holdings(&state, &alice), holdings(&state, &bob));
+        let ledger_before = state.ledger.len();
+
+        assert_eq!(state.accept_trade(&bob, &offer.uuid).unwrap_err(), TradeError::Missing { user: bob.clone() });
+        assert_eq!(holdings(&state, &alice), alice_before);
+        assert_eq!(holdings(&state, &bob), bob_before);
+        assert_eq!(owner(&state, &alice_cat), alice);
+        assert_eq!(owner(&state, &bob_cat), bob);
+        assert_eq!(state.ledger.len(), ledger_before);
+        assert!(state.transfer_log.is_empty());
+
+        // The offer can't go through any more, so it's dropped
+        assert!(state.trade_offers.is_empty());
+    }
+
+    #[test]
+    fn test_expired_offers_are_rejected() {
+        let (mut state, clock, (alice, _), (bob, _)) = setup();
+
+        let offer = state.propose_trade(&alice, &bob, bundle(10, 0, &[]), bundle(0, 1, &[])).unwrap();
+        clock.advance(state.config.trading.offer_expiry_hours * MS_PER_HOUR + 1);
+
+        assert!(state.get_trade_offers(&bob).is_empty());
+        assert_eq!(state.accept_trade(&bob, &offer.uuid).unwrap_err(), TradeError::Expired);
+        assert_eq!(holdings(&state, &alice).0, 100);
+
+        // Expired offers are cleared out by the periodic job too
+        let offer = state.propose_trade(&alice, &bob, bundle(10, 0, &[]), bundle(0, 1, &[])).unwrap();
+        clock.advance(state.config.trading.offer_expiry_hours * MS_PER_HOUR + 1);
+        state.expire_trade_offers();
+        assert!(!state.trade_offers.contains_key(&offer.uuid));
+    }
+}
@@ -0,0 +1,272 @@
+/*
+
+This file handles pets changing owners.
+
+A user can offer a pet to another user, who can accept or decline it until the offer expires.
+Users can also surrender pets to the adoption center, where anyone can adopt them.
+Every change is recorded in an audit trail.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const MS_PER_HOUR: u64 = 1000 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransferConfig {
+    // How long an offer waits for the other user to answer
+    pub offer_expiry_hours: u64,
+    // Number of audit records kept, oldest are dropped first
+    pub max_audit_records: usize,
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        Self {
+            offer_expiry_hours: 48,
+            max_audit_records: 10_000,
+        }
+    }
+}
+
+impl TransferConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.offer_expiry_hours == 0 {
+            return Err("transfers.offer_expiry_hours must be greater than 0".to_string());
+        }
+
+        if self.max_audit_records == 0 {
+            return Err("transfers.max_audit_records must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// An offer to give a pet to another user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferOffer {
+    pub uuid: String,
+    pub pet: String,
+    pub from_user: String,
+    pub to_user: String,
+    pub created: u64,
+    pub expires: u64,
+}
+
+impl TransferOffer {
+    pub fn new(pet: String, from_user: String, to_user: String, now: u64, config: &TransferConfig) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            pet,
+            from_user,
+            to_user,
+            created: now,
+            expires: now + config.offer_expiry_hours * MS_PER_HOUR,
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.expires
+    }
+
+    pub fn for_user(&self) -> serde_json::Value {
+        serde_json::json!(self)
+    }
+}
+
+/// A pet waiting in the adoption center
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AdoptionListing {
+    pub pet: String,
+    pub surrendered_by: String,
+    pub listed_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferEvent {
+    Offered,
+    Accepted,
+    Declined,
+    Cancelled,
+    Expired,
+    Surrendered,
+    Adopted,
+    // Changed hands as part of a trade
+    Traded,
+}
+
+/// An entry in the audit trail. Users are None when the pet is in the adoption center.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferRecord {
+    pub timestamp: u64,
+    pub pet: String,
+    pub from_user: Option<String>,
+    pub to_user: Option<String>,
+    pub event: TransferEvent,
+}
+
+impl TransferRecord {
+    pub fn involves(&self, user_uuid: &str) -> bool {
+        self.from_user.as_deref() == Some(user_uuid) || self.to_user.as_deref() == Some(user_uuid)
+    }
+}
+
+/// Why a pet couldn't change owners
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferError {
+    NotFound,
+    NotOwner,
+    SelfTransfer,
+    Dead,
+    AlreadyOffered,
+    Expired,
+}
+
+impl std::fmt::Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferError::NotFound => write!(f, "Pet, user or offer not found"),
+            TransferError::NotOwner => write!(f, "Pet belongs to another user"),
+            TransferError::SelfTransfer => write!(f, "Can't give a pet to yourself"),
+            TransferError::Dead => write!(f, "Pet has died"),
+            TransferError::AlreadyOffered => write!(f, "Pet has already been offered to someone"),
+            TransferError::Expired => write!(f, "Offer has expired"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::simulation::{add_user_with_pet, mock_state, MS_PER_DAY};
+    use crate::structs::AppState;
+
+    /// The pet's audit trail, oldest first
+    fn trail(state: &AppState, pet_uuid: &str) -> Vec<(Option<String>, Option<String>, TransferEvent)> {
+        state
+            .transfer_log
+            .iter()
+            .filter(|record| record.pet == pet_uuid)
+            .map(|record| (record.from_user.clone(), record.to_user.clone(), record.event))
+            .collect()
+    }
+
+    #[test]
+    fn test_offers_expire() {
+        let config = TransferConfig {
+            offer_expiry_hours: 2,
+            ..TransferConfig::default()
+        };
+        let offer = TransferOffer::new("pet".to_string(), "alice".to_string(), "bob".to_string(), 1000, &config);
+
+        assert_eq!(offer.expires, 1000 + 2 * MS_PER_HOUR);
+        assert!(!offer.is_expired(offer.expires));
+        assert!(offer.is_expired(offer.expires + 1));
+
+        let record = TransferRecord {
+            timestamp: 1000,
+            pet: "pet".to_string(),
+            from_user: Some("alice".to_string()),
+            to_user: None,
+            event: TransferEvent::Surrendered,
+        };
+        assert!(record.involves("alice"));
+        assert!(!record.involves("bob"));
+    }
+
+    #[test]
+    fn test_accepted_offers_change_the_owner() {
+        let (mut state, _) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+
+        let offer = state.offer_pet_transfer(&alice, &cat, &bob).unwrap();
+        assert_eq!(state.offer_pet_transfer(&alice, &cat, &bob).unwrap_err(), TransferError::AlreadyOffered);
+
+        // Only the user it was offered to can take it
+        assert_eq!(state.accept_pet_transfer(&alice, &offer.uuid).unwrap_err(), TransferError::NotFound);
+        state.accept_pet_transfer(&bob, &offer.uuid).unwrap();
+
+        assert_eq!(state.get_pet_owner(&cat), Some(bob.clone()));
+        assert!(state.transfer_offers.is_empty());
+        assert_eq!(
+            trail(&state, &cat),
+            vec![
+                (Some(alice.clone()), Some(bob.clone()), TransferEvent::Offered),
+                (Some(alice.clone()), Some(bob.clone()), TransferEvent::Accepted),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_declined_and_cancelled_offers_keep_the_owner() {
+        let (mut state, _) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+
+        let offer = state.offer_pet_transfer(&alice, &cat, &bob).unwrap();
+        state.decline_pet_transfer(&bob, &offer.uuid).unwrap();
+
+        let offer = state.offer_pet_transfer(&alice, &cat, &bob).unwrap();
+        state.decline_pet_transfer(&alice, &offer.uuid).unwrap();
+        assert_eq!(state.accept_pet_transfer(&bob, &offer.uuid).unwrap_err(), TransferError::NotFound);
+
+        assert_eq!(state.get_pet_owner(&cat), Some(alice.clone()));
+        assert_eq!(
+            trail(&state, &cat).into_iter().map(|(_, _, event)| event).collect::<Vec<_>>(),
+            vec![TransferEvent::Offered, TransferEvent::Declined, TransferEvent::Offered, TransferEvent::Cancelled]
+        );
+    }
+
+    #[test]
+    fn test_surrendered_pets_can_be_adopted() {
+        let (mut state, clock) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+
+        state.surrender_pet(&alice, &cat).unwrap();
+        assert_eq!(state.get_pet_owner(&cat), None);
+        assert_eq!(state.get_adoption_listings().len(), 1);
+
+        // Time in the adoption center doesn't count against the pet
+        clock.advance(10 * MS_PER_DAY);
+        let pet = state.adopt_pet(&bob, &cat).unwrap();
+        assert!(!pet.is_dead());
+        assert_eq!(state.get_pet_owner(&cat), Some(bob.clone()));
+        assert!(state.get_adoption_listings().is_empty());
+        assert_eq!(state.adopt_pet(&alice, &cat).unwrap_err(), TransferError::NotFound);
+
+        assert_eq!(
+            trail(&state, &cat),
+            vec![
+                (Some(alice.clone()), None, TransferEvent::Surrendered),
+                (None, Some(bob.clone()), TransferEvent::Adopted),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dead_pets_cant_be_given_away() {
+        let mut config = Config::default();
+        config.transfers.offer_expiry_hours = 7 * 24;
+        let (mut state, clock) = mock_state(config);
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+
+        let offer = state.offer_pet_transfer(&alice, &cat, &bob).unwrap();
+
+        // Starved before the kill check got to it, so it's still in the state
+        clock.advance(4 * MS_PER_DAY);
+        assert_eq!(state.accept_pet_transfer(&bob, &offer.uuid).unwrap_err(), TransferError::Dead);
+        assert_eq!(state.offer_pet_transfer(&alice, &cat, &bob).unwrap_err(), TransferError::Dead);
+        assert_eq!(state.surrender_pet(&alice, &cat).unwrap_err(), TransferError::Dead);
+
+        assert_eq!(state.get_pet_owner(&cat), Some(alice.clone()));
+        assert_eq!(trail(&state, &cat).len(), 1);
+    }
+}
@@ -1,3 +1,6 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
 
 
 /// Creates a self-signed certificate, and returns the certificate and key as strings
@@ -7,4 +10,18 @@ pub fn create_cert() -> Result<(String, String), Box<dyn std::error::Error>> {
     let key = certificate.serialize_private_key_pem();
 
     Ok((cert, key))
-}
\ No newline at end of file
+}
+
+/// Writes the contents to a temporary file next to the path, then renames it over the path.
+/// A crash mid-write leaves the previous file intact instead of a half written one.
+pub fn write_file_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    std::fs::rename(&tmp_path, path)
+}
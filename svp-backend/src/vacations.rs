@@ -0,0 +1,232 @@
+/*
+
+This file handles vacations and pet-sitters.
+
+A user going away can start a vacation, which stops their pets' stats decaying and their health
+changing until they come back or the vacation runs out. Vacations have a maximum length and
+need a break between them, so pets can't be put on hold forever.
+
+Users can also trust another user, like a yard-mate, to look after some of their pets for a while.
+The sitter can feed and pet those pets until the sitting runs out or either of them ends it.
+
+*/
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+const MS_PER_DAY: u64 = 1000 * 60 * 60 * 24;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VacationConfig {
+    pub max_days: u64,
+    // Days after a vacation ends before the next one can start
+    pub days_between: u64,
+    pub max_sitting_days: u64,
+    // Pet-sittings a user can have going at once
+    pub max_sittings: usize,
+}
+
+impl Default for VacationConfig {
+    fn default() -> Self {
+        Self {
+            max_days: 14,
+            days_between: 7,
+            max_sitting_days: 14,
+            max_sittings: 5,
+        }
+    }
+}
+
+impl VacationConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_days == 0 || self.max_sitting_days == 0 {
+            return Err("vacations.max_days and max_sitting_days must be greater than 0".to_string());
+        }
+
+        if self.max_sittings == 0 {
+            return Err("vacations.max_sittings must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// A user's vacation, while their pets are on hold
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vacation {
+    pub started: u64,
+    pub ends: u64,
+    // The pets' clocks have been held up to here
+    pub paused_until: u64,
+}
+
+impl Vacation {
+    pub fn new(now: u64, days: u64) -> Self {
+        Self {
+            started: now,
+            ends: now + days * MS_PER_DAY,
+            paused_until: now,
+        }
+    }
+
+    pub fn is_over(&self, now: u64) -> bool {
+        now >= self.ends
+    }
+
+    /// The time since the pets were last held, up to now or the end of the vacation,
+    /// which is then counted as held
+    pub fn pause_until(&mut self, now: u64) -> Option<(u64, u64)> {
+        let from = self.paused_until;
+        let to = now.min(self.ends);
+
+        if to <= from {
+            return None;
+        }
+
+        self.paused_until = to;
+        Some((from, to))
+    }
+}
+
+/// When a user can go on vacation again after their last one ended
+pub fn next_vacation_at(last_ended: Option<u64>, config: &VacationConfig) -> u64 {
+    last_ended.map_or(0, |ended| ended + config.days_between * MS_PER_DAY)
+}
+
+/// Another user trusted to feed and pet some of a user's pets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PetSitting {
+    pub uuid: String,
+    pub owner: String,
+    pub sitter: String,
+    pub pets: Vec<String>,
+    pub created: u64,
+    pub expires: u64,
+}
+
+impl PetSitting {
+    pub fn new(owner: String, sitter: String, pets: Vec<String>, now: u64, days: u64) -> Self {
+        Self {
+            uuid: Uuid::new_v4().to_string(),
+            owner,
+            sitter,
+            pets,
+            created: now,
+            expires: now + days * MS_PER_DAY,
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now > self.expires
+    }
+
+    pub fn involves(&self, user_uuid: &str) -> bool {
+        self.owner == user_uuid || self.sitter == user_uuid
+    }
+
+    /// Whether the user can look after the pet through this sitting
+    pub fn covers(&self, user_uuid: &str, pet_uuid: &str, now: u64) -> bool {
+        self.sitter == user_uuid && !self.is_expired(now) && self.pets.iter().any(|pet| pet == pet_uuid)
+    }
+
+    pub fn for_user(&self) -> serde_json::Value {
+        serde_json::json!(self)
+    }
+}
+
+/// Why a vacation or pet-sitting couldn't be started or ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum VacationError {
+    UserNotFound,
+    PetNotFound,
+    SittingNotFound,
+    // Too short or too long
+    InvalidLength,
+    AlreadyAway,
+    NotAway,
+    // The last vacation ended too recently
+    TooSoon { available_at: u64 },
+    NotOwner,
+    SameUser,
+    NoPets,
+    TooManySittings,
+}
+
+impl std::fmt::Display for VacationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VacationError::UserNotFound => write!(f, "User not found"),
+            VacationError::PetNotFound => write!(f, "Pet not found"),
+            VacationError::SittingNotFound => write!(f, "Pet-sitting not found"),
+            VacationError::InvalidLength => write!(f, "That's too short or too long"),
+            VacationError::AlreadyAway => write!(f, "Already on vacation"),
+            VacationError::NotAway => write!(f, "Not on vacation"),
+            VacationError::TooSoon { .. } => write!(f, "The last vacation ended too recently"),
+            VacationError::NotOwner => write!(f, "Pet belongs to another user"),
+            VacationError::SameUser => write!(f, "Users can't sit their own pets"),
+            VacationError::NoPets => write!(f, "Choose at least one pet to be looked after"),
+            VacationError::TooManySittings => write!(f, "Too many pet-sitters already"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::simulation::{add_user_with_pet, mock_state};
+    use crate::structs::PetYard;
+
+    #[test]
+    fn test_vacations_hold_pets_until_they_end() {
+        let mut vacation = Vacation::new(1000, 2);
+
+        assert_eq!(vacation.pause_until(5000), Some((1000, 5000)));
+        assert_eq!(vacation.pause_until(5000), None);
+
+        // Time after the vacation ends isn't held
+        assert!(!vacation.is_over(MS_PER_DAY));
+        assert_eq!(vacation.pause_until(3 * MS_PER_DAY), Some((5000, 1000 + 2 * MS_PER_DAY)));
+        assert!(vacation.is_over(3 * MS_PER_DAY));
+
+        let config = VacationConfig::default();
+        assert_eq!(next_vacation_at(None, &config), 0);
+        assert_eq!(next_vacation_at(Some(MS_PER_DAY), &config), 8 * MS_PER_DAY);
+
+        let sitting = PetSitting::new("owner".to_string(), "sitter".to_string(), vec!["rex".to_string()], 0, 1);
+        assert!(sitting.covers("sitter", "rex", MS_PER_DAY));
+        assert!(!sitting.covers("owner", "rex", 0));
+        assert!(!sitting.covers("sitter", "tom", 0));
+        assert!(!sitting.covers("sitter", "rex", MS_PER_DAY + 1));
+    }
+
+    #[test]
+    fn test_only_owners_yard_mates_and_sitters_care_for_pets() {
+        let (mut state, clock) = mock_state(Config::default());
+        let (alice, cat) = add_user_with_pet(&mut state, "alice");
+        let (bob, _) = add_user_with_pet(&mut state, "bob");
+        let (carol, _) = add_user_with_pet(&mut state, "carol");
+        let (dave, _) = add_user_with_pet(&mut state, "dave");
+
+        assert!(state.can_care_for(&alice, &cat));
+        assert!(!state.can_care_for(&bob, &cat));
+        assert!(!state.can_care_for(&carol, &cat));
+        assert!(!state.can_care_for(&dave, &cat));
+
+        let mut pet_yard = PetYard::new("Park".to_string(), alice.clone(), 1);
+        pet_yard.add_member(carol.clone());
+        pet_yard.add_pet(cat.clone());
+        state.update_pet_yard(pet_yard);
+        state.start_pet_sitting(&alice, &dave, vec![cat.clone()], 1).unwrap();
+
+        assert!(state.can_care_for(&carol, &cat));
+        assert!(state.can_care_for(&dave, &cat));
+        assert!(!state.can_care_for(&bob, &cat));
+
+        // Sitters only help out until the sitting ends
+        clock.advance(MS_PER_DAY + 1);
+        assert!(!state.can_care_for(&dave, &cat));
+        assert!(state.can_care_for(&carol, &cat));
+    }
+}